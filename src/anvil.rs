@@ -0,0 +1,180 @@
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::block::BlockType;
+use crate::block_mapping::BlockMapping;
+use crate::chunk::{Chunk, WorldHeight};
+use crate::nbt::{self, Tag};
+use crate::pos::ChunkPos;
+
+// Importer for Minecraft Java Edition region files (r.<x>.<z>.mca, 1.13+ chunk formats)
+
+const SECTOR_SIZE: usize = 4096;
+const REGION_CHUNKS: usize = 32;
+
+// First DataVersion (20w17a) where palette indices no longer span two longs
+const DATA_VERSION_NO_SPANNING: i64 = 2529;
+
+pub struct Region<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Region<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < SECTOR_SIZE * 2 {
+            bail!("region file is too small to contain a header ({} bytes)", bytes.len());
+        }
+        Ok(Self { bytes })
+    }
+
+    // Local chunk coordinates inside the region, 0..32 on both axes
+    pub fn chunk_nbt(&self, x: usize, z: usize) -> Result<Option<Tag>> {
+        let header = 4 * (x % REGION_CHUNKS + (z % REGION_CHUNKS) * REGION_CHUNKS);
+        let location = u32::from_be_bytes(self.bytes[header..header + 4].try_into().unwrap());
+        let offset = (location >> 8) as usize * SECTOR_SIZE;
+        if offset == 0 {
+            return Ok(None);
+        }
+
+        let data = self.bytes.get(offset..offset + 5)
+            .ok_or_else(|| anyhow!("chunk {}, {} points past the end of the region", x, z))?;
+        let length = u32::from_be_bytes(data[0..4].try_into().unwrap()) as usize;
+        let compression = data[4];
+        if length == 0 {
+            return Ok(None);
+        }
+        let payload = self.bytes.get(offset + 5..offset + 4 + length)
+            .ok_or_else(|| anyhow!("chunk {}, {} is truncated", x, z))?;
+
+        // 1 = gzip, 2 = zlib, 3 = uncompressed, nbt::decompress sniffs the header for all three
+        if compression & 0x80 != 0 {
+            bail!("chunk {}, {} is stored in an external .mcc file, which isn't supported", x, z);
+        }
+        if !(1..=3).contains(&compression) {
+            bail!("chunk {}, {} uses unsupported compression type {}", x, z, compression);
+        }
+        let (_, tag) = nbt::read_compressed(payload)?;
+        Ok(Some(tag))
+    }
+}
+
+pub struct ImportedChunk {
+    pub pos: ChunkPos,
+    pub block_types: Vec<Vec<Vec<BlockType>>>,
+}
+
+// Sections outside of `height` are left out
+pub fn import_region(bytes: &[u8], mapping: &BlockMapping, height: WorldHeight) -> Result<Vec<ImportedChunk>> {
+    let region = Region::new(bytes)?;
+    let mut chunks = Vec::new();
+
+    for z in 0..REGION_CHUNKS {
+        for x in 0..REGION_CHUNKS {
+            let Some(tag) = region.chunk_nbt(x, z)? else {
+                continue;
+            };
+            let chunk = import_chunk(&tag, mapping, height)
+                .with_context(|| format!("failed to import chunk {}, {} of region", x, z))?;
+            if let Some(chunk) = chunk {
+                chunks.push(chunk);
+            }
+        }
+    }
+
+    Ok(chunks)
+}
+
+// Returns None for chunks that haven't finished generating in Minecraft
+fn import_chunk(tag: &Tag, mapping: &BlockMapping, height: WorldHeight) -> Result<Option<ImportedChunk>> {
+    let data_version = tag.get("DataVersion").and_then(Tag::as_i64).unwrap_or(0);
+
+    // Before 1.18 everything lived in a "Level" compound with capitalized names
+    let (level, sections_key, palette_key, data_key) = match tag.get("Level") {
+        Some(level) => (level, "Sections", "Palette", "BlockStates"),
+        None => (tag, "sections", "palette", "data"),
+    };
+
+    // Chunks at the edge of the explored area are only partially generated
+    let status = level.get("Status").and_then(Tag::as_str).unwrap_or("full");
+    let status = status.trim_start_matches("minecraft:");
+    if !matches!(status, "full" | "fullchunk" | "postprocessed") {
+        return Ok(None);
+    }
+
+    let pos = ChunkPos::new(
+        level.get("xPos").and_then(Tag::as_i32).ok_or_else(|| anyhow!("missing xPos"))?,
+        level.get("zPos").and_then(Tag::as_i32).ok_or_else(|| anyhow!("missing zPos"))?,
+    );
+
+    let mut block_types = Chunk::empty_blocks(height);
+
+    let sections = level.get(sections_key).and_then(Tag::as_list).unwrap_or(&[]);
+    for section in sections {
+        let Some(section_y) = section.get("Y").and_then(Tag::as_i32) else {
+            continue;
+        };
+        let Some(base_y) = height.index(section_y * 16) else {
+            continue;
+        };
+
+        // 1.18+ nests the palette in "block_states", older versions store it on the section
+        let states = section.get("block_states").unwrap_or(section);
+        let Some(palette) = states.get(palette_key).and_then(Tag::as_list) else {
+            continue;
+        };
+        let palette: Vec<BlockType> = palette.iter()
+            .map(|entry| entry.get("Name").and_then(Tag::as_str).map(|name| mapping.get(name)).unwrap_or(mapping.placeholder))
+            .collect();
+        if palette.is_empty() {
+            continue;
+        }
+
+        let indices = match states.get(data_key).and_then(Tag::as_long_array) {
+            Some(data) => unpack_indices(data, palette.len(), data_version >= DATA_VERSION_NO_SPANNING)?,
+            // A single entry palette has no data array, the whole section is that block
+            None => vec![0; 4096],
+        };
+
+        for (i, &index) in indices.iter().enumerate() {
+            let x = i & 15;
+            let z = (i >> 4) & 15;
+            let y = i >> 8;
+            let block_type = palette.get(index).copied().unwrap_or(mapping.placeholder);
+            block_types[x][base_y + y][z] = block_type;
+        }
+    }
+
+    Ok(Some(ImportedChunk { pos, block_types }))
+}
+
+fn unpack_indices(data: &[i64], palette_len: usize, no_spanning: bool) -> Result<Vec<usize>> {
+    let bits = (usize::BITS - (palette_len - 1).leading_zeros()).max(4) as usize;
+    let mask = (1u64 << bits) - 1;
+    let mut indices = Vec::with_capacity(4096);
+
+    if no_spanning {
+        let per_long = 64 / bits;
+        if data.len() < 4096_usize.div_ceil(per_long) {
+            bail!("block state array is too short ({} longs for {} bits per block)", data.len(), bits);
+        }
+        for i in 0..4096 {
+            let long = data[i / per_long] as u64;
+            indices.push(((long >> ((i % per_long) * bits)) & mask) as usize);
+        }
+    } else {
+        if data.len() * 64 < 4096 * bits {
+            bail!("block state array is too short ({} longs for {} bits per block)", data.len(), bits);
+        }
+        for i in 0..4096 {
+            let bit = i * bits;
+            let word = bit / 64;
+            let offset = bit % 64;
+            let mut value = (data[word] as u64) >> offset;
+            if offset + bits > 64 {
+                value |= (data[word + 1] as u64) << (64 - offset);
+            }
+            indices.push((value & mask) as usize);
+        }
+    }
+
+    Ok(indices)
+}
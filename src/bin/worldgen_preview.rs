@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use bassicraft2::{
+    chunk::WorldHeight,
+    generator::GeneratorSettings,
+    preview::{self, Region},
+    seed,
+    terrain::TerrainSettings,
+    vox::ColorMapping,
+};
+
+// Writes top-down maps of generated terrain as PNGs, without opening a window:
+//   worldgen_preview --seed glacier --generator amplified --center 0,0 --size 512,512 --cave-y -20 --out preview
+
+const USAGE: &str = "\
+usage: worldgen_preview [options]
+    --seed <seed>            A number or any text (default 0)
+    --generator <generator>  default, amplified, flat, void or flat:<layers> (default default)
+    --settings <path>        JSON terrain settings, for the custom generator
+    --center <x>,<z>         Middle of the maps in blocks (default 0,0)
+    --size <width>,<depth>   In blocks, one pixel each (default 256,256)
+    --cave-y <y>             Height of the cave slice (default 0)
+    --out <directory>        Where the PNGs go (default preview)";
+
+struct Options {
+    seed: i64,
+    generator: GeneratorSettings,
+    center: [i32; 2],
+    size: [u32; 2],
+    cave_y: i32,
+    out: PathBuf,
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let options = parse_options(&args).with_context(|| format!("\n{}", USAGE))?;
+
+    let generator = options.generator.build(options.seed);
+    let region = Region::around(options.center, options.size);
+    let maps = preview::render(generator.as_ref(), WorldHeight::default(), region, options.cave_y, &ColorMapping::default());
+
+    std::fs::create_dir_all(&options.out).with_context(|| format!("failed to create {}", options.out.display()))?;
+    let cave_name = format!("caves_y{}.png", options.cave_y);
+    for (name, image) in [("height.png", &maps.height), ("surface.png", &maps.surface), ("biomes.png", &maps.biomes), (cave_name.as_str(), &maps.caves)] {
+        let path = options.out.join(name);
+        image.save(&path).with_context(|| format!("failed to write {}", path.display()))?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn parse_options(args: &[String]) -> Result<Options> {
+    let mut options = Options {
+        seed: 0,
+        generator: GeneratorSettings::Default,
+        center: [0, 0],
+        size: [256, 256],
+        cave_y: 0,
+        out: PathBuf::from("preview"),
+    };
+    let mut args = args.iter();
+    while let Some(name) = args.next() {
+        let value = args.next().ok_or_else(|| anyhow!("{} needs a value", name))?;
+        match name.as_str() {
+            "--seed" => options.seed = seed::parse(value),
+            "--generator" => options.generator = GeneratorSettings::parse(value)?,
+            "--settings" => options.generator = GeneratorSettings::Custom(Box::new(TerrainSettings::load(value.as_ref())?)),
+            "--center" => options.center = pair(value)?,
+            "--size" => {
+                options.size = pair(value)?;
+                if options.size.contains(&0) {
+                    bail!("the size can't be 0");
+                }
+            }
+            "--cave-y" => options.cave_y = value.parse().map_err(|_| anyhow!("invalid height {}", value))?,
+            "--out" => options.out = PathBuf::from(value),
+            _ => bail!("unknown option {}", name),
+        }
+    }
+    Ok(options)
+}
+
+// Two numbers like "12,-40"
+fn pair<T: std::str::FromStr>(value: &str) -> Result<[T; 2]> {
+    let (a, b) = value.split_once(',').ok_or_else(|| anyhow!("expected two numbers like 10,20, got {}", value))?;
+    let number = |text: &str| text.trim().parse().map_err(|_| anyhow!("invalid number {}", text));
+    Ok([number(a)?, number(b)?])
+}
@@ -0,0 +1,288 @@
+use noise::{NoiseFn, OpenSimplex};
+
+use crate::{
+    block::{self, BlockType},
+    feature::Tree,
+    terrain::NOISE_STRETCH,
+};
+
+// Biomes are picked from three slow climate noises like Minecraft's: temperature and
+// humidity choose between the lowland biomes, continentalness sinks oceans where it is low
+// and raises mountains far inland.
+// Each biome has its own height profile, which the generator blends across borders, its
+// own surface blocks, trees and plants, and tints multiplied into the colour of grass and leaves.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Biome {
+    #[default]
+    Plains,
+    Forest,
+    Desert,
+    Savanna,
+    Taiga,
+    SnowyPlains,
+    Swamp,
+    Mountains,
+    Ocean,
+}
+
+pub struct BiomeProperties {
+    pub name: &'static str,
+    // Of the surface, before the terrain noise is added
+    pub base_height: f64,
+    // How far the rolling hills move the surface up or down
+    pub amplitude: f64,
+    // How much of the ridged mountain noise is added, from 0 to 1
+    pub ruggedness: f64,
+    pub surface: BlockType,
+    // Below the surface, stone starts under filler_depth blocks of it
+    pub filler: BlockType,
+    pub filler_depth: i32,
+    // Multiplied into the texture colour, white leaves it as it is
+    pub grass_tint: [f32; 3],
+    pub foliage_tint: [f32; 3],
+    // Plants put on the surface, with the chance of each per column
+    pub vegetation: &'static [(BlockType, f32)],
+    // Also per column, a column with a tree or boulder gets no plant
+    pub trees: &'static [(Tree, f32)],
+    pub boulders: f32,
+}
+
+const PLAINS: BiomeProperties = BiomeProperties {
+    name: "Plains",
+    base_height: 72.0,
+    amplitude: 4.0,
+    ruggedness: 0.0,
+    surface: block::GRASS,
+    filler: block::DIRT,
+    filler_depth: 3,
+    grass_tint: block::NO_TINT,
+    foliage_tint: block::NO_TINT,
+    vegetation: &[(block::TALL_GRASS, 0.1), (block::POPPY, 0.01), (block::DANDELION, 0.01)],
+    trees: &[(Tree::Oak, 0.002)],
+    boulders: 0.0,
+};
+
+const FOREST: BiomeProperties = BiomeProperties {
+    name: "Forest",
+    base_height: 74.0,
+    amplitude: 8.0,
+    grass_tint: [0.85, 0.95, 0.8],
+    foliage_tint: [0.85, 0.95, 0.8],
+    vegetation: &[(block::TALL_GRASS, 0.15), (block::FERN, 0.05), (block::RED_MUSHROOM, 0.005)],
+    trees: &[(Tree::Oak, 0.025), (Tree::Birch, 0.01)],
+    ..PLAINS
+};
+
+const DESERT: BiomeProperties = BiomeProperties {
+    name: "Desert",
+    base_height: 70.0,
+    amplitude: 5.0,
+    ruggedness: 0.0,
+    surface: block::SAND,
+    filler: block::SANDSTONE,
+    filler_depth: 5,
+    grass_tint: [1.2, 1.1, 0.6],
+    foliage_tint: [1.2, 1.1, 0.6],
+    vegetation: &[(block::DEAD_BUSH, 0.01), (block::CACTUS, 0.005)],
+    trees: &[],
+    boulders: 0.0,
+};
+
+const SAVANNA: BiomeProperties = BiomeProperties {
+    name: "Savanna",
+    base_height: 74.0,
+    amplitude: 6.0,
+    grass_tint: [1.15, 1.05, 0.6],
+    foliage_tint: [1.1, 1.0, 0.6],
+    vegetation: &[(block::TALL_GRASS, 0.2), (block::DEAD_BUSH, 0.005)],
+    trees: &[(Tree::Acacia, 0.004)],
+    ..PLAINS
+};
+
+const TAIGA: BiomeProperties = BiomeProperties {
+    name: "Taiga",
+    base_height: 76.0,
+    amplitude: 10.0,
+    ruggedness: 0.15,
+    grass_tint: [0.75, 0.95, 0.85],
+    foliage_tint: [0.7, 0.9, 0.8],
+    vegetation: &[(block::FERN, 0.1), (block::TALL_GRASS, 0.05), (block::BROWN_MUSHROOM, 0.005)],
+    trees: &[(Tree::Spruce, 0.03)],
+    boulders: 0.002,
+    ..PLAINS
+};
+
+const SNOWY_PLAINS: BiomeProperties = BiomeProperties {
+    name: "Snowy Plains",
+    surface: block::SNOW,
+    grass_tint: [0.8, 0.95, 0.95],
+    foliage_tint: [0.8, 0.95, 0.95],
+    vegetation: &[],
+    trees: &[(Tree::Spruce, 0.002)],
+    ..PLAINS
+};
+
+const SWAMP: BiomeProperties = BiomeProperties {
+    name: "Swamp",
+    base_height: 66.0,
+    amplitude: 2.0,
+    filler: block::CLAY,
+    grass_tint: [0.6, 0.7, 0.45],
+    foliage_tint: [0.6, 0.7, 0.45],
+    vegetation: &[(block::TALL_GRASS, 0.05), (block::BROWN_MUSHROOM, 0.02), (block::SUGAR_CANE, 0.01)],
+    trees: &[(Tree::Oak, 0.01)],
+    ..PLAINS
+};
+
+const MOUNTAINS: BiomeProperties = BiomeProperties {
+    name: "Mountains",
+    base_height: 90.0,
+    amplitude: 12.0,
+    ruggedness: 1.0,
+    filler: block::STONE,
+    filler_depth: 1,
+    grass_tint: [0.8, 0.95, 0.85],
+    foliage_tint: [0.8, 0.95, 0.85],
+    vegetation: &[(block::TALL_GRASS, 0.02)],
+    trees: &[(Tree::Spruce, 0.004)],
+    boulders: 0.003,
+    ..PLAINS
+};
+
+const OCEAN: BiomeProperties = BiomeProperties {
+    name: "Ocean",
+    base_height: 44.0,
+    amplitude: 6.0,
+    surface: block::GRAVEL,
+    filler: block::SAND,
+    vegetation: &[],
+    trees: &[],
+    ..PLAINS
+};
+
+impl Biome {
+    pub const ALL: [Biome; 9] = [
+        Biome::Plains,
+        Biome::Forest,
+        Biome::Desert,
+        Biome::Savanna,
+        Biome::Taiga,
+        Biome::SnowyPlains,
+        Biome::Swamp,
+        Biome::Mountains,
+        Biome::Ocean,
+    ];
+
+    pub fn properties(self) -> &'static BiomeProperties {
+        match self {
+            Biome::Plains => &PLAINS,
+            Biome::Forest => &FOREST,
+            Biome::Desert => &DESERT,
+            Biome::Savanna => &SAVANNA,
+            Biome::Taiga => &TAIGA,
+            Biome::SnowyPlains => &SNOWY_PLAINS,
+            Biome::Swamp => &SWAMP,
+            Biome::Mountains => &MOUNTAINS,
+            Biome::Ocean => &OCEAN,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        self.properties().name
+    }
+
+    pub fn from_climate(climate: Climate) -> Biome {
+        let Climate { temperature, humidity, continentalness } = climate;
+        if continentalness < -0.4 {
+            Biome::Ocean
+        } else if continentalness > 0.45 {
+            Biome::Mountains
+        } else if temperature < -0.4 {
+            Biome::SnowyPlains
+        } else if temperature < -0.15 {
+            Biome::Taiga
+        } else if temperature > 0.45 {
+            if humidity < 0.0 { Biome::Desert } else { Biome::Savanna }
+        } else if humidity > 0.45 {
+            Biome::Swamp
+        } else if humidity > 0.05 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    }
+
+    // What a block in this biome is multiplied by when it's drawn
+    pub fn tint(self, block_type: BlockType) -> [f32; 3] {
+        match block::id_of(block_type) {
+            block::GRASS | block::TALL_GRASS | block::FERN => self.properties().grass_tint,
+            block::LEAVES => self.properties().foliage_tint,
+            _ => block::NO_TINT,
+        }
+    }
+}
+
+// Each in -1..1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Climate {
+    pub temperature: f64,
+    pub humidity: f64,
+    pub continentalness: f64,
+}
+
+// In blocks per noise unit, climates change over a few hundred blocks
+const TEMPERATURE_SCALE: f64 = 300.0;
+const HUMIDITY_SCALE: f64 = 250.0;
+const CONTINENTALNESS_SCALE: f64 = 500.0;
+
+#[derive(Clone, Debug)]
+pub struct ClimateNoise {
+    temperature: OpenSimplex,
+    humidity: OpenSimplex,
+    continentalness: OpenSimplex,
+}
+
+impl ClimateNoise {
+    // The seeds are offset so the fields don't line up with each other or the terrain
+    pub fn new(seed: u32) -> Self {
+        Self {
+            temperature: OpenSimplex::new(seed.wrapping_add(1)),
+            humidity: OpenSimplex::new(seed.wrapping_add(2)),
+            continentalness: OpenSimplex::new(seed.wrapping_add(3)),
+        }
+    }
+
+    pub fn sample(&self, x: i32, z: i32) -> Climate {
+        let at = |noise: &OpenSimplex, scale: f64| {
+            (noise.get([x as f64 / scale, z as f64 / scale]) * NOISE_STRETCH).clamp(-1.0, 1.0)
+        };
+        Climate {
+            temperature: at(&self.temperature, TEMPERATURE_SCALE),
+            humidity: at(&self.humidity, HUMIDITY_SCALE),
+            continentalness: at(&self.continentalness, CONTINENTALNESS_SCALE),
+        }
+    }
+
+    pub fn biome(&self, x: i32, z: i32) -> Biome {
+        Biome::from_climate(self.sample(x, z))
+    }
+}
+
+// The same value in 0..1 every time for a column of a world, for placing plants and such
+pub fn column_random(seed: u32, x: i32, z: i32) -> f32 {
+    (position_hash(seed, x, z) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// A well mixed value for a column or chunk of a world, to seed the random features there
+pub fn position_hash(seed: u32, x: i32, z: i32) -> u64 {
+    mix(mix(mix(seed as u64) ^ x as u32 as u64) ^ z as u32 as u64)
+}
+
+// The splitmix64 finaliser
+pub(crate) fn mix(value: u64) -> u64 {
+    let mut h = value.wrapping_add(0x9e3779b97f4a7c15);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
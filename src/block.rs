@@ -1,14 +1,122 @@
 use crate::{model::Vertex, texture_atlas};
 
-type BlockType = u32;
+pub type BlockType = u32;
+
+// Block ids are the index of their texture in the atlas + 1, 0 is air
+pub const AIR: BlockType = 0;
+pub const GRASS: BlockType = 1;
+pub const STONE: BlockType = 2;
+pub const DIRT: BlockType = 3;
+pub const PLANKS: BlockType = 5;
+pub const BRICKS: BlockType = 8;
+pub const TNT: BlockType = 9;
+pub const COBWEB: BlockType = 12;
+pub const POPPY: BlockType = 13;
+pub const DANDELION: BlockType = 14;
+pub const SAPLING: BlockType = 16;
+pub const COBBLESTONE: BlockType = 17;
+pub const BEDROCK: BlockType = 18;
+pub const SAND: BlockType = 19;
+pub const GRAVEL: BlockType = 20;
+pub const LOG: BlockType = 21;
+pub const IRON_BLOCK: BlockType = 23;
+pub const GOLD_BLOCK: BlockType = 24;
+pub const DIAMOND_BLOCK: BlockType = 25;
+pub const RED_MUSHROOM: BlockType = 29;
+pub const BROWN_MUSHROOM: BlockType = 30;
+pub const GOLD_ORE: BlockType = 33;
+pub const IRON_ORE: BlockType = 34;
+pub const COAL_ORE: BlockType = 35;
+pub const BOOKSHELF: BlockType = 36;
+pub const MOSSY_COBBLESTONE: BlockType = 37;
+pub const OBSIDIAN: BlockType = 38;
+pub const TALL_GRASS: BlockType = 40;
+pub const CRAFTING_TABLE: BlockType = 44;
+pub const FURNACE: BlockType = 45;
+pub const SPONGE: BlockType = 49;
+pub const GLASS: BlockType = 50;
+pub const DIAMOND_ORE: BlockType = 51;
+pub const REDSTONE_ORE: BlockType = 52;
+pub const LEAVES: BlockType = 53;
+pub const STONE_BRICKS: BlockType = 55;
+pub const DEAD_BUSH: BlockType = 56;
+pub const FERN: BlockType = 57;
+pub const WOOL: BlockType = 65;
+pub const SNOW: BlockType = 67;
+pub const ICE: BlockType = 68;
+pub const CACTUS: BlockType = 71;
+pub const CLAY: BlockType = 73;
+pub const SUGAR_CANE: BlockType = 74;
+pub const FARMLAND: BlockType = 87;
+// Wheat has 8 growth stages with consecutive ids
+pub const WHEAT: BlockType = 89;
+pub const WHEAT_RIPE: BlockType = 96;
+pub const NETHERRACK: BlockType = 104;
+pub const SOUL_SAND: BlockType = 105;
+pub const GLOWSTONE: BlockType = 106;
+pub const LAPIS_BLOCK: BlockType = 145;
+pub const LAPIS_ORE: BlockType = 161;
+pub const SANDSTONE: BlockType = 193;
+pub const WATER: BlockType = 206;
+pub const LAVA: BlockType = 238;
+// Purple "missing texture" tile, used for blocks we don't know about
+pub const UNKNOWN: BlockType = 28;
+
+// Block values keep the id in their low 16 bits, the bits above are state that depends
+// on the block, like the level of a fluid. Code that only cares about what a block is
+// should look at id_of.
+pub const STATE_SHIFT: u32 = 16;
+
+pub fn id_of(block_type: BlockType) -> BlockType {
+    block_type & ((1 << STATE_SHIFT) - 1)
+}
+
+pub fn state_of(block_type: BlockType) -> u32 {
+    block_type >> STATE_SHIFT
+}
+
+pub fn with_state(id: BlockType, state: u32) -> BlockType {
+    id_of(id) | (state << STATE_SHIFT)
+}
+
+// What the simulation needs to know about each block type
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BlockProperties {
+    // Stops falling things and holds plants up
+    pub solid: bool,
+    // Falls when the block under it isn't solid
+    pub falling: bool,
+    // Pops off when the block under it isn't solid
+    pub plant: bool,
+    // Falling blocks land in place of it instead of dropping as an item
+    pub replaceable: bool,
+}
+
+pub fn properties(block_type: BlockType) -> BlockProperties {
+    const SOLID: BlockProperties = BlockProperties { solid: true, falling: false, plant: false, replaceable: false };
+
+    match id_of(block_type) {
+        AIR | WATER | LAVA => BlockProperties { replaceable: true, ..Default::default() },
+        SAND | GRAVEL => BlockProperties { falling: true, ..SOLID },
+        TALL_GRASS | FERN | DEAD_BUSH => BlockProperties { plant: true, replaceable: true, ..Default::default() },
+        POPPY | DANDELION | SAPLING | RED_MUSHROOM | BROWN_MUSHROOM | SUGAR_CANE | WHEAT..=WHEAT_RIPE => {
+            BlockProperties { plant: true, ..Default::default() }
+        }
+        _ => SOLID,
+    }
+}
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct BlockVertex {
     pub position: [f32; 3],
     pub tex_coords: [f32; 2],
+    // Multiplied into the texture, for biome tints
+    pub color: [f32; 3],
 }
 
+pub const NO_TINT: [f32; 3] = [1.0, 1.0, 1.0];
+
 impl Vertex for BlockVertex {
     fn desc() -> wgpu::VertexBufferLayout<'static> {
         use std::mem;
@@ -30,6 +138,11 @@ impl Vertex for BlockVertex {
                     offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
                     shader_location: 1,
                 },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x3,
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                },
             ],
             // attributes: &ATTRIBS,
         }
@@ -48,44 +161,44 @@ pub enum FaceDirections {
 
 impl FaceDirections {
     fn get_verts(self, mat: BlockType) -> [BlockVertex; 4] {
-        let tex_coords = texture_atlas::TextureAtlas::get_block_texture_from_type(mat);
+        let tex_coords = texture_atlas::TextureAtlas::get_block_texture_from_type(id_of(mat));
 
         match self {
             FaceDirections::FRONT => [
-                BlockVertex {position: [0.0, 1.0, 1.0], tex_coords: tex_coords[0]},
-                BlockVertex {position: [0.0, 0.0, 1.0], tex_coords: tex_coords[1]},
-                BlockVertex {position: [1.0, 0.0, 1.0], tex_coords: tex_coords[2]},
-                BlockVertex {position: [1.0, 1.0, 1.0], tex_coords: tex_coords[3]},
+                BlockVertex {position: [0.0, 1.0, 1.0], tex_coords: tex_coords[0], color: NO_TINT},
+                BlockVertex {position: [0.0, 0.0, 1.0], tex_coords: tex_coords[1], color: NO_TINT},
+                BlockVertex {position: [1.0, 0.0, 1.0], tex_coords: tex_coords[2], color: NO_TINT},
+                BlockVertex {position: [1.0, 1.0, 1.0], tex_coords: tex_coords[3], color: NO_TINT},
             ],
             FaceDirections::BACK => [
-                BlockVertex {position: [1.0, 1.0, 0.0], tex_coords: tex_coords[0]},
-                BlockVertex {position: [1.0, 0.0, 0.0], tex_coords: tex_coords[1]},
-                BlockVertex {position: [0.0, 0.0, 0.0], tex_coords: tex_coords[2]},
-                BlockVertex {position: [0.0, 1.0, 0.0], tex_coords: tex_coords[3]},
+                BlockVertex {position: [1.0, 1.0, 0.0], tex_coords: tex_coords[0], color: NO_TINT},
+                BlockVertex {position: [1.0, 0.0, 0.0], tex_coords: tex_coords[1], color: NO_TINT},
+                BlockVertex {position: [0.0, 0.0, 0.0], tex_coords: tex_coords[2], color: NO_TINT},
+                BlockVertex {position: [0.0, 1.0, 0.0], tex_coords: tex_coords[3], color: NO_TINT},
             ],
             FaceDirections::LEFT => [
-                BlockVertex {position: [0.0, 1.0, 0.0], tex_coords: tex_coords[0]},
-                BlockVertex {position: [0.0, 0.0, 0.0], tex_coords: tex_coords[1]},
-                BlockVertex {position: [0.0, 0.0, 1.0], tex_coords: tex_coords[2]},
-                BlockVertex {position: [0.0, 1.0, 1.0], tex_coords: tex_coords[3]},
+                BlockVertex {position: [0.0, 1.0, 0.0], tex_coords: tex_coords[0], color: NO_TINT},
+                BlockVertex {position: [0.0, 0.0, 0.0], tex_coords: tex_coords[1], color: NO_TINT},
+                BlockVertex {position: [0.0, 0.0, 1.0], tex_coords: tex_coords[2], color: NO_TINT},
+                BlockVertex {position: [0.0, 1.0, 1.0], tex_coords: tex_coords[3], color: NO_TINT},
             ],
             FaceDirections::RIGHT => [
-                BlockVertex {position: [1.0, 1.0, 1.0], tex_coords: tex_coords[0]},
-                BlockVertex {position: [1.0, 0.0, 1.0], tex_coords: tex_coords[1]},
-                BlockVertex {position: [1.0, 0.0, 0.0], tex_coords: tex_coords[2]},
-                BlockVertex {position: [1.0, 1.0, 0.0], tex_coords: tex_coords[3]},
+                BlockVertex {position: [1.0, 1.0, 1.0], tex_coords: tex_coords[0], color: NO_TINT},
+                BlockVertex {position: [1.0, 0.0, 1.0], tex_coords: tex_coords[1], color: NO_TINT},
+                BlockVertex {position: [1.0, 0.0, 0.0], tex_coords: tex_coords[2], color: NO_TINT},
+                BlockVertex {position: [1.0, 1.0, 0.0], tex_coords: tex_coords[3], color: NO_TINT},
             ],
             FaceDirections::TOP => [
-                BlockVertex {position: [0.0, 1.0, 0.0], tex_coords: tex_coords[0]},
-                BlockVertex {position: [0.0, 1.0, 1.0], tex_coords: tex_coords[1]},
-                BlockVertex {position: [1.0, 1.0, 1.0], tex_coords: tex_coords[2]},
-                BlockVertex {position: [1.0, 1.0, 0.0], tex_coords: tex_coords[3]},
+                BlockVertex {position: [0.0, 1.0, 0.0], tex_coords: tex_coords[0], color: NO_TINT},
+                BlockVertex {position: [0.0, 1.0, 1.0], tex_coords: tex_coords[1], color: NO_TINT},
+                BlockVertex {position: [1.0, 1.0, 1.0], tex_coords: tex_coords[2], color: NO_TINT},
+                BlockVertex {position: [1.0, 1.0, 0.0], tex_coords: tex_coords[3], color: NO_TINT},
             ],
             FaceDirections::BOTTOM => [
-                BlockVertex {position: [0.0, 0.0, 1.0], tex_coords: tex_coords[0]},
-                BlockVertex {position: [0.0, 0.0, 0.0], tex_coords: tex_coords[1]},
-                BlockVertex {position: [1.0, 0.0, 0.0], tex_coords: tex_coords[2]},
-                BlockVertex {position: [1.0, 0.0, 1.0], tex_coords: tex_coords[3]},
+                BlockVertex {position: [0.0, 0.0, 1.0], tex_coords: tex_coords[0], color: NO_TINT},
+                BlockVertex {position: [0.0, 0.0, 0.0], tex_coords: tex_coords[1], color: NO_TINT},
+                BlockVertex {position: [1.0, 0.0, 0.0], tex_coords: tex_coords[2], color: NO_TINT},
+                BlockVertex {position: [1.0, 0.0, 1.0], tex_coords: tex_coords[3], color: NO_TINT},
             ],
         }
     }
@@ -141,8 +254,4 @@ impl Block {
             faces: faces,
         }
     }
-
-    pub fn is_air(&self) -> bool {
-        self.mat == 0
-    }
 }
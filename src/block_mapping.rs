@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::block::{self, BlockType};
+
+const NATIVE_PREFIX: &str = "bassicraft2:block_";
+
+// Maps namespaced Minecraft block names ("minecraft:oak_log") to our block ids.
+// Block state properties are ignored, anything missing from the table becomes the placeholder.
+#[derive(Clone, Debug)]
+pub struct BlockMapping {
+    pub names: HashMap<String, BlockType>,
+    // Name written back when exporting, the first name mapped to an id wins
+    pub canonical_names: HashMap<BlockType, String>,
+    pub placeholder: BlockType,
+}
+
+impl Default for BlockMapping {
+    fn default() -> Self {
+        use block::*;
+
+        let table: &[(&str, BlockType)] = &[
+            ("air", AIR), ("cave_air", AIR), ("void_air", AIR),
+            ("grass_block", GRASS), ("stone", STONE), ("dirt", DIRT), ("coarse_dirt", DIRT),
+            ("rooted_dirt", DIRT), ("podzol", DIRT), ("mycelium", GRASS), ("dirt_path", DIRT),
+            ("farmland", FARMLAND), ("granite", STONE), ("diorite", STONE), ("andesite", STONE),
+            ("deepslate", STONE), ("tuff", STONE), ("calcite", STONE),
+            ("oak_planks", PLANKS), ("spruce_planks", PLANKS), ("birch_planks", PLANKS),
+            ("jungle_planks", PLANKS), ("acacia_planks", PLANKS), ("dark_oak_planks", PLANKS),
+            ("bricks", BRICKS), ("tnt", TNT), ("cobweb", COBWEB),
+            ("poppy", POPPY), ("dandelion", DANDELION), ("oak_sapling", SAPLING),
+            ("cobblestone", COBBLESTONE), ("cobbled_deepslate", COBBLESTONE),
+            ("bedrock", BEDROCK), ("sand", SAND), ("red_sand", SAND), ("gravel", GRAVEL),
+            ("oak_log", LOG), ("spruce_log", LOG), ("birch_log", LOG), ("jungle_log", LOG),
+            ("acacia_log", LOG), ("dark_oak_log", LOG),
+            ("iron_block", IRON_BLOCK), ("gold_block", GOLD_BLOCK), ("diamond_block", DIAMOND_BLOCK),
+            ("red_mushroom", RED_MUSHROOM), ("brown_mushroom", BROWN_MUSHROOM),
+            ("gold_ore", GOLD_ORE), ("deepslate_gold_ore", GOLD_ORE),
+            ("iron_ore", IRON_ORE), ("deepslate_iron_ore", IRON_ORE),
+            ("coal_ore", COAL_ORE), ("deepslate_coal_ore", COAL_ORE),
+            ("diamond_ore", DIAMOND_ORE), ("deepslate_diamond_ore", DIAMOND_ORE),
+            ("redstone_ore", REDSTONE_ORE), ("deepslate_redstone_ore", REDSTONE_ORE),
+            ("lapis_ore", LAPIS_ORE), ("deepslate_lapis_ore", LAPIS_ORE), ("lapis_block", LAPIS_BLOCK),
+            ("bookshelf", BOOKSHELF), ("mossy_cobblestone", MOSSY_COBBLESTONE), ("obsidian", OBSIDIAN),
+            ("short_grass", TALL_GRASS), ("grass", TALL_GRASS), ("tall_grass", TALL_GRASS),
+            ("fern", FERN), ("dead_bush", DEAD_BUSH),
+            ("crafting_table", CRAFTING_TABLE), ("furnace", FURNACE), ("sponge", SPONGE),
+            ("glass", GLASS), ("stone_bricks", STONE_BRICKS), ("mossy_stone_bricks", STONE_BRICKS),
+            ("oak_leaves", LEAVES), ("spruce_leaves", LEAVES), ("birch_leaves", LEAVES),
+            ("jungle_leaves", LEAVES), ("acacia_leaves", LEAVES), ("dark_oak_leaves", LEAVES),
+            ("white_wool", WOOL), ("snow_block", SNOW), ("snow", SNOW), ("ice", ICE), ("packed_ice", ICE),
+            ("cactus", CACTUS), ("clay", CLAY), ("sugar_cane", SUGAR_CANE), ("wheat", WHEAT),
+            ("netherrack", NETHERRACK), ("soul_sand", SOUL_SAND), ("glowstone", GLOWSTONE),
+            ("sandstone", SANDSTONE), ("water", WATER), ("lava", LAVA),
+        ];
+
+        let mut mapping = Self {
+            names: HashMap::new(),
+            canonical_names: HashMap::new(),
+            placeholder: UNKNOWN,
+        };
+        for &(name, id) in table {
+            mapping.insert(format!("minecraft:{}", name), id);
+        }
+        mapping
+    }
+}
+
+impl BlockMapping {
+    // Text format, one entry per line, '#' starts a comment :
+    //   minecraft:stone = 2
+    //   placeholder = 28
+    // Entries are added on top of the default table.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut mapping = Self::default();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, id) = line.split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected `name = block id`", line_number + 1))?;
+            let name = name.trim();
+            let id: BlockType = id.trim().parse()
+                .with_context(|| format!("line {}: invalid block id", line_number + 1))?;
+            if name == "placeholder" {
+                mapping.placeholder = id;
+            } else if name.contains(':') {
+                mapping.insert(name.to_string(), id);
+            } else {
+                mapping.insert(format!("minecraft:{}", name), id);
+            }
+        }
+        Ok(mapping)
+    }
+
+    pub fn insert(&mut self, name: String, id: BlockType) {
+        self.canonical_names.entry(id).or_insert_with(|| name.clone());
+        self.names.insert(name, id);
+    }
+
+    // Accepts block states too ("minecraft:oak_log[axis=y]"), the properties are dropped
+    pub fn get(&self, name: &str) -> BlockType {
+        self.lookup(name).unwrap_or(self.placeholder)
+    }
+
+    // Like get, without falling back to the placeholder
+    pub fn lookup(&self, name: &str) -> Option<BlockType> {
+        let name = name.split('[').next().unwrap();
+        if let Some(&id) = self.names.get(name) {
+            return Some(id);
+        }
+        name.strip_prefix(NATIVE_PREFIX).and_then(|id| id.parse().ok())
+    }
+
+    // Blocks without a Minecraft equivalent are written with our own namespace so they survive a round trip
+    pub fn name_of(&self, id: BlockType) -> String {
+        match self.canonical_names.get(&id) {
+            Some(name) => name.clone(),
+            None => format!("{}{}", NATIVE_PREFIX, id),
+        }
+    }
+}
+
@@ -1,6 +1,4 @@
 use cgmath::*;
-use winit::event::*;
-use winit::dpi::PhysicalPosition;
 use winit::keyboard::KeyCode;
 use instant::Duration;
 
@@ -93,13 +91,11 @@ pub struct CameraController {
     pub amount_down: f32,
     rotate_horizontal: f32,
     rotate_vertical: f32,
-    scroll: f32,
-    speed: f32,
     sensitivity: f32,
 }
 
 impl CameraController {
-    pub fn new(speed: f32, sensitivity: f32) -> Self {
+    pub fn new(sensitivity: f32) -> Self {
         Self {
             amount_left: 0.0,
             amount_right: 0.0,
@@ -109,8 +105,6 @@ impl CameraController {
             amount_down: 0.0,
             rotate_horizontal: 0.0,
             rotate_vertical: 0.0,
-            scroll: 0.0,
-            speed,
             sensitivity,
         }
     }
@@ -151,40 +145,21 @@ impl CameraController {
         self.rotate_vertical = mouse_dy as f32;
     }
 
-    pub fn handle_mouse_scroll(&mut self, delta: &MouseScrollDelta) {
-        self.scroll = -match delta {
-            // I'm assuming a line is about 100 pixels
-            MouseScrollDelta::LineDelta(_, scroll) => scroll * 100.0,
-            MouseScrollDelta::PixelDelta(PhysicalPosition {
-                y: scroll,
-                ..
-            }) => *scroll as f32,
-        };
+    // The horizontal direction the keys held ask for, relative to where the camera looks.
+    // At most 1 long so walking diagonally isn't faster.
+    pub fn movement(&self, camera: &Camera) -> [f32; 2] {
+        let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
+        let forward = self.amount_forward - self.amount_backward;
+        let right = self.amount_right - self.amount_left;
+        let walk = [forward * yaw_cos - right * yaw_sin, forward * yaw_sin + right * yaw_cos];
+        let length = (walk[0] * walk[0] + walk[1] * walk[1]).sqrt();
+        if length > 1.0 { walk.map(|c| c / length) } else { walk }
     }
 
+    // Only turns the camera, the player's body moves it
     pub fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
         let dt = dt.as_secs_f32();
 
-        // Move forward/backward and left/right
-        let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
-        let forward = Vector3::new(yaw_cos, 0.0, yaw_sin).normalize();
-        let right = Vector3::new(-yaw_sin, 0.0, yaw_cos).normalize();
-        camera.position += forward * (self.amount_forward - self.amount_backward) * self.speed * dt;
-        camera.position += right * (self.amount_right - self.amount_left) * self.speed * dt;
-
-        // Move in/out (aka. "zoom")
-        // Note: this isn't an actual zoom. The camera's position
-        // changes when zooming. I've added this to make it easier
-        // to get closer to an object you want to focus on.
-        let (pitch_sin, pitch_cos) = camera.pitch.0.sin_cos();
-        let scrollward = Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin).normalize();
-        camera.position += scrollward * self.scroll * self.speed * self.sensitivity * dt;
-        self.scroll = 0.0;
-
-        // Move up/down. Since we don't use roll, we can just
-        // modify the y coordinate directly.
-        camera.position.y += (self.amount_up - self.amount_down) * self.speed * dt;
-
         // Rotate
         camera.yaw += Rad(self.rotate_horizontal) * self.sensitivity * dt;
         camera.pitch += Rad(-self.rotate_vertical) * self.sensitivity * dt;
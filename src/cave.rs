@@ -0,0 +1,253 @@
+use std::f64::consts::PI;
+
+use anyhow::{bail, Result};
+use noise::{NoiseFn, OpenSimplex};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    biome::position_hash,
+    block::{self, BlockType},
+    chunk::{WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    pos::ChunkPos,
+    terrain::NOISE_STRETCH,
+    tick::Random,
+};
+
+// Caves are carved out of a chunk once its terrain is filled in. There are two kinds:
+//
+// Cheese caverns are where 3D noise goes over a threshold. The noise is only sampled every
+// few blocks and interpolated in between, which is much cheaper and smooths the walls.
+// They stay a few blocks under the surface so the ground above them holds.
+//
+// Worm tunnels wander from a random start in some chunks. Each chunk replays the worms
+// starting in every chunk close enough to reach it, from a random generator seeded by
+// the chunk they start in, so a tunnel carves the same path whichever chunk is generated
+// first. Worms ignore the surface and cut openings where they come out of hillsides, but
+// stay under the sea floor and lake beds.
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaveSettings {
+    pub enabled: bool,
+    // In blocks per noise unit
+    pub cheese_scale: f64,
+    pub cheese_vertical_scale: f64,
+    // Noise in -1..1 over this is cave, higher gives fewer and smaller caverns
+    pub cheese_threshold: f64,
+    // Blocks of ground kept over caverns
+    pub surface_margin: i32,
+    // Of a chunk being the start of a tunnel
+    pub worm_chance: f64,
+    // In steps of one block
+    pub worm_length: [u32; 2],
+    pub worm_radius: [f64; 2],
+    // Tunnels start between the bottom of the world and this
+    pub worm_max_y: i32,
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cheese_scale: 48.0,
+            cheese_vertical_scale: 24.0,
+            cheese_threshold: 0.55,
+            surface_margin: 8,
+            worm_chance: 0.15,
+            worm_length: [60, 140],
+            worm_radius: [1.5, 3.5],
+            worm_max_y: 90,
+        }
+    }
+}
+
+// Bounds for settings files, well past anything that still looks like a cave
+const MAX_WORM_RADIUS: f64 = 16.0;
+const MAX_WORM_LENGTH: u32 = 1000;
+
+pub fn check(settings: &CaveSettings) -> Result<()> {
+    let positive = |value: f64| value.is_finite() && value > 0.0;
+    if !(positive(settings.cheese_scale) && positive(settings.cheese_vertical_scale)) {
+        bail!("cave scales have to be over 0");
+    }
+    if !settings.cheese_threshold.is_finite() || !(0.0..=1.0).contains(&settings.worm_chance) {
+        bail!("caves need a finite threshold and a worm chance from 0 to 1");
+    }
+    let [min_radius, max_radius] = settings.worm_radius;
+    if !(min_radius > 0.0 && min_radius <= max_radius && max_radius <= MAX_WORM_RADIUS) {
+        bail!("worm radius has to go from over 0 up to at most {}", MAX_WORM_RADIUS);
+    }
+    let [min_length, max_length] = settings.worm_length;
+    if min_length > max_length || max_length > MAX_WORM_LENGTH {
+        bail!("worm length has to go up to at most {}", MAX_WORM_LENGTH);
+    }
+    Ok(())
+}
+
+// Blocks between noise samples, the height of a world is always a multiple
+const CELL: usize = 4;
+// How far in chunks a worm can get from the chunk it starts in
+const WORM_REACH: i32 = 9;
+// The bottom layers of the world are never carved
+const FLOOR: i32 = 4;
+
+#[derive(Clone, Debug)]
+pub struct Caves {
+    seed: u32,
+    settings: CaveSettings,
+    noise: OpenSimplex,
+}
+
+impl Caves {
+    pub fn new(seed: u32, settings: CaveSettings) -> Self {
+        Self { seed, settings, noise: OpenSimplex::new(seed.wrapping_add(400)) }
+    }
+
+    // `surfaces` are the y of the top block of each column, indexed [x][z]
+    pub fn carve(
+        &self,
+        pos: ChunkPos,
+        height: WorldHeight,
+        surfaces: &[[i32; CHUNK_Z_SIZE]; CHUNK_X_SIZE],
+        blocks: &mut [Vec<Vec<BlockType>>],
+    ) {
+        if !self.settings.enabled {
+            return;
+        }
+        self.carve_cheese(pos, height, surfaces, blocks);
+        // Tunnels stay under the beds of the sea and lakes so the water doesn't hang over them
+        let mut ceilings = [[i32::MAX; CHUNK_Z_SIZE]; CHUNK_X_SIZE];
+        for (x, column) in ceilings.iter_mut().enumerate() {
+            for (z, ceiling) in column.iter_mut().enumerate() {
+                let surface = surfaces[x][z];
+                if height.index(surface + 1).is_some_and(|i| blocks[x][i][z] == block::WATER) {
+                    *ceiling = surface - 2;
+                }
+            }
+        }
+        for x in pos.x - WORM_REACH..=pos.x + WORM_REACH {
+            for z in pos.z - WORM_REACH..=pos.z + WORM_REACH {
+                self.carve_worm(ChunkPos::new(x, z), pos, height, &ceilings, blocks);
+            }
+        }
+    }
+
+    fn carve_cheese(&self, pos: ChunkPos, height: WorldHeight, surfaces: &[[i32; CHUNK_Z_SIZE]; CHUNK_X_SIZE], blocks: &mut [Vec<Vec<BlockType>>]) {
+        let settings = &self.settings;
+        let origin = pos.min_block();
+        let (xs, ys, zs) = (CHUNK_X_SIZE / CELL + 1, height.height / CELL + 1, CHUNK_Z_SIZE / CELL + 1);
+        let samples: Vec<f64> = (0..xs * ys * zs).map(|i| {
+            let (x, y, z) = (i / (ys * zs), i / zs % ys, i % zs);
+            let point = [
+                (origin.x + (x * CELL) as i32) as f64 / settings.cheese_scale,
+                (height.min_y + (y * CELL) as i32) as f64 / settings.cheese_vertical_scale,
+                (origin.z + (z * CELL) as i32) as f64 / settings.cheese_scale,
+            ];
+            self.noise.get(point) * NOISE_STRETCH
+        }).collect();
+        let sample = |x: usize, y: usize, z: usize| samples[(x * ys + y) * zs + z];
+
+        for (x, plane) in blocks.iter_mut().enumerate() {
+            for z in 0..CHUNK_Z_SIZE {
+                let top = surfaces[x][z] - settings.surface_margin;
+                for (i, row) in plane.iter_mut().enumerate() {
+                    let y = height.min_y + i as i32;
+                    if y > top || y < height.min_y + FLOOR || row[z] == block::AIR {
+                        continue;
+                    }
+                    let (cx, cy, cz) = (x / CELL, i / CELL, z / CELL);
+                    let [tx, ty, tz] = [x, i, z].map(|v| (v % CELL) as f64 / CELL as f64);
+                    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+                    let along_z = |x: usize, y: usize| lerp(sample(x, y, cz), sample(x, y, cz + 1), tz);
+                    let along_y = |x: usize| lerp(along_z(x, cy), along_z(x, cy + 1), ty);
+                    if lerp(along_y(cx), along_y(cx + 1), tx) > settings.cheese_threshold {
+                        row[z] = block::AIR;
+                    }
+                }
+            }
+        }
+    }
+
+    // Carves the part of the worm starting in `start` that goes through `target`
+    fn carve_worm(
+        &self,
+        start: ChunkPos,
+        target: ChunkPos,
+        height: WorldHeight,
+        ceilings: &[[i32; CHUNK_Z_SIZE]; CHUNK_X_SIZE],
+        blocks: &mut [Vec<Vec<BlockType>>],
+    ) {
+        let settings = &self.settings;
+        let mut random = Random::new(position_hash(self.seed.wrapping_add(500), start.x, start.z));
+        if random.next_f64() >= settings.worm_chance {
+            return;
+        }
+        let range = |random: &mut Random, [min, max]: [f64; 2]| min + random.next_f64() * (max - min);
+
+        let origin = start.min_block();
+        let max_y = settings.worm_max_y.min(height.max_y() - 1) as f64;
+        let min_y = (height.min_y + FLOOR * 2) as f64;
+        let mut point = [
+            origin.x as f64 + random.next_f64() * CHUNK_X_SIZE as f64,
+            range(&mut random, [min_y, max_y.max(min_y)]),
+            origin.z as f64 + random.next_f64() * CHUNK_Z_SIZE as f64,
+        ];
+        let mut yaw = random.next_f64() * 2.0 * PI;
+        let mut pitch = (random.next_f64() - 0.5) * 0.5;
+        let [min_length, max_length] = settings.worm_length;
+        let length = range(&mut random, [min_length as f64, max_length as f64]) as u32;
+        let radius = range(&mut random, settings.worm_radius);
+
+        let target_min = target.min_block();
+        for step in 0..length {
+            point[0] += libm::cos(yaw) * libm::cos(pitch);
+            point[1] += libm::sin(pitch);
+            point[2] += libm::sin(yaw) * libm::cos(pitch);
+            // Flattens out over time and turns a little each step
+            pitch = pitch * 0.7 + (random.next_f64() - 0.5) * 0.3;
+            yaw += (random.next_f64() - 0.5) * 0.4;
+
+            // Thickest in the middle
+            let r = radius * (0.5 + libm::sin(PI * step as f64 / length as f64) * 0.5).max(0.6);
+            let local = [point[0] - target_min.x as f64, point[2] - target_min.z as f64];
+            if local[0] < -r || local[0] > CHUNK_X_SIZE as f64 + r || local[1] < -r || local[1] > CHUNK_Z_SIZE as f64 + r {
+                continue;
+            }
+            carve_sphere(point, r, target, height, ceilings, blocks);
+        }
+    }
+}
+
+fn carve_sphere(
+    center: [f64; 3],
+    radius: f64,
+    pos: ChunkPos,
+    height: WorldHeight,
+    ceilings: &[[i32; CHUNK_Z_SIZE]; CHUNK_X_SIZE],
+    blocks: &mut [Vec<Vec<BlockType>>],
+) {
+    let origin = pos.min_block();
+    let xs = (center[0] - radius).floor() as i32 - origin.x..=(center[0] + radius).floor() as i32 - origin.x;
+    let ys = (center[1] - radius).floor() as i32..=(center[1] + radius).floor() as i32;
+    let zs = (center[2] - radius).floor() as i32 - origin.z..=(center[2] + radius).floor() as i32 - origin.z;
+    for x in xs.clone().filter(|&x| (0..CHUNK_X_SIZE as i32).contains(&x)) {
+        for y in ys.clone().filter(|&y| y >= height.min_y + FLOOR) {
+            let Some(i) = height.index(y) else {
+                continue;
+            };
+            for z in zs.clone().filter(|&z| (0..CHUNK_Z_SIZE as i32).contains(&z)) {
+                if y > ceilings[x as usize][z as usize] {
+                    continue;
+                }
+                let offset = [
+                    (origin.x + x) as f64 + 0.5 - center[0],
+                    y as f64 + 0.5 - center[1],
+                    (origin.z + z) as f64 + 0.5 - center[2],
+                ];
+                if offset.iter().map(|o| o * o).sum::<f64>() <= radius * radius {
+                    blocks[x as usize][i][z as usize] = block::AIR;
+                }
+            }
+        }
+    }
+}
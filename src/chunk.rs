@@ -1,14 +1,69 @@
-use std::{default, vec};
+use std::{default, ops::Range, vec};
 
-use noise::{NoiseFn, OpenSimplex};
+use serde::{Deserialize, Serialize};
 
-use crate::block::{Block, BlockVertex, Face};
+use crate::biome::Biome;
+use crate::block::{Block, BlockType, BlockVertex, Face};
+use crate::fluid::{self, Fluid};
+use crate::generator::TerrainGenerator;
+use crate::heightmap::Heightmaps;
+use crate::pos::{ChunkPos, Direction, LocalPos};
 
 pub const CHUNK_X_SIZE: usize = 16;
-pub const CHUNK_Y_SIZE: usize = 256;
 pub const CHUNK_Z_SIZE: usize = 16;
+// Heights are made of sections this high, like Minecraft's
+pub const SECTION_HEIGHT: usize = 16;
 
-#[derive(Clone, Debug)]
+// The vertical range of a world, the same for all of its chunks. Blocks go from min_y
+// included to min_y + height excluded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldHeight {
+    pub min_y: i32,
+    pub height: usize,
+}
+
+impl WorldHeight {
+    // What worlds had before the height was configurable, and saves without one use
+    pub const LEGACY: WorldHeight = WorldHeight { min_y: 0, height: 256 };
+
+    // Both have to be whole sections
+    pub fn new(min_y: i32, height: usize) -> Self {
+        assert!(min_y % SECTION_HEIGHT as i32 == 0 && height.is_multiple_of(SECTION_HEIGHT) && height > 0,
+            "world height {}..{} isn't made of whole sections", min_y, min_y + height as i32);
+        Self { min_y, height }
+    }
+
+    // Excluded
+    pub fn max_y(self) -> i32 {
+        self.min_y + self.height as i32
+    }
+
+    pub fn ys(self) -> Range<i32> {
+        self.min_y..self.max_y()
+    }
+
+    pub fn contains(self, y: i32) -> bool {
+        self.ys().contains(&y)
+    }
+
+    // Index of a y in the blocks of a chunk
+    pub fn index(self, y: i32) -> Option<usize> {
+        self.contains(y).then(|| (y - self.min_y) as usize)
+    }
+
+    pub fn sections(self) -> usize {
+        self.height / SECTION_HEIGHT
+    }
+}
+
+// Same range as Minecraft since 1.18
+impl Default for WorldHeight {
+    fn default() -> Self {
+        Self::new(-64, 384)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Mesh {
     pub vertices: Vec<BlockVertex>,
     pub indices: Vec<u32>,
@@ -16,15 +71,49 @@ pub struct Mesh {
 }
 
 impl Mesh {
-    pub fn new(pos: [i32; 2], blocks: &Vec<Vec<Vec<Block>>>) -> Self {
+    // `neighbors` are the blocks touching the chunk on each side, in the order of
+    // Direction::HORIZONTAL, indexed [z][y] for the x sides and [x][y] for the z sides.
+    // Faces on a side without a neighbouring chunk are always drawn, and blocks are tinted
+    // with the biome of their column.
+    pub fn new(
+        pos: ChunkPos,
+        min_y: i32,
+        blocks: &[Vec<Vec<BlockType>>],
+        biomes: &[Biome],
+        neighbors: &[Option<Vec<Vec<BlockType>>>; 4],
+    ) -> Self {
         let mut vertices: Vec<BlockVertex> = Vec::new();
         let mut indices: Vec<u32> = Vec::new();
         let mut num_elements: u32 = 0;
 
+        let neighbor = |side: usize, i: usize, y: usize| neighbors[side].as_ref().map(|blocks| blocks[i][y]);
+        let height = blocks.first().map_or(0, |column| column.len());
+
         for x in 0..CHUNK_X_SIZE {
-            for y in 0..CHUNK_Y_SIZE {
+            for y in 0..height {
                 for z in 0..CHUNK_Z_SIZE {
-                    let block = &blocks[x][y][z];
+                    let block_type = blocks[x][y][z];
+                    if block_type == 0 {
+                        continue;
+                    }
+
+                    // Same order as the faces of Block and Direction::ALL
+                    let touching = [
+                        if z == 0 { neighbor(2, x, y) } else { Some(blocks[x][y][z - 1]) },
+                        if z == CHUNK_Z_SIZE - 1 { neighbor(3, x, y) } else { Some(blocks[x][y][z + 1]) },
+                        if x == 0 { neighbor(0, z, y) } else { Some(blocks[x - 1][y][z]) },
+                        if x == CHUNK_X_SIZE - 1 { neighbor(1, z, y) } else { Some(blocks[x + 1][y][z]) },
+                        if y == height - 1 { None } else { Some(blocks[x][y + 1][z]) },
+                        if y == 0 { None } else { Some(blocks[x][y - 1][z]) },
+                    ];
+                    let close_blocks: [bool; 6] = std::array::from_fn(|i| {
+                        touching[i].is_some_and(|other| fluid::hides_face(block_type, other, Direction::ALL[i].is_vertical()))
+                    });
+                    let block = Block::new(block_type, close_blocks);
+
+                    let tint = biomes[x * CHUNK_Z_SIZE + z].tint(block_type);
+                    let fluid = Fluid::of(block_type);
+                    let covered = fluid.is_some() && y + 1 < height && Fluid::of(blocks[x][y + 1][z]) == fluid;
 
                     block.faces.iter()
                         .filter_map(|face| face.as_ref())
@@ -34,9 +123,16 @@ impl Mesh {
                                     // THIS IS BAD ! Later : send the pos of the chunk in the shader and move them there
 
                                     let mut v = v.clone();
-                                    v.position[0] += x as f32 + CHUNK_X_SIZE as f32 * pos[0] as f32;
-                                    v.position[1] += y as f32;
-                                    v.position[2] += z as f32 + CHUNK_Z_SIZE as f32 * pos[1] as f32;
+                                    // Lower the top of fluids to slope towards their neighbours
+                                    if fluid.is_some() && !covered && v.position[1] == 1.0 {
+                                        let corner = [v.position[0] as usize, v.position[2] as usize];
+                                        v.position[1] = fluid_corner_height(blocks, [x, y, z], corner);
+                                    }
+                                    let origin = pos.min_block();
+                                    v.position[0] += x as f32 + origin.x as f32;
+                                    v.position[1] += (min_y + y as i32) as f32;
+                                    v.position[2] += z as f32 + origin.z as f32;
+                                    v.color = tint;
                                     v
                                 })
                             );
@@ -47,20 +143,51 @@ impl Mesh {
             }
         }
 
-        // println!("MESH : {:?}", vertices);
-
         Self {
             vertices,
-            num_elements: indices.clone().len() as u32,
+            num_elements: indices.len() as u32,
             indices,
         }
     }
 }
 
+// Height of a fluid surface at one corner of the top of a block, averaged over the blocks
+// of the chunk sharing that corner. Full height when one of them has fluid above it.
+fn fluid_corner_height(blocks: &[Vec<Vec<BlockType>>], [x, y, z]: [usize; 3], corner: [usize; 2]) -> f32 {
+    let fluid = Fluid::of(blocks[x][y][z]);
+    let xs = (x + corner[0]).saturating_sub(1)..(x + corner[0] + 1).min(CHUNK_X_SIZE);
+    let zs = (z + corner[1]).saturating_sub(1)..(z + corner[1] + 1).min(CHUNK_Z_SIZE);
+    let cells = blocks[xs].iter().flat_map(|column| {
+        zs.clone().map(move |cz| (column[y][cz], column.get(y + 1).map(|above| above[cz])))
+    });
+
+    let mut total = 0.0;
+    let mut count = 0;
+    for (block_type, above) in cells {
+        if Fluid::of(block_type) != fluid {
+            continue;
+        }
+        if above.is_some_and(|above| Fluid::of(above) == fluid) {
+            return 1.0;
+        }
+        total += fluid::height(block_type);
+        count += 1;
+    }
+    total / count as f32
+}
+
+// Only block values are stored, faces are worked out when the chunk is meshed
 #[derive(Clone, Debug)]
 pub struct Chunk {
-    pub pos: [i32; 2],
-    pub blocks: Vec<Vec<Vec<Block>>>,
+    pub pos: ChunkPos,
+    pub height: WorldHeight,
+    // Indexed [x][y - min_y][z]
+    pub blocks: Vec<Vec<Vec<BlockType>>>,
+    // Of each column, indexed x * 16 + z
+    pub biomes: Vec<Biome>,
+    // Kept up to date by set_block
+    pub heightmaps: Heightmaps,
+    // Empty until the world meshes the chunk, since faces on the sides depend on the neighbouring chunks
     pub mesh: Mesh,
 }
 
@@ -71,274 +198,72 @@ impl PartialEq for Chunk {
 }
 
 impl Chunk {
-    pub fn new(pos: [i32; 2], noise_fn: OpenSimplex) -> Self {
-        let blocks = Chunk::generate_blocks(pos, noise_fn);
-
-        let mesh = Mesh::new(pos, &blocks);
-
+    pub fn new(pos: ChunkPos, height: WorldHeight, generator: &dyn TerrainGenerator) -> Self {
+        let mut blocks = Chunk::empty_blocks(height);
+        generator.generate(pos, height, &mut blocks);
         Self {
             pos,
-            blocks: blocks,
-            mesh: mesh,
+            height,
+            heightmaps: Heightmaps::new(height, &blocks),
+            blocks,
+            biomes: Chunk::generate_biomes(pos, generator),
+            mesh: Mesh::default(),
         }
     }
 
-    fn generate_blocks(pos: [i32; 2], noise_fn: OpenSimplex) -> Vec<Vec<Vec<Block>>> {
-        let mut block_types = vec![vec![vec![0u32; CHUNK_Z_SIZE]; CHUNK_Y_SIZE]; CHUNK_X_SIZE];
-
-        for x in 0..CHUNK_X_SIZE {
-            for z in 0..CHUNK_Z_SIZE {
-                let noise_val = noise_fn.get([
-                    (x as i32 + pos[0] * CHUNK_X_SIZE as i32) as f64 / 20.0,
-                    (z as i32 + pos[1] * CHUNK_Z_SIZE as i32) as f64 / 20.0,
-                ]);
-                let ground_height = (noise_val * 10.0 + 80.0) as usize;
-                const STONE_HEIGHT: usize = 60;
-                for y in 0..CHUNK_Y_SIZE {
-                    let block_type = if y < ground_height.saturating_sub(1) && y <= STONE_HEIGHT {
-                        2
-                    } else if y < ground_height.saturating_sub(1) && y > STONE_HEIGHT {
-                        3
-                    } else if y == ground_height.saturating_sub(1) && ground_height > 0 {
-                        1
-                    } else {
-                        0
-                    };
-                    block_types[x][y][z] = block_type;
-                }
-            }
-        }
-
-        let mut blocks = vec![vec![vec![Block::new(0, [false; 6]); CHUNK_Z_SIZE]; CHUNK_Y_SIZE]; CHUNK_X_SIZE];
-        for x in 0..CHUNK_X_SIZE {
-            for y in 0..CHUNK_Y_SIZE {
-                for z in 0..CHUNK_Z_SIZE {
-                    let block_type = block_types[x][y][z];
-                    if block_type == 0 {
-                        blocks[x][y][z] = Block::new(0, [false; 6]);
-                        continue;
-                    }
-                    let mut close_blocks = [false; 6];
-                    // BACK (-z)
-                    close_blocks[0] = if z == 0 {
-                        false
-                    } else {
-                        block_types[x][y][z-1] != 0
-                    };
-                    // FRONT (+z)
-                    close_blocks[1] = if z == CHUNK_Z_SIZE-1 {
-                        false
-                    } else {
-                        block_types[x][y][z+1] != 0
-                    };
-                    // LEFT (-x)
-                    close_blocks[2] = if x == 0 {
-                        false
-                    } else {
-                        block_types[x-1][y][z] != 0
-                    };
-                    // RIGHT (+x)
-                    close_blocks[3] = if x == CHUNK_X_SIZE-1 {
-                        false
-                    } else {
-                        block_types[x+1][y][z] != 0
-                    };
-                    // TOP (+y)
-                    close_blocks[4] = if y == CHUNK_Y_SIZE-1 {
-                        false
-                    } else {
-                        block_types[x][y+1][z] != 0
-                    };
-                    // BOTTOM (-y)
-                    close_blocks[5] = if y == 0 {
-                        false
-                    } else {
-                        block_types[x][y-1][z] != 0
-                    };
-                    blocks[x][y][z] = Block::new(block_type, close_blocks);
-                }
-            }
+    // `block_types` has to be as high as `height`
+    pub fn from_block_types(pos: ChunkPos, height: WorldHeight, block_types: Vec<Vec<Vec<BlockType>>>, biomes: Vec<Biome>) -> Self {
+        Self {
+            pos,
+            height,
+            heightmaps: Heightmaps::new(height, &block_types),
+            blocks: block_types,
+            biomes,
+            mesh: Mesh::default(),
         }
-        blocks
     }
 
-    fn update_block_faces(&mut self) {
-        self.update_block_faces_with_neighbors(None, None, None, None);
+    // Chunks only keep their blocks in saves, their biomes always come from the generator
+    pub fn generate_biomes(pos: ChunkPos, generator: &dyn TerrainGenerator) -> Vec<Biome> {
+        (0..CHUNK_X_SIZE as i32).flat_map(|x| {
+            (0..CHUNK_Z_SIZE as i32).map(move |z| {
+                let column = pos.block(LocalPos::new(x, 0, z));
+                generator.biome(column.x, column.z)
+            })
+        }).collect()
     }
 
-    pub fn update_block_faces_with_neighbors(
-        &mut self,
-        left_chunk: Option<&Chunk>,
-        right_chunk: Option<&Chunk>,
-        back_chunk: Option<&Chunk>,
-        front_chunk: Option<&Chunk>,
-    ) {
-        for x in 0..CHUNK_X_SIZE {
-            for y in 0..CHUNK_Y_SIZE {
-                for z in 0..CHUNK_Z_SIZE {
-                    let block_type = self.blocks[x][y][z].mat;
-                    if block_type == 0 {
-                        self.blocks[x][y][z] = Block::new(0, [false; 6]);
-                        continue;
-                    }
-                    let mut close_blocks = [false; 6];
-                    
-                    // BACK (-z)
-                    close_blocks[0] = if z == 0 {
-                        back_chunk
-                            .and_then(|chunk| chunk.get_block([x as i32, y as i32, (CHUNK_Z_SIZE - 1) as i32]))
-                            .map(|block| block.mat != 0)
-                            .unwrap_or(false)
-                    } else {
-                        self.blocks[x][y][z-1].mat != 0
-                    };
-                    
-                    // FRONT (+z)
-                    close_blocks[1] = if z == CHUNK_Z_SIZE-1 {
-                        front_chunk
-                            .and_then(|chunk| chunk.get_block([x as i32, y as i32, 0]))
-                            .map(|block| block.mat != 0)
-                            .unwrap_or(false)
-                    } else {
-                        self.blocks[x][y][z+1].mat != 0
-                    };
-                    
-                    // LEFT (-x)
-                    close_blocks[2] = if x == 0 {
-                        left_chunk
-                            .and_then(|chunk| chunk.get_block([(CHUNK_X_SIZE - 1) as i32, y as i32, z as i32]))
-                            .map(|block| block.mat != 0)
-                            .unwrap_or(false)
-                    } else {
-                        self.blocks[x-1][y][z].mat != 0
-                    };
-                    
-                    // RIGHT (+x)
-                    close_blocks[3] = if x == CHUNK_X_SIZE-1 {
-                        right_chunk
-                            .and_then(|chunk| chunk.get_block([0, y as i32, z as i32]))
-                            .map(|block| block.mat != 0)
-                            .unwrap_or(false)
-                    } else {
-                        self.blocks[x+1][y][z].mat != 0
-                    };
-                    
-                    // TOP (+y)
-                    close_blocks[4] = if y == CHUNK_Y_SIZE-1 {
-                        false
-                    } else {
-                        self.blocks[x][y+1][z].mat != 0
-                    };
-                    
-                    // BOTTOM (-y)
-                    close_blocks[5] = if y == 0 {
-                        false
-                    } else {
-                        self.blocks[x][y-1][z].mat != 0
-                    };
-                    
-                    self.blocks[x][y][z] = Block::new(block_type, close_blocks);
-                }
-            }
-        }
+    pub fn biome(&self, pos: LocalPos) -> Biome {
+        self.biomes[pos.x as usize * CHUNK_Z_SIZE + pos.z as usize]
     }
 
-    pub fn get_block(&self, pos: [i32; 3]) -> Option<Block> {
-        if pos[0] < 0 || pos[1] < 0 || pos[2] < 0 {
-            return None;
-        }
-        let x = pos[0] as usize;
-        let y = pos[1] as usize;
-        let z = pos[2] as usize;
-        if x >= CHUNK_X_SIZE || y >= CHUNK_Y_SIZE || z >= CHUNK_Z_SIZE {
-            return None;
-        }
-        Some(self.blocks[x][y][z].clone())
+    // Only air, for the importers to fill
+    pub fn empty_blocks(height: WorldHeight) -> Vec<Vec<Vec<BlockType>>> {
+        vec![vec![vec![0u32; CHUNK_Z_SIZE]; height.height]; CHUNK_X_SIZE]
     }
 
-    pub fn break_block(&mut self, pos: [i32; 3]) {
-        let local_x = pos[0] - self.pos[0] * CHUNK_X_SIZE as i32;
-        let local_y = pos[1];
-        let local_z = pos[2] - self.pos[1] * CHUNK_Z_SIZE as i32;
-        
-        if local_x < 0 || local_y < 0 || local_z < 0 {
-            return;
-        }
-        let x = local_x as usize;
-        let y = local_y as usize;
-        let z = local_z as usize;
-        if x >= CHUNK_X_SIZE || y >= CHUNK_Y_SIZE || z >= CHUNK_Z_SIZE {
-            return;
-        }
-        self.blocks[x][y][z] = Block::new(0, [false; 6]);
-        self.update_block_faces();
-        self.mesh = Mesh::new(self.pos, &self.blocks);
-    }
-
-    pub fn place_block(&mut self, pos: [i32; 3], block_type: u32) {
-        let local_x = pos[0] - self.pos[0] * CHUNK_X_SIZE as i32;
-        let local_y = pos[1];
-        let local_z = pos[2] - self.pos[1] * CHUNK_Z_SIZE as i32;
-        
-        if local_x < 0 || local_y < 0 || local_z < 0 {
-            return;
-        }
-        let x = local_x as usize;
-        let y = local_y as usize;
-        let z = local_z as usize;
-        if x >= CHUNK_X_SIZE || y >= CHUNK_Y_SIZE || z >= CHUNK_Z_SIZE {
-            return;
-        }
-        if self.blocks[x][y][z].mat != 0 {
-            return;
-        }
-        self.blocks[x][y][z] = Block::new(block_type, [false; 6]);
-        self.update_block_faces();
-        self.mesh = Mesh::new(self.pos, &self.blocks);
+    pub fn get_block(&self, pos: LocalPos) -> Option<BlockType> {
+        let [x, y, z] = self.index(pos)?;
+        Some(self.blocks[x][y][z])
     }
 
-    pub fn contains_block(&self, pos: [i32; 3]) -> bool {
-        let chunk_world_x_min = self.pos[0] * CHUNK_X_SIZE as i32;
-        let chunk_world_x_max = chunk_world_x_min + CHUNK_X_SIZE as i32;
-        let chunk_world_z_min = self.pos[1] * CHUNK_Z_SIZE as i32;
-        let chunk_world_z_max = chunk_world_z_min + CHUNK_Z_SIZE as i32;
-        
-        if pos[0] < chunk_world_x_min || pos[0] >= chunk_world_x_max {
-            return false;
-        }
-        if pos[1] < 0 || pos[1] >= CHUNK_Y_SIZE as i32 {
-            return false;
-        }
-        if pos[2] < chunk_world_z_min || pos[2] >= chunk_world_z_max {
+    // Changes a block without touching the mesh, for bulk edits that remesh once at the end
+    pub fn set_block(&mut self, pos: LocalPos, block_type: BlockType) -> bool {
+        let Some([x, y, z]) = self.index(pos) else {
             return false;
-        }
-
-        let local_x = (pos[0] - chunk_world_x_min) as usize;
-        let local_y = pos[1] as usize;
-        let local_z = (pos[2] - chunk_world_z_min) as usize;
-        
-        self.blocks[local_x][local_y][local_z].mat != 0
+        };
+        self.blocks[x][y][z] = block_type;
+        self.heightmaps.update(&self.blocks, [x, y, z], block_type);
+        true
     }
 
-    pub fn contains_position(&self, pos: [i32; 3]) -> bool {
-        let chunk_world_x_min = self.pos[0] * CHUNK_X_SIZE as i32;
-        let chunk_world_x_max = chunk_world_x_min + CHUNK_X_SIZE as i32;
-        let chunk_world_z_min = self.pos[1] * CHUNK_Z_SIZE as i32;
-        let chunk_world_z_max = chunk_world_z_min + CHUNK_Z_SIZE as i32;
-        
-        pos[0] >= chunk_world_x_min && pos[0] < chunk_world_x_max
-            && pos[1] >= 0 && pos[1] < CHUNK_Y_SIZE as i32
-            && pos[2] >= chunk_world_z_min && pos[2] < chunk_world_z_max
+    fn index(&self, pos: LocalPos) -> Option<[usize; 3]> {
+        let y = self.height.index(pos.y)?;
+        let in_bounds = (0..CHUNK_X_SIZE as i32).contains(&pos.x) && (0..CHUNK_Z_SIZE as i32).contains(&pos.z);
+        in_bounds.then_some([pos.x as usize, y, pos.z as usize])
     }
-    
-    pub fn get_local_pos(&self, pos: [i32; 3]) -> [i32; 3] {
-        let local_x = pos[0] - self.pos[0] * CHUNK_X_SIZE as i32;
-        let local_y = pos[1];
-        let local_z = pos[2] - self.pos[1] * CHUNK_Z_SIZE as i32;
-        [local_x, local_y, local_z]
-    }
-    
-    pub fn regenerate_mesh(&mut self) {
-        self.mesh = Mesh::new(self.pos, &self.blocks);
+
+    pub fn regenerate_mesh(&mut self, neighbors: &[Option<Vec<Vec<BlockType>>>; 4]) {
+        self.mesh = Mesh::new(self.pos, self.height.min_y, &self.blocks, &self.biomes, neighbors);
     }
 }
@@ -0,0 +1,102 @@
+use anyhow::{anyhow, bail, Result};
+
+use crate::{chunk::WorldHeight, generator::GeneratorSettings, seed};
+
+// Commands typed in the console. They're only parsed here, the State runs them since they
+// can move the player and change what's rendered as well as the world.
+
+pub const HELP: &str = "\
+help - List the commands
+dimension list - List the dimensions of the save
+dimension <name> - Go to another dimension
+dimension create <name> [seed] [generator] - Generate a new dimension, the seed can be a number or a word
+    generators: default, amplified, void, flat or flat:<layers> like flat:bedrock,2*dirt,grass_block
+tp <x> <y> <z> - Teleport
+spawn - Go back to the spawn point
+setworldspawn [<x> <y> <z>] - Move the spawn point to where you stand or to a block";
+
+// How far from the origin positions can be on x and z, like Minecraft's world border
+pub const MAX_HORIZONTAL: i32 = 30_000_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Help,
+    ListDimensions,
+    GoToDimension(String),
+    CreateDimension { name: String, seed: Option<i64>, generator: GeneratorSettings },
+    Teleport([f32; 3]),
+    GoToSpawn,
+    // Where the player stands without a position
+    SetSpawn(Option<[i32; 3]>),
+}
+
+// The leading slash is optional
+pub fn parse(line: &str) -> Result<Command> {
+    let line = line.trim();
+    let line = line.strip_prefix('/').unwrap_or(line);
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    match words[..] {
+        [] => bail!("empty command"),
+        ["help"] => Ok(Command::Help),
+        ["dimension" | "dim"] | ["dimension" | "dim", "list"] => Ok(Command::ListDimensions),
+        ["dimension" | "dim", "create", name, ref options @ ..] if options.len() <= 2 => {
+            // The seed comes first but either can be left out, a lone word that isn't a
+            // generator is a seed
+            let (seed, generator) = match *options {
+                [] => (None, None),
+                [seed, generator] => (Some(seed::parse(seed)), Some(GeneratorSettings::parse(generator)?)),
+                [option] => match GeneratorSettings::parse(option) {
+                    Ok(generator) => (None, Some(generator)),
+                    Err(_) => (Some(seed::parse(option)), None),
+                },
+                _ => unreachable!(),
+            };
+            Ok(Command::CreateDimension { name: name.to_string(), seed, generator: generator.unwrap_or_default() })
+        }
+        ["dimension" | "dim", name] => Ok(Command::GoToDimension(name.to_string())),
+        ["tp", x, y, z] => {
+            // Parsing takes nan, inf and 1e30 too, which the physics can't do anything with
+            let coordinate = |value: &str| value.parse::<f32>().ok().filter(|v| v.is_finite()).ok_or_else(|| anyhow!("invalid coordinate {}", value));
+            let [x, y, z] = [coordinate(x)?, coordinate(y)?, coordinate(z)?];
+            check_horizontal(x)?;
+            check_horizontal(z)?;
+            Ok(Command::Teleport([x, y, z]))
+        }
+        ["spawn"] => Ok(Command::GoToSpawn),
+        ["setworldspawn"] => Ok(Command::SetSpawn(None)),
+        ["setworldspawn", x, y, z] => {
+            let coordinate = |value: &str| value.parse::<i32>().map_err(|_| anyhow!("invalid block coordinate {}", value));
+            let [x, y, z] = [coordinate(x)?, coordinate(y)?, coordinate(z)?];
+            check_horizontal(x as f32)?;
+            check_horizontal(z as f32)?;
+            Ok(Command::SetSpawn(Some([x, y, z])))
+        }
+        [name, ..] if matches!(name, "help" | "dimension" | "dim" | "tp" | "spawn" | "setworldspawn") => bail!("wrong arguments for {}, try help", name),
+        [name, ..] => bail!("unknown command {}, try help", name),
+    }
+}
+
+fn check_horizontal(value: f32) -> Result<()> {
+    if value.abs() > MAX_HORIZONTAL as f32 {
+        bail!("{} is further than {} from the origin", value, MAX_HORIZONTAL);
+    }
+    Ok(())
+}
+
+// The height depends on the world a command runs in, so it's checked when it runs rather than
+// when it's parsed. Feet can go from the bottom of the world to the top of its highest blocks.
+pub fn check_height(y: f32, height: WorldHeight) -> Result<()> {
+    if !(height.min_y as f32..=height.max_y() as f32).contains(&y) {
+        bail!("y {} is outside of the world, which goes from {} to {}", y, height.min_y, height.max_y());
+    }
+    Ok(())
+}
+
+// A block, like the one the spawn point's feet are in, has to be one of the world's
+pub fn check_block_height(y: i32, height: WorldHeight) -> Result<()> {
+    if !height.contains(y) {
+        bail!("y {} is outside of the world, which goes from {} to {}", y, height.min_y, height.max_y() - 1);
+    }
+    Ok(())
+}
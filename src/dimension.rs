@@ -0,0 +1,115 @@
+use anyhow::{bail, Result};
+
+use crate::world::{World, WorldSettings};
+
+// A save holds several named dimensions, each one a separate World with its own seed,
+// height and chunks. They all tick, but only the active one is rendered and played in.
+
+pub const OVERWORLD: &str = "overworld";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sky {
+    // Linear RGB, what the screen is cleared to
+    pub color: [f32; 3],
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self { color: [0.1, 0.2, 0.3] }
+    }
+}
+
+// Names are single words so commands can take them
+pub fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        bail!("invalid dimension name {:?}", name);
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct Dimension {
+    pub name: String,
+    pub sky: Sky,
+    pub world: World,
+}
+
+#[derive(Debug)]
+pub struct Dimensions {
+    pub(crate) dimensions: Vec<Dimension>,
+    active: usize,
+}
+
+impl Dimensions {
+    // The first dimension starts active
+    pub fn new(name: &str, world: World, sky: Sky) -> Self {
+        Self {
+            dimensions: vec![Dimension { name: name.to_string(), sky, world }],
+            active: 0,
+        }
+    }
+
+    pub fn add(&mut self, name: &str, world: World, sky: Sky) -> Result<&mut Dimension> {
+        check_name(name)?;
+        if self.get(name).is_some() {
+            bail!("dimension {} already exists", name);
+        }
+        self.dimensions.push(Dimension { name: name.to_string(), sky, world });
+        Ok(self.dimensions.last_mut().unwrap())
+    }
+
+    // Generates a new dimension
+    pub fn create(&mut self, name: &str, settings: WorldSettings, sky: Sky) -> Result<&mut Dimension> {
+        // Checked before add so nothing is generated for a name that's taken
+        if self.get(name).is_some() {
+            bail!("dimension {} already exists", name);
+        }
+        self.add(name, World::with_settings(settings), sky)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Dimension> {
+        self.dimensions.iter().find(|d| d.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Dimension> {
+        self.dimensions.iter_mut().find(|d| d.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Dimension> {
+        self.dimensions.iter()
+    }
+
+    pub fn active(&self) -> &Dimension {
+        &self.dimensions[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Dimension {
+        &mut self.dimensions[self.active]
+    }
+
+    pub fn world(&self) -> &World {
+        &self.active().world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.active_mut().world
+    }
+
+    pub fn switch_to(&mut self, name: &str) -> Result<()> {
+        let Some(index) = self.dimensions.iter().position(|d| d.name == name) else {
+            bail!("no dimension named {}", name);
+        };
+        self.active = index;
+        Ok(())
+    }
+
+    pub fn update_ticks(&mut self, dt: f32) {
+        for (i, dimension) in self.dimensions.iter_mut().enumerate() {
+            dimension.world.update_ticks(dt);
+            // Nothing draws the others, they're uploaded whole when switched to
+            if i != self.active {
+                dimension.world.take_remeshed_chunks();
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{
+    block::{self, BlockType},
+    events::BlockChangeCause,
+    pos::{BlockPos, BlockRegion, Direction},
+    volume::BlockVolume,
+    world::World,
+};
+
+// WorldEdit style bulk operations. Everything goes through World::set_blocks so each
+// operation is one undo step and each touched chunk is remeshed once at the end.
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Selection {
+    pub pos1: Option<BlockPos>,
+    pub pos2: Option<BlockPos>,
+}
+
+impl Selection {
+    pub fn region(&self) -> Option<BlockRegion> {
+        Some(BlockRegion::new(self.pos1?, self.pos2?))
+    }
+
+    pub fn size(&self) -> Option<[i32; 3]> {
+        Some(self.region()?.size())
+    }
+
+    // Nothing when one of the corners isn't set
+    pub fn positions(&self) -> impl Iterator<Item = BlockPos> + use<> {
+        self.region().into_iter().flat_map(|region| region.iter())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PasteTransform {
+    // Quarter turns clockwise around y
+    pub rotation: u32,
+    pub mirror_x: bool,
+    pub mirror_z: bool,
+    pub include_air: bool,
+}
+
+impl PasteTransform {
+    pub fn apply(&self, volume: &BlockVolume) -> BlockVolume {
+        let mut volume = volume.rotated_y(self.rotation);
+        if self.mirror_x {
+            volume = volume.mirrored(0);
+        }
+        if self.mirror_z {
+            volume = volume.mirrored(2);
+        }
+        volume
+    }
+}
+
+impl World {
+    pub fn fill(&mut self, selection: &Selection, block_type: BlockType) -> usize {
+        self.set_blocks(selection.positions().map(|pos| (pos, block_type)), BlockChangeCause::Edit)
+    }
+
+    pub fn replace(&mut self, selection: &Selection, from: BlockType, to: BlockType) -> usize {
+        let changes: Vec<_> = selection.positions()
+            .filter(|&pos| self.get_block_type(pos) == Some(from))
+            .map(|pos| (pos, to))
+            .collect();
+        self.set_blocks(changes, BlockChangeCause::Edit)
+    }
+
+    // Removes every block of the selection that isn't touching air, leaving a one block thick shell.
+    // Blocks outside the selection count as air so its outer faces stay even when it's buried.
+    pub fn hollow(&mut self, selection: &Selection) -> usize {
+        let Some(region) = selection.region() else {
+            return 0;
+        };
+        let is_solid = |world: &World, pos: BlockPos| {
+            region.contains(pos) && world.get_block_type(pos).is_some_and(|b| b != block::AIR)
+        };
+        let changes: Vec<_> = selection.positions()
+            .filter(|&pos| is_solid(self, pos) && pos.neighbors().all(|neighbor| is_solid(self, neighbor)))
+            .map(|pos| (pos, block::AIR))
+            .collect();
+        self.set_blocks(changes, BlockChangeCause::Edit)
+    }
+
+    // The four vertical sides of the selection
+    pub fn walls(&mut self, selection: &Selection, block_type: BlockType) -> usize {
+        let Some(BlockRegion { min, max }) = selection.region() else {
+            return 0;
+        };
+        let changes: Vec<_> = selection.positions()
+            .filter(|pos| pos.x == min.x || pos.x == max.x || pos.z == min.z || pos.z == max.z)
+            .map(|pos| (pos, block_type))
+            .collect();
+        self.set_blocks(changes, BlockChangeCause::Edit)
+    }
+
+    pub fn copy(&self, selection: &Selection) -> Option<BlockVolume> {
+        Some(self.export_volume(selection.region()?))
+    }
+
+    pub fn cut(&mut self, selection: &Selection) -> Option<BlockVolume> {
+        let volume = self.copy(selection)?;
+        self.fill(selection, block::AIR);
+        Some(volume)
+    }
+
+    // `origin` is where the min corner of the transformed volume ends up
+    pub fn paste(&mut self, origin: BlockPos, volume: &BlockVolume, transform: &PasteTransform) -> usize {
+        let volume = transform.apply(volume);
+        let changes: Vec<_> = volume.iter()
+            .filter(|&(_, block_type)| transform.include_air || block_type != block::AIR)
+            .map(|(pos, block_type)| (origin.offset(pos[0] as i32, pos[1] as i32, pos[2] as i32), block_type))
+            .collect();
+        self.set_blocks(changes, BlockChangeCause::Edit)
+    }
+
+    // Repeats the selection `count` times next to itself
+    pub fn stack(&mut self, selection: &Selection, count: u32, direction: Direction) -> usize {
+        let (Some(region), Some(volume)) = (selection.region(), self.copy(selection)) else {
+            return 0;
+        };
+        let (size, offset) = (region.size(), direction.offset());
+        let mut changes = Vec::new();
+        for n in 1..=count as i32 {
+            let origin = region.min.offset(offset[0] * size[0] * n, offset[1] * size[1] * n, offset[2] * size[2] * n);
+            changes.extend(volume.iter().map(|(pos, block_type)| (origin.offset(pos[0] as i32, pos[1] as i32, pos[2] as i32), block_type)));
+        }
+        self.set_blocks(changes, BlockChangeCause::Edit)
+    }
+}
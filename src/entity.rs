@@ -0,0 +1,139 @@
+use crate::{
+    block::{self, Block, BlockType, BlockVertex, Face},
+    chunk::Mesh,
+    events::BlockChangeCause,
+    pos::{BlockPos, ChunkPos},
+    world::World,
+};
+
+// Things that move on their own between ticks, for now falling blocks and the items they
+// drop when they can't land. They only move vertically, with Minecraft's gravity and drag.
+
+// In blocks per tick, per tick
+pub const GRAVITY: f32 = 0.04;
+pub const DRAG: f32 = 0.98;
+// Items lying around disappear after 5 minutes
+const ITEM_LIFETIME: u64 = 6000;
+const ITEM_SIZE: f32 = 0.25;
+// How far below the bottom of the world entities fall before being removed, and the player
+// before respawning
+pub const VOID_DEPTH: i32 = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntityKind {
+    FallingBlock,
+    Item,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entity {
+    pub kind: EntityKind,
+    pub block_type: BlockType,
+    // Centre of the bottom face
+    pub pos: [f32; 3],
+    pub velocity: f32,
+    pub age: u64,
+}
+
+impl Entity {
+    pub fn falling_block(pos: BlockPos, block_type: BlockType) -> Self {
+        Self {
+            kind: EntityKind::FallingBlock,
+            block_type,
+            pos: [pos.x as f32 + 0.5, pos.y as f32, pos.z as f32 + 0.5],
+            velocity: 0.0,
+            age: 0,
+        }
+    }
+
+    pub fn item(pos: [f32; 3], block_type: BlockType) -> Self {
+        Self {
+            kind: EntityKind::Item,
+            block_type,
+            pos,
+            velocity: 0.0,
+            age: 0,
+        }
+    }
+
+    pub fn size(&self) -> f32 {
+        match self.kind {
+            EntityKind::FallingBlock => 1.0,
+            EntityKind::Item => ITEM_SIZE,
+        }
+    }
+
+    // The block column the entity is in
+    fn column(&self) -> [i32; 2] {
+        [self.pos[0].floor() as i32, self.pos[2].floor() as i32]
+    }
+}
+
+impl World {
+    pub fn tick_entities(&mut self, dirty_chunks: &mut Vec<ChunkPos>) {
+        let mut entities = std::mem::take(&mut self.entities);
+        let mut spawned = Vec::new();
+        let void_y = (self.height.min_y - VOID_DEPTH) as f32;
+
+        entities.retain_mut(|entity| {
+            entity.age += 1;
+            if entity.kind == EntityKind::Item && entity.age > ITEM_LIFETIME {
+                return false;
+            }
+
+            entity.velocity = (entity.velocity - GRAVITY) * DRAG;
+            let [x, z] = entity.column();
+            let old_y = entity.pos[1];
+            let new_y = old_y + entity.velocity;
+
+            // Every cell the bottom of the entity went through this tick, from the top,
+            // so fast entities can't skip over a block
+            let landed_on = (new_y.floor() as i32..=old_y.ceil() as i32 - 1).rev()
+                .find(|&y| self.is_solid(BlockPos::new(x, y, z)));
+            let Some(ground) = landed_on else {
+                entity.pos[1] = new_y;
+                // Fell out of the world
+                return new_y > void_y;
+            };
+
+            entity.pos[1] = (ground + 1) as f32;
+            entity.velocity = 0.0;
+            if entity.kind == EntityKind::Item {
+                return true;
+            }
+
+            let target = BlockPos::new(x, ground + 1, z);
+            if self.get_block_type(target).is_some_and(|b| block::properties(b).replaceable) {
+                self.set_block_type(target, entity.block_type, BlockChangeCause::Tick, dirty_chunks);
+            } else {
+                spawned.push(Entity::item(entity.pos, entity.block_type));
+            }
+            false
+        });
+
+        entities.extend(spawned);
+        self.entities = entities;
+    }
+
+    // Every entity is a cube textured like its block
+    pub fn entity_mesh(&self) -> Mesh {
+        let mut vertices: Vec<BlockVertex> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        for entity in &self.entities {
+            let size = entity.size();
+            let corner = [entity.pos[0] - size / 2.0, entity.pos[1], entity.pos[2] - size / 2.0];
+            for face in Block::new(entity.block_type, [false; 6]).faces.iter().flatten() {
+                let first = vertices.len() as u32;
+                vertices.extend(face.verts.iter().map(|v| BlockVertex {
+                    position: [0, 1, 2].map(|i| corner[i] + v.position[i] * size),
+                    tex_coords: v.tex_coords,
+                    color: v.color,
+                }));
+                indices.extend(Face::get_indices().iter().map(|&i| i as u32 + first));
+            }
+        }
+
+        let num_elements = indices.len() as u32;
+        Mesh { vertices, indices, num_elements }
+    }
+}
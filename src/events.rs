@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+
+use crate::{
+    block::{self, BlockType},
+    pos::{BlockPos, ChunkPos},
+};
+
+// Everything that happens to the world is queued as a WorldEvent and handed to the
+// subscribers in order when the queue is drained, once per tick. Subscribers never run
+// in the middle of an edit so they always see a world that's done changing.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockChangeCause {
+    Player,
+    Edit,
+    Undo,
+    Redo,
+    // Random or scheduled block ticks, like grass spreading
+    Tick,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorldEvent {
+    BlockChanged {
+        pos: BlockPos,
+        old: BlockType,
+        new: BlockType,
+        cause: BlockChangeCause,
+    },
+    // A chunk that didn't exist before was created by the terrain generator
+    ChunkGenerated { pos: ChunkPos },
+    // A chunk was added from somewhere else than the generator, like a region import
+    ChunkLoaded { pos: ChunkPos },
+    ChunkUnloaded { pos: ChunkPos },
+}
+
+// Send so a World can be moved to another thread, like a server's tick loop
+pub type Subscriber = Box<dyn FnMut(&WorldEvent) + Send>;
+
+#[derive(Default)]
+pub struct WorldEvents {
+    queue: VecDeque<WorldEvent>,
+    subscribers: Vec<Subscriber>,
+}
+
+impl WorldEvents {
+    pub fn subscribe(&mut self, subscriber: impl FnMut(&WorldEvent) + Send + 'static) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    pub fn push(&mut self, event: WorldEvent) {
+        self.queue.push_back(event);
+    }
+
+    // Moves the events queued in `other` to the end of the queue, its subscribers stay where they are
+    pub fn append(&mut self, other: &mut WorldEvents) {
+        self.queue.append(&mut other.queue);
+    }
+
+    // Delivers every queued event to every subscriber
+    pub fn dispatch(&mut self) {
+        while let Some(event) = self.queue.pop_front() {
+            for subscriber in &mut self.subscribers {
+                subscriber(&event);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for WorldEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorldEvents")
+            .field("queue", &self.queue)
+            .field("subscribers", &self.subscribers.len())
+            .finish()
+    }
+}
+
+// Counts of what the player did to the world, shown in the stats window
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlockStats {
+    pub broken: u64,
+    pub placed: u64,
+    pub edited: u64,
+}
+
+impl BlockStats {
+    pub fn record(&mut self, event: &WorldEvent) {
+        match *event {
+            WorldEvent::BlockChanged { new, cause: BlockChangeCause::Player, .. } if new == block::AIR => self.broken += 1,
+            WorldEvent::BlockChanged { cause: BlockChangeCause::Player, .. } => self.placed += 1,
+            WorldEvent::BlockChanged { cause: BlockChangeCause::Edit, .. } => self.edited += 1,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{
+    biome::{column_random, position_hash, BiomeProperties},
+    block::{self, BlockType},
+    pos::BlockPos,
+    tick::Random,
+};
+
+// Trees, boulders and plants, put on the terrain once a chunk is generated. A feature starts
+// in a column of the chunk being decorated but can reach a few blocks into the chunks around
+// it. The world writes those blocks into the neighbours that are already generated and keeps
+// the rest until their chunk is. Feature blocks only replace air and blocks that rank below
+// them, with ties going to the higher id, so trees from two chunks end up the same whichever
+// chunk comes first.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tree {
+    Oak,
+    Birch,
+    Spruce,
+    Acacia,
+}
+
+impl Tree {
+    // The blocks of a tree whose trunk starts at `base`
+    pub fn blocks(self, base: BlockPos, random: &mut Random) -> Vec<(BlockPos, BlockType)> {
+        let mut blocks = Vec::new();
+        match self {
+            Tree::Oak | Tree::Birch => {
+                let trunk = if self == Tree::Oak { 4 + random.below(3) } else { 6 + random.below(2) } as i32;
+                let top = base.offset(0, trunk - 1, 0);
+                logs(&mut blocks, base, trunk);
+                // Two wide layers around the top of the trunk, then two narrow ones
+                for dy in -2..=1 {
+                    leaf_layer(&mut blocks, top.offset(0, dy, 0), if dy < 0 { 2 } else { 1 });
+                }
+            }
+            Tree::Spruce => {
+                let trunk = 6 + random.below(4) as i32;
+                logs(&mut blocks, base, trunk);
+                // Wider towards the bottom, every other layer pulled in
+                for dy in 2..=trunk {
+                    let from_top = trunk - dy;
+                    let radius = if from_top == 0 { 0 } else { (1 + from_top / 2).min(3) - (from_top + 1) % 2 };
+                    leaf_layer(&mut blocks, base.offset(0, dy, 0), radius);
+                }
+            }
+            Tree::Acacia => {
+                // Straight up, then leaning to one side under a flat canopy
+                let [dx, dz] = [[1, 0], [-1, 0], [0, 1], [0, -1]][random.below(4) as usize];
+                let lean = 2 + random.below(2) as i32;
+                logs(&mut blocks, base, 3);
+                let mut top = base.offset(0, 2, 0);
+                for _ in 0..lean {
+                    top = top.offset(dx, 1, dz);
+                    blocks.push((top, block::LOG));
+                }
+                leaf_layer(&mut blocks, top, 3);
+                leaf_layer(&mut blocks, top.up(), 1);
+            }
+        }
+        blocks
+    }
+}
+
+fn logs(blocks: &mut Vec<(BlockPos, BlockType)>, base: BlockPos, height: i32) {
+    blocks.extend((0..height).map(|dy| (base.offset(0, dy, 0), block::LOG)));
+}
+
+// A rounded square of leaves
+fn leaf_layer(blocks: &mut Vec<(BlockPos, BlockType)>, center: BlockPos, radius: i32) {
+    for dx in -radius..=radius {
+        for dz in -radius..=radius {
+            if dx * dx + dz * dz <= radius * radius + radius {
+                blocks.push((center.offset(dx, 0, dz), block::LEAVES));
+            }
+        }
+    }
+}
+
+// A lump of cobblestone half sunk into the ground at `center`
+pub fn boulder(center: BlockPos, random: &mut Random) -> Vec<(BlockPos, BlockType)> {
+    let radius = 1 + random.below(2) as i32;
+    let mut blocks = Vec::new();
+    for dx in -radius..=radius {
+        for dy in -radius..=radius {
+            for dz in -radius..=radius {
+                if dx * dx + dy * dy + dz * dz <= radius * radius + 1 {
+                    let block_type = if random.below(3) == 0 { block::MOSSY_COBBLESTONE } else { block::COBBLESTONE };
+                    blocks.push((center.offset(dx, dy, dz), block_type));
+                }
+            }
+        }
+    }
+    blocks
+}
+
+// The features of a column whose top block is `ground`, the same every time for a seed. At
+// most one of a tree, a boulder or a plant.
+pub fn column_features(seed: u32, biome: &BiomeProperties, ground: BlockPos) -> Vec<(BlockPos, BlockType)> {
+    let mut random = Random::new(position_hash(seed.wrapping_add(700), ground.x, ground.z));
+    let mut roll = random.next_f64() as f32;
+    for &(tree, chance) in biome.trees {
+        if roll < chance {
+            return tree.blocks(ground.up(), &mut random);
+        }
+        roll -= chance;
+    }
+    if roll < biome.boulders {
+        return boulder(ground, &mut random);
+    }
+
+    let mut roll = column_random(seed, ground.x, ground.z);
+    for &(plant, chance) in biome.vegetation {
+        if roll < chance {
+            if can_grow_on(plant, biome.surface) {
+                return vec![(ground.up(), plant)];
+            }
+            break;
+        }
+        roll -= chance;
+    }
+    Vec::new()
+}
+
+fn can_grow_on(plant: BlockType, ground: BlockType) -> bool {
+    match plant {
+        block::CACTUS | block::DEAD_BUSH => ground == block::SAND,
+        _ => ground == block::GRASS,
+    }
+}
+
+// Whether a feature block can go where `old` is. Between blocks of the same rank the higher
+// id wins, so two features claiming the same block agree whichever is written first.
+pub fn can_replace(old: BlockType, new: BlockType) -> bool {
+    rank(old) < TERRAIN && (rank(old), old) < (rank(new), new)
+}
+
+// Terrain and anything else features don't make, which they never replace
+const TERRAIN: u8 = 4;
+
+// Air, then plants, then leaves, then logs and stone of boulders
+fn rank(block_type: BlockType) -> u8 {
+    match block::id_of(block_type) {
+        block::AIR => 0,
+        block::TALL_GRASS | block::FERN | block::POPPY | block::DANDELION | block::DEAD_BUSH | block::RED_MUSHROOM
+        | block::BROWN_MUSHROOM | block::SAPLING | block::SUGAR_CANE => 1,
+        block::LEAVES => 2,
+        block::LOG | block::COBBLESTONE | block::MOSSY_COBBLESTONE | block::CACTUS => 3,
+        _ => TERRAIN,
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    block::{self, BlockType},
+    pos::{BlockPos, ChunkPos, Direction},
+    world::World,
+};
+
+// Water and lava, simulated cell by cell with scheduled ticks like Minecraft does.
+// The state bits of a fluid block hold its level, 0 for a source and up to 7 for the
+// thinnest flowing fluid, plus a flag for fluid falling down a column.
+
+const LEVEL_MASK: u32 = 0b111;
+const FALLING: u32 = 0b1000;
+const MAX_LEVEL: u32 = 7;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fluid {
+    Water,
+    Lava,
+}
+
+impl Fluid {
+    pub fn of(block_type: BlockType) -> Option<Fluid> {
+        match block::id_of(block_type) {
+            block::WATER => Some(Fluid::Water),
+            block::LAVA => Some(Fluid::Lava),
+            _ => None,
+        }
+    }
+
+    pub fn block(self, level: u32, falling: bool) -> BlockType {
+        let id = match self {
+            Fluid::Water => block::WATER,
+            Fluid::Lava => block::LAVA,
+        };
+        block::with_state(id, level | if falling { FALLING } else { 0 })
+    }
+
+    pub fn tick_delay(self) -> u64 {
+        match self {
+            Fluid::Water => 5,
+            Fluid::Lava => 30,
+        }
+    }
+
+    // How much the level goes up with each block of horizontal flow
+    fn level_step(self) -> u32 {
+        match self {
+            Fluid::Water => 1,
+            Fluid::Lava => 2,
+        }
+    }
+}
+
+pub fn level(block_type: BlockType) -> u32 {
+    block::state_of(block_type) & LEVEL_MASK
+}
+
+pub fn is_falling(block_type: BlockType) -> bool {
+    block::state_of(block_type) & FALLING != 0
+}
+
+pub fn is_source(block_type: BlockType) -> bool {
+    block::state_of(block_type) == 0
+}
+
+// Height of the surface inside the block, full for falling fluid
+pub fn height(block_type: BlockType) -> f32 {
+    if is_falling(block_type) {
+        1.0
+    } else {
+        (8 - level(block_type)) as f32 / 9.0
+    }
+}
+
+// Falling fluid spreads sideways like a source once it lands
+fn spreading_level(block_type: BlockType) -> u32 {
+    if is_falling(block_type) { 0 } else { level(block_type) }
+}
+
+// Fluid replaces the same fluid only when it's stronger than what's there
+fn strength(block_type: BlockType) -> u32 {
+    if is_falling(block_type) { 8 } else { 8 - level(block_type) }
+}
+
+// Whether `neighbor` hides the face of `block_type` that touches it. Fluids don't hide
+// other blocks since their surface can be lower than a full block, and only hide the
+// sides of the same fluid when they're at least as high.
+pub fn hides_face(block_type: BlockType, neighbor: BlockType, vertical: bool) -> bool {
+    if block::id_of(neighbor) == block::AIR {
+        return false;
+    }
+    match (Fluid::of(block_type), Fluid::of(neighbor)) {
+        (_, None) => true,
+        (Some(fluid), Some(other)) if fluid == other => vertical || height(neighbor) >= height(block_type),
+        _ => false,
+    }
+}
+
+impl World {
+    pub fn fluid_tick(&mut self, pos: BlockPos, block_type: BlockType, dirty_chunks: &mut Vec<ChunkPos>) {
+        let Some(fluid) = Fluid::of(block_type) else {
+            return;
+        };
+
+        // Lava touching water hardens, into obsidian if it was a source
+        if fluid == Fluid::Lava {
+            let touches_water = Direction::HORIZONTAL.iter().chain(&[Direction::Up])
+                .any(|&direction| self.get_block_type(pos.neighbor(direction)).and_then(Fluid::of) == Some(Fluid::Water));
+            if touches_water {
+                let hardened = if is_source(block_type) { block::OBSIDIAN } else { block::COBBLESTONE };
+                self.set_block_from_tick(pos, hardened, dirty_chunks);
+                return;
+            }
+        }
+
+        let mut current = block_type;
+        if !is_source(block_type) {
+            match self.expected_fluid(pos, fluid) {
+                None => {
+                    self.set_block_from_tick(pos, block::AIR, dirty_chunks);
+                    return;
+                }
+                Some(expected) if expected != block_type => {
+                    self.set_block_from_tick(pos, expected, dirty_chunks);
+                    current = expected;
+                }
+                Some(_) => {}
+            }
+        }
+
+        let can_fall = self.fluid_can_fall(pos, fluid);
+        if can_fall {
+            self.flow_into(pos.down(), fluid.block(0, true), fluid, dirty_chunks);
+            // Only sources spread sideways while they can fall, so fluid pours down in a column
+            if !is_source(current) {
+                return;
+            }
+        }
+
+        let next_level = spreading_level(current) + fluid.level_step();
+        if next_level > MAX_LEVEL {
+            return;
+        }
+        for direction in Direction::HORIZONTAL {
+            self.flow_into(pos.neighbor(direction), fluid.block(next_level, false), fluid, dirty_chunks);
+        }
+    }
+
+    // What a flowing block should be given its neighbours, None if nothing feeds it anymore
+    fn expected_fluid(&self, pos: BlockPos, fluid: Fluid) -> Option<BlockType> {
+        let same_fluid = |pos| self.get_block_type(pos).filter(|&b| Fluid::of(b) == Some(fluid));
+
+        if same_fluid(pos.up()).is_some() {
+            return Some(fluid.block(0, true));
+        }
+
+        let mut sources = 0;
+        let mut lowest_level = None;
+        for direction in Direction::HORIZONTAL {
+            let neighbor_pos = pos.neighbor(direction);
+            let Some(neighbor) = same_fluid(neighbor_pos) else {
+                continue;
+            };
+            // Flowing fluid that can fall doesn't feed its sides
+            if !is_source(neighbor) && self.fluid_can_fall(neighbor_pos, fluid) {
+                continue;
+            }
+            if is_source(neighbor) {
+                sources += 1;
+            }
+            let level = spreading_level(neighbor);
+            lowest_level = Some(lowest_level.map_or(level, |lowest: u32| lowest.min(level)));
+        }
+
+        // Water between two sources becomes a source if it's resting on something
+        if fluid == Fluid::Water && sources >= 2 {
+            let below = self.get_block_type(pos.down());
+            if below.is_some_and(|b| block::properties(b).solid || (Fluid::of(b) == Some(fluid) && is_source(b))) {
+                return Some(fluid.block(0, false));
+            }
+        }
+
+        lowest_level
+            .map(|level| level + fluid.level_step())
+            .filter(|&level| level <= MAX_LEVEL)
+            .map(|level| fluid.block(level, false))
+    }
+
+    fn fluid_can_fall(&self, pos: BlockPos, fluid: Fluid) -> bool {
+        match self.get_block_type(pos.down()) {
+            Some(below) if Fluid::of(below) == Some(fluid) => !is_source(below),
+            Some(below) => block::properties(below).replaceable,
+            None => false,
+        }
+    }
+
+    fn flow_into(&mut self, pos: BlockPos, new: BlockType, fluid: Fluid, dirty_chunks: &mut Vec<ChunkPos>) {
+        let Some(old) = self.get_block_type(pos) else {
+            return;
+        };
+        match Fluid::of(old) {
+            Some(other) if other == fluid && (is_source(old) || strength(old) >= strength(new)) => return,
+            Some(other) if other == fluid => {}
+            // Lava flowing into water turns it to stone, water flowing into lava hardens it
+            Some(_) => {
+                let hardened = match fluid {
+                    Fluid::Lava => block::STONE,
+                    Fluid::Water if is_source(old) => block::OBSIDIAN,
+                    Fluid::Water => block::COBBLESTONE,
+                };
+                self.set_block_from_tick(pos, hardened, dirty_chunks);
+                return;
+            }
+            None if !block::properties(old).replaceable => return,
+            None => {}
+        }
+        self.set_block_from_tick(pos, new, dirty_chunks);
+    }
+}
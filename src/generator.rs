@@ -0,0 +1,347 @@
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{
+    biome::{Biome, ClimateNoise},
+    block::{self, BlockType},
+    block_mapping::BlockMapping,
+    cave::Caves,
+    chunk::{WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    feature,
+    ore::Ores,
+    structure::Structures,
+    pos::{BlockPos, ChunkPos, LocalPos},
+    seed,
+    terrain::{TerrainNoise, TerrainSettings},
+    water,
+};
+
+// What fills new chunks. Each world has one generator, picked from its GeneratorSettings
+// when it's created and rebuilt from the settings saved with it. Generators only see the
+// chunk being generated, so chunks can be generated in any order. Features that cross into
+// other chunks are handed back to the world to write.
+
+// Send so the world holding it can move to another thread
+pub trait TerrainGenerator: fmt::Debug + Send {
+    // `blocks` is indexed [x][y - min_y][z] like Chunk::blocks and starts as air
+    fn generate(&self, pos: ChunkPos, height: WorldHeight, blocks: &mut [Vec<Vec<BlockType>>]);
+
+    // Blocks of the trees and such that start in a generated chunk, some can be in the chunks
+    // around it
+    fn decorate(&self, _pos: ChunkPos, _height: WorldHeight, _blocks: &[Vec<Vec<BlockType>>]) -> Vec<(BlockPos, BlockType)> {
+        Vec::new()
+    }
+
+    fn biome(&self, _x: i32, _z: i32) -> Biome {
+        Biome::Plains
+    }
+}
+
+// The generators a world can be created with, saved by name
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum GeneratorSettings {
+    #[default]
+    Default,
+    Amplified,
+    Flat(FlatGenerator),
+    Void,
+    // The default terrain with settings of its own, usually from a JSON file
+    Custom(Box<TerrainSettings>),
+}
+
+impl GeneratorSettings {
+    pub const NAMES: [&str; 5] = ["default", "amplified", "flat", "void", "custom"];
+
+    // A name from NAMES, flat can be followed by a preset: "flat:minecraft:bedrock,2*minecraft:dirt"
+    // and custom has to be followed by terrain settings in JSON: "custom:{\"amplification\":2.0}"
+    pub fn parse(text: &str) -> Result<Self> {
+        let (name, preset) = match text.split_once(':') {
+            Some((name, preset)) => (name, Some(preset)),
+            None => (text, None),
+        };
+        Ok(match (name.trim(), preset) {
+            ("default", None) => GeneratorSettings::Default,
+            ("amplified", None) => GeneratorSettings::Amplified,
+            ("void", None) => GeneratorSettings::Void,
+            ("flat", None) => GeneratorSettings::Flat(FlatGenerator::default()),
+            ("flat", Some(preset)) => GeneratorSettings::Flat(FlatGenerator::parse(preset)?),
+            ("custom", Some(json)) => GeneratorSettings::Custom(Box::new(TerrainSettings::from_json(json)?)),
+            _ => bail!("unknown generator {:?}, expected one of {}", text, Self::NAMES.join(", ")),
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GeneratorSettings::Default => "default",
+            GeneratorSettings::Amplified => "amplified",
+            GeneratorSettings::Flat(_) => "flat",
+            GeneratorSettings::Void => "void",
+            GeneratorSettings::Custom(_) => "custom",
+        }
+    }
+
+    pub fn build(&self, seed: i64) -> Box<dyn TerrainGenerator> {
+        let seed = seed::noise_seed(seed);
+        match self {
+            GeneratorSettings::Default => Box::new(NoiseGenerator::default_terrain(seed)),
+            GeneratorSettings::Amplified => Box::new(NoiseGenerator::amplified(seed)),
+            GeneratorSettings::Flat(flat) => Box::new(flat.clone()),
+            GeneratorSettings::Void => Box::new(VoidGenerator),
+            GeneratorSettings::Custom(settings) => Box::new(NoiseGenerator::new(seed, settings.as_ref().clone())),
+        }
+    }
+}
+
+// Written the way parse reads it
+impl fmt::Display for GeneratorSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorSettings::Flat(flat) => write!(f, "flat:{}", flat),
+            GeneratorSettings::Custom(settings) => write!(f, "custom:{}", settings.to_json()),
+            other => f.write_str(other.name()),
+        }
+    }
+}
+
+// A 2D heightmap shaped by the biomes, with each biome's surface and filler over stone, the
+// sea and lakes, caves and ores underground, structures and its trees and plants on top
+#[derive(Clone, Debug)]
+pub struct NoiseGenerator {
+    seed: u32,
+    terrain: TerrainNoise,
+    climate: ClimateNoise,
+    caves: Caves,
+    ores: Ores,
+    structures: Structures,
+}
+
+// Biome height profiles are blended over the 5 by 5 grid points around a column, this far apart
+const BLEND_SPACING: i32 = 4;
+const BLEND_RADIUS: i32 = 2;
+// Grid points per chunk side, including the one shared with the next chunk
+const BLEND_POINTS: usize = CHUNK_X_SIZE / BLEND_SPACING as usize + 1;
+
+impl NoiseGenerator {
+    pub fn new(seed: u32, settings: TerrainSettings) -> Self {
+        Self {
+            seed,
+            caves: Caves::new(seed, settings.caves.clone()),
+            ores: Ores::new(seed, settings.ores.clone()),
+            structures: Structures::new(seed, settings.structures.clone(), settings.templates.clone()),
+            terrain: TerrainNoise::new(seed, settings),
+            climate: ClimateNoise::new(seed),
+        }
+    }
+
+    pub fn default_terrain(seed: u32) -> Self {
+        Self::new(seed, TerrainSettings::default())
+    }
+
+    // Much taller hills and mountains
+    pub fn amplified(seed: u32) -> Self {
+        Self::new(seed, TerrainSettings::amplified())
+    }
+
+    // The y of the top block of a column
+    pub fn surface_height(&self, x: i32, z: i32) -> i32 {
+        let pos = BlockPos::new(x, 0, z);
+        let local = pos.local();
+        self.height_profiles(pos.chunk()).surface(self, pos.x, pos.z, local.x as usize, local.z as usize)
+    }
+
+    // The height profile of every biome around each grid point of the chunk, averaged
+    fn height_profiles(&self, pos: ChunkPos) -> HeightProfiles {
+        // Biomes of every grid point within the blend radius of the chunk's points
+        let size = BLEND_POINTS + 2 * BLEND_RADIUS as usize;
+        let origin = pos.min_block().offset(-BLEND_RADIUS * BLEND_SPACING, 0, -BLEND_RADIUS * BLEND_SPACING);
+        let profiles: Vec<Vec<[f64; 3]>> = (0..size).map(|i| {
+            (0..size).map(|j| {
+                let biome = self.climate.biome(origin.x + i as i32 * BLEND_SPACING, origin.z + j as i32 * BLEND_SPACING);
+                let properties = biome.properties();
+                [properties.base_height, properties.amplitude, properties.ruggedness]
+            }).collect()
+        }).collect();
+
+        let window = (2 * BLEND_RADIUS + 1) as usize;
+        let points = std::array::from_fn(|i| std::array::from_fn(|j| {
+            let mut total = [0.0; 3];
+            for column in &profiles[i..i + window] {
+                for profile in &column[j..j + window] {
+                    for (total, value) in total.iter_mut().zip(profile) {
+                        *total += value;
+                    }
+                }
+            }
+            total.map(|t| t / (window * window) as f64)
+        }));
+        HeightProfiles { points }
+    }
+}
+
+struct HeightProfiles {
+    // Base height, amplitude and ruggedness at every grid point of a chunk, indexed [x][z]
+    points: [[[f64; 3]; BLEND_POINTS]; BLEND_POINTS],
+}
+
+impl HeightProfiles {
+    // Interpolates between the grid points around a column
+    fn surface(&self, generator: &NoiseGenerator, x: i32, z: i32, local_x: usize, local_z: usize) -> i32 {
+        let spacing = BLEND_SPACING as usize;
+        let (gx, gz) = ((local_x / spacing).min(BLEND_POINTS - 2), (local_z / spacing).min(BLEND_POINTS - 2));
+        let (tx, tz) = ((local_x - gx * spacing) as f64 / spacing as f64, (local_z - gz * spacing) as f64 / spacing as f64);
+        let profile = [0, 1, 2].map(|k| {
+            let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+            let near = lerp(self.points[gx][gz][k], self.points[gx + 1][gz][k], tx);
+            let far = lerp(self.points[gx][gz + 1][k], self.points[gx + 1][gz + 1][k], tx);
+            lerp(near, far, tz)
+        });
+        generator.terrain.height(x, z, profile).floor() as i32
+    }
+}
+
+impl TerrainGenerator for NoiseGenerator {
+    fn generate(&self, pos: ChunkPos, height: WorldHeight, blocks: &mut [Vec<Vec<BlockType>>]) {
+        let profiles = self.height_profiles(pos);
+        let sea_level = self.terrain.settings.sea_level;
+        let mut surfaces = [[0; CHUNK_Z_SIZE]; CHUNK_X_SIZE];
+        for (x, plane) in blocks.iter_mut().enumerate() {
+            for z in 0..CHUNK_Z_SIZE {
+                let world_pos = pos.block(LocalPos::new(x as i32, 0, z as i32));
+                let surface = profiles.surface(self, world_pos.x, world_pos.z, x, z);
+                surfaces[x][z] = surface;
+                let biome = self.climate.biome(world_pos.x, world_pos.z).properties();
+                let (surface_block, filler) = water::shore_blocks(biome, surface, sea_level);
+                let stone_top = surface - 1 - biome.filler_depth;
+                for (row, y) in plane.iter_mut().zip(height.ys()) {
+                    row[z] = if y <= stone_top {
+                        block::STONE
+                    } else if y < surface {
+                        filler
+                    } else if y == surface {
+                        surface_block
+                    } else if y <= sea_level {
+                        block::WATER
+                    } else {
+                        block::AIR
+                    };
+                }
+            }
+        }
+
+        water::fill_lake(self.seed, pos, height, sea_level, &mut surfaces, blocks);
+        self.caves.carve(pos, height, &surfaces, blocks);
+        self.ores.place(pos, height, blocks);
+        self.structures.place(pos, height, sea_level, &|x, z| self.surface_height(x, z), blocks);
+    }
+
+    // Features only grow on the biome's surface block, not on the floor of a cave opening
+    fn decorate(&self, pos: ChunkPos, height: WorldHeight, blocks: &[Vec<Vec<BlockType>>]) -> Vec<(BlockPos, BlockType)> {
+        let mut features = Vec::new();
+        for (x, plane) in blocks.iter().enumerate() {
+            for z in 0..CHUNK_Z_SIZE {
+                let Some(top) = plane.iter().rposition(|row| row[z] != block::AIR) else {
+                    continue;
+                };
+                let ground = pos.block(LocalPos::new(x as i32, height.min_y + top as i32, z as i32));
+                let biome = self.climate.biome(ground.x, ground.z).properties();
+                if plane[top][z] == biome.surface {
+                    features.extend(feature::column_features(self.seed, biome, ground));
+                }
+            }
+        }
+        features
+    }
+
+    fn biome(&self, x: i32, z: i32) -> Biome {
+        self.climate.biome(x, z)
+    }
+}
+
+// Layers of blocks from the bottom of the world up, like Minecraft's superflat
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlatGenerator {
+    // How many blocks thick and of what
+    pub layers: Vec<(usize, BlockType)>,
+}
+
+impl Default for FlatGenerator {
+    fn default() -> Self {
+        Self { layers: vec![(1, block::BEDROCK), (2, block::DIRT), (1, block::GRASS)] }
+    }
+}
+
+impl FlatGenerator {
+    // Minecraft's preset format, bottom layer first: "minecraft:bedrock,2*minecraft:dirt,minecraft:grass_block".
+    // The namespace is optional, block ids work too, and a biome after ';' is ignored.
+    pub fn parse(preset: &str) -> Result<Self> {
+        let mapping = BlockMapping::default();
+        let layers = preset.split(';').next().unwrap();
+        let mut parsed = Vec::new();
+        for layer in layers.split(',').map(str::trim).filter(|layer| !layer.is_empty()) {
+            let (count, name) = match layer.split_once('*') {
+                Some((count, name)) => {
+                    let count = count.trim().parse().map_err(|_| anyhow!("invalid layer count in {:?}", layer))?;
+                    (count, name.trim())
+                }
+                None => (1, layer),
+            };
+            let block_type = match name.parse::<BlockType>() {
+                Ok(id) => Some(id),
+                Err(_) if name.contains(':') => mapping.lookup(name),
+                Err(_) => mapping.lookup(&format!("minecraft:{}", name)),
+            };
+            let block_type = block_type.ok_or_else(|| anyhow!("unknown block {}", name))?;
+            parsed.push((count, block_type));
+        }
+        if parsed.is_empty() {
+            bail!("the flat preset has no layers");
+        }
+        Ok(Self { layers: parsed })
+    }
+}
+
+impl fmt::Display for FlatGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mapping = BlockMapping::default();
+        let layers: Vec<String> = self.layers.iter().map(|&(count, block_type)| {
+            let name = mapping.name_of(block_type);
+            if count == 1 { name } else { format!("{}*{}", count, name) }
+        }).collect();
+        f.write_str(&layers.join(","))
+    }
+}
+
+impl TerrainGenerator for FlatGenerator {
+    fn generate(&self, _pos: ChunkPos, height: WorldHeight, blocks: &mut [Vec<Vec<BlockType>>]) {
+        let column = self.layers.iter().flat_map(|&(count, block_type)| std::iter::repeat_n(block_type, count));
+        for (i, block_type) in column.take(height.height).enumerate() {
+            for row in blocks.iter_mut() {
+                row[i].fill(block_type);
+            }
+        }
+    }
+}
+
+// Only air, apart from a small stone platform at the origin to stand on
+#[derive(Clone, Copy, Debug)]
+pub struct VoidGenerator;
+
+const VOID_PLATFORM_Y: i32 = 63;
+const VOID_PLATFORM_RADIUS: i32 = 2;
+
+impl TerrainGenerator for VoidGenerator {
+    fn generate(&self, pos: ChunkPos, height: WorldHeight, blocks: &mut [Vec<Vec<BlockType>>]) {
+        let Some(y) = height.index(VOID_PLATFORM_Y) else {
+            return;
+        };
+        for (x, plane) in blocks.iter_mut().enumerate() {
+            for (z, block_type) in plane[y].iter_mut().enumerate() {
+                let world_pos = pos.block(LocalPos::new(x as i32, 0, z as i32));
+                if world_pos.x.abs() <= VOID_PLATFORM_RADIUS && world_pos.z.abs() <= VOID_PLATFORM_RADIUS {
+                    *block_type = block::STONE;
+                }
+            }
+        }
+    }
+}
@@ -11,6 +11,11 @@ use egui_winit::winit::event::WindowEvent;
 use egui_winit::winit::window::Window;
 use wgpu::util::DeviceExt;
 
+use crate::edit::{PasteTransform, Selection};
+use crate::generator::GeneratorSettings;
+use crate::pos::{BlockPos, Direction};
+use crate::volume::BlockVolume;
+
 pub struct EguiRenderer {
     pub context: Context,
     state: State,
@@ -230,3 +235,375 @@ impl BlockRenderResources {
         }
     }
 }
+
+pub enum WorldMenuAction {
+    NewWorld,
+    SaveWorld,
+    LoadWorld,
+    ImportRegion,
+    PasteStructure,
+    ExportStructure,
+}
+
+#[derive(Default)]
+pub struct WorldMenu {
+    pub save_path: String,
+    pub region_path: String,
+    pub mapping_path: String,
+    pub structure_path: String,
+    pub color_mapping_path: String,
+    pub new_seed: String,
+    // Index in GeneratorSettings::NAMES
+    pub new_generator: usize,
+    // Layers for the flat generator, empty for the default ones
+    pub flat_preset: String,
+    // JSON terrain settings for the custom generator
+    pub terrain_settings_path: String,
+    pub status: String,
+}
+
+impl WorldMenu {
+    // The settings picked in the New world section
+    pub fn generator_settings(&self) -> anyhow::Result<GeneratorSettings> {
+        let name = GeneratorSettings::NAMES[self.new_generator];
+        if name == "flat" && !self.flat_preset.trim().is_empty() {
+            GeneratorSettings::parse(&format!("flat:{}", self.flat_preset.trim()))
+        } else if name == "custom" {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let path = std::path::Path::new(self.terrain_settings_path.trim());
+                Ok(GeneratorSettings::Custom(Box::new(crate::terrain::TerrainSettings::load(path)?)))
+            }
+            #[cfg(target_arch = "wasm32")]
+            anyhow::bail!("custom terrain settings can't be loaded on the web build")
+        } else {
+            GeneratorSettings::parse(name)
+        }
+    }
+
+    pub fn show(&mut self, ctx: &Context) -> Option<WorldMenuAction> {
+        let mut action = None;
+
+        egui::Window::new("World")
+            .default_pos([10.0, 400.0])
+            .show(ctx, |ui| {
+                ui.heading("New world");
+                ui.separator();
+                egui::Grid::new("new_world").num_columns(2).show(ui, |ui| {
+                    ui.label("Seed");
+                    ui.text_edit_singleline(&mut self.new_seed).on_hover_text("A number or any text, empty for 0");
+                    ui.end_row();
+                    ui.label("Generator");
+                    egui::ComboBox::from_id_salt("generator")
+                        .selected_text(GeneratorSettings::NAMES[self.new_generator])
+                        .show_ui(ui, |ui| {
+                            for (i, name) in GeneratorSettings::NAMES.iter().enumerate() {
+                                ui.selectable_value(&mut self.new_generator, i, *name);
+                            }
+                        });
+                    ui.end_row();
+                    if GeneratorSettings::NAMES[self.new_generator] == "flat" {
+                        ui.label("Layers");
+                        ui.text_edit_singleline(&mut self.flat_preset)
+                            .on_hover_text("Bottom first, like minecraft:bedrock,2*minecraft:dirt,minecraft:grass_block");
+                        ui.end_row();
+                    }
+                    if GeneratorSettings::NAMES[self.new_generator] == "custom" {
+                        ui.label("Terrain settings (.json)");
+                        ui.text_edit_singleline(&mut self.terrain_settings_path)
+                            .on_hover_text("Noise, spline and warping parameters, see res/terrain.json");
+                        ui.end_row();
+                    }
+                });
+                if ui.button("Create").clicked() {
+                    action = Some(WorldMenuAction::NewWorld);
+                }
+
+                ui.separator();
+                ui.heading("Save");
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Save file");
+                    ui.text_edit_singleline(&mut self.save_path);
+                });
+                if cfg!(target_arch = "wasm32") {
+                    ui.label("Saving isn't available on the web build");
+                } else {
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            action = Some(WorldMenuAction::SaveWorld);
+                        }
+                        if ui.button("Load").clicked() {
+                            action = Some(WorldMenuAction::LoadWorld);
+                        }
+                    });
+                }
+
+                ui.separator();
+                ui.heading("Import Minecraft region");
+                ui.separator();
+                egui::Grid::new("import_region").num_columns(2).show(ui, |ui| {
+                    ui.label("Region file (.mca)");
+                    ui.text_edit_singleline(&mut self.region_path);
+                    ui.end_row();
+                    ui.label("Block mapping (optional)");
+                    ui.text_edit_singleline(&mut self.mapping_path);
+                    ui.end_row();
+                });
+                if cfg!(target_arch = "wasm32") {
+                    ui.label("Importing files isn't available on the web build");
+                } else if ui.button("Import").clicked() {
+                    action = Some(WorldMenuAction::ImportRegion);
+                }
+
+                ui.separator();
+                ui.heading("Structures (.vox, .schem)");
+                ui.separator();
+                egui::Grid::new("structures").num_columns(2).show(ui, |ui| {
+                    ui.label("Structure file");
+                    ui.text_edit_singleline(&mut self.structure_path);
+                    ui.end_row();
+                    ui.label("Colour mapping (optional)");
+                    ui.text_edit_singleline(&mut self.color_mapping_path);
+                    ui.end_row();
+                });
+                ui.label("Pasting uses the transform of the Edit window, exporting saves the selection");
+                if !cfg!(target_arch = "wasm32") {
+                    ui.horizontal(|ui| {
+                        if ui.button("Paste at target").clicked() {
+                            action = Some(WorldMenuAction::PasteStructure);
+                        }
+                        if ui.button("Export selection").clicked() {
+                            action = Some(WorldMenuAction::ExportStructure);
+                        }
+                    });
+                }
+                if !self.status.is_empty() {
+                    ui.separator();
+                    ui.label(&self.status);
+                }
+            });
+
+        action
+    }
+}
+
+pub enum EditAction {
+    SetPos1,
+    SetPos2,
+    Fill,
+    Replace,
+    Hollow,
+    Walls,
+    Copy,
+    Cut,
+    Paste,
+    Stack,
+    Undo,
+    Redo,
+}
+
+pub const STACK_DIRECTIONS: [(&str, Direction); 6] = [
+    ("Up", Direction::Up),
+    ("Down", Direction::Down),
+    ("North (-z)", Direction::North),
+    ("South (+z)", Direction::South),
+    ("West (-x)", Direction::West),
+    ("East (+x)", Direction::East),
+];
+
+pub struct EditMenu {
+    pub block: u32,
+    pub replace_from: u32,
+    pub transform: PasteTransform,
+    pub stack_count: u32,
+    pub stack_direction: usize,
+    pub status: String,
+}
+
+impl Default for EditMenu {
+    fn default() -> Self {
+        Self {
+            block: 1,
+            replace_from: 1,
+            transform: PasteTransform::default(),
+            stack_count: 1,
+            stack_direction: 0,
+            status: String::new(),
+        }
+    }
+}
+
+impl EditMenu {
+    pub fn show(&mut self, ctx: &Context, selection: &Selection, clipboard: Option<&BlockVolume>) -> Option<EditAction> {
+        let mut action = None;
+
+        egui::Window::new("Edit")
+            .default_pos([400.0, 10.0])
+            .show(ctx, |ui| {
+                ui.heading("Selection");
+                ui.separator();
+                let format_pos = |pos: Option<BlockPos>| match pos {
+                    Some(p) => p.to_string(),
+                    None => "not set".to_string(),
+                };
+                ui.horizontal(|ui| {
+                    ui.label(format!("Pos 1: {}", format_pos(selection.pos1)));
+                    if ui.button("Set to target").clicked() {
+                        action = Some(EditAction::SetPos1);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label(format!("Pos 2: {}", format_pos(selection.pos2)));
+                    if ui.button("Set to target").clicked() {
+                        action = Some(EditAction::SetPos2);
+                    }
+                });
+                if let Some(size) = selection.size() {
+                    ui.label(format!("Size: {}x{}x{}", size[0], size[1], size[2]));
+                }
+
+                ui.separator();
+                egui::Grid::new("edit_blocks").num_columns(2).show(ui, |ui| {
+                    ui.label("Block");
+                    ui.add(egui::DragValue::new(&mut self.block).range(0..=256));
+                    ui.end_row();
+                    ui.label("Replace");
+                    ui.add(egui::DragValue::new(&mut self.replace_from).range(0..=256));
+                    ui.end_row();
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Fill").clicked() {
+                        action = Some(EditAction::Fill);
+                    }
+                    if ui.button("Replace").clicked() {
+                        action = Some(EditAction::Replace);
+                    }
+                    if ui.button("Hollow").clicked() {
+                        action = Some(EditAction::Hollow);
+                    }
+                    if ui.button("Walls").clicked() {
+                        action = Some(EditAction::Walls);
+                    }
+                });
+
+                ui.separator();
+                ui.heading("Clipboard");
+                match clipboard {
+                    Some(volume) => ui.label(format!("{}x{}x{} blocks", volume.size[0], volume.size[1], volume.size[2])),
+                    None => ui.label("Empty"),
+                };
+                ui.horizontal(|ui| {
+                    if ui.button("Copy").clicked() {
+                        action = Some(EditAction::Copy);
+                    }
+                    if ui.button("Cut").clicked() {
+                        action = Some(EditAction::Cut);
+                    }
+                    if ui.button("Paste at target").clicked() {
+                        action = Some(EditAction::Paste);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Rotation")
+                        .selected_text(format!("{}°", self.transform.rotation * 90))
+                        .show_ui(ui, |ui| {
+                            for rotation in 0..4 {
+                                ui.selectable_value(&mut self.transform.rotation, rotation, format!("{}°", rotation * 90));
+                            }
+                        });
+                    ui.checkbox(&mut self.transform.mirror_x, "Mirror x");
+                    ui.checkbox(&mut self.transform.mirror_z, "Mirror z");
+                });
+                ui.checkbox(&mut self.transform.include_air, "Paste air blocks");
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Stack").clicked() {
+                        action = Some(EditAction::Stack);
+                    }
+                    ui.add(egui::DragValue::new(&mut self.stack_count).range(1..=64));
+                    ui.label("times");
+                    egui::ComboBox::from_id_salt("stack_direction")
+                        .selected_text(STACK_DIRECTIONS[self.stack_direction].0)
+                        .show_ui(ui, |ui| {
+                            for (i, (name, _)) in STACK_DIRECTIONS.iter().enumerate() {
+                                ui.selectable_value(&mut self.stack_direction, i, *name);
+                            }
+                        });
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Undo (Ctrl+Z)").clicked() {
+                        action = Some(EditAction::Undo);
+                    }
+                    if ui.button("Redo (Ctrl+Y)").clicked() {
+                        action = Some(EditAction::Redo);
+                    }
+                });
+
+                if !self.status.is_empty() {
+                    ui.separator();
+                    ui.label(&self.status);
+                }
+            });
+
+        action
+    }
+}
+
+// Keeps this many lines of output, older ones scroll away
+const CONSOLE_LINES: usize = 100;
+
+#[derive(Default)]
+pub struct Console {
+    pub input: String,
+    pub output: Vec<String>,
+    // Set to move the keyboard focus to the input on the next frame
+    pub focus: bool,
+}
+
+impl Console {
+    // Returns the line entered, if Enter was pressed
+    pub fn show(&mut self, ctx: &Context) -> Option<String> {
+        let mut submitted = None;
+
+        egui::Window::new("Console")
+            .default_pos([10.0, 700.0])
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(150.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in &self.output {
+                            ui.monospace(line);
+                        }
+                    });
+                ui.separator();
+                let response = ui.text_edit_singleline(&mut self.input);
+                if self.focus {
+                    response.request_focus();
+                    self.focus = false;
+                }
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    let line = std::mem::take(&mut self.input);
+                    if !line.trim().is_empty() {
+                        self.print(format!("> {}", line.trim()));
+                        submitted = Some(line);
+                    }
+                    response.request_focus();
+                }
+            });
+
+        submitted
+    }
+
+    pub fn print(&mut self, text: impl Into<String>) {
+        for line in text.into().lines() {
+            self.output.push(line.to_string());
+        }
+        let extra = self.output.len().saturating_sub(CONSOLE_LINES);
+        self.output.drain(..extra);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{
+    block::{self, BlockType},
+    chunk::{WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    fluid::Fluid,
+};
+
+// The top of each column of a chunk, for the things that keep asking for the ground under a
+// point: spawning the player, light from the sky, maps. Built when a chunk is generated or
+// loaded and kept up to date by every block change, so lookups don't scan the column. A
+// change only scans down from the old top when it removes that top block.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Heightmap {
+    // Any block but air
+    WorldSurface,
+    // Solid blocks and fluids, what stops a falling player or item
+    MotionBlocking,
+    // Solid blocks only, the bed under any water
+    OceanFloor,
+}
+
+impl Heightmap {
+    pub const ALL: [Heightmap; 3] = [Heightmap::WorldSurface, Heightmap::MotionBlocking, Heightmap::OceanFloor];
+
+    pub fn counts(self, block_type: BlockType) -> bool {
+        match self {
+            Heightmap::WorldSurface => block::id_of(block_type) != block::AIR,
+            Heightmap::MotionBlocking => block::properties(block_type).solid || Fluid::of(block_type).is_some(),
+            Heightmap::OceanFloor => block::properties(block_type).solid,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Heightmaps {
+    height: WorldHeight,
+    // The y of the top block of each column for each heightmap, in the order of Heightmap::ALL
+    // and indexed x * 16 + z. Columns without one hold min_y - 1.
+    tops: [Vec<i32>; 3],
+}
+
+impl Heightmaps {
+    // `blocks` is indexed [x][y - min_y][z] like Chunk::blocks
+    pub fn new(height: WorldHeight, blocks: &[Vec<Vec<BlockType>>]) -> Self {
+        let mut heightmaps = Self { height, tops: std::array::from_fn(|_| vec![height.min_y - 1; CHUNK_X_SIZE * CHUNK_Z_SIZE]) };
+        for x in 0..CHUNK_X_SIZE {
+            for z in 0..CHUNK_Z_SIZE {
+                for (i, heightmap) in Heightmap::ALL.into_iter().enumerate() {
+                    heightmaps.tops[i][x * CHUNK_Z_SIZE + z] = heightmaps.scan(heightmap, blocks, x, height.max_y() - 1, z);
+                }
+            }
+        }
+        heightmaps
+    }
+
+    // The y of the top block of a column, None when the column has none
+    pub fn get(&self, heightmap: Heightmap, x: usize, z: usize) -> Option<i32> {
+        let top = self.tops[heightmap as usize][x * CHUNK_Z_SIZE + z];
+        (top >= self.height.min_y).then_some(top)
+    }
+
+    // Called once the block at local x, y, z of `blocks` is set to `block_type`
+    pub fn update(&mut self, blocks: &[Vec<Vec<BlockType>>], [x, y, z]: [usize; 3], block_type: BlockType) {
+        let y = self.height.min_y + y as i32;
+        for (i, heightmap) in Heightmap::ALL.into_iter().enumerate() {
+            let top = self.tops[i][x * CHUNK_Z_SIZE + z];
+            if heightmap.counts(block_type) {
+                if y > top {
+                    self.tops[i][x * CHUNK_Z_SIZE + z] = y;
+                }
+            } else if y == top {
+                self.tops[i][x * CHUNK_Z_SIZE + z] = self.scan(heightmap, blocks, x, y - 1, z);
+            }
+        }
+    }
+
+    // The highest block from `from` down that counts for the heightmap
+    fn scan(&self, heightmap: Heightmap, blocks: &[Vec<Vec<BlockType>>], x: usize, from: i32, z: usize) -> i32 {
+        (self.height.min_y..=from)
+            .rev()
+            .find(|&y| heightmap.counts(blocks[x][(y - self.height.min_y) as usize][z]))
+            .unwrap_or(self.height.min_y - 1)
+    }
+}
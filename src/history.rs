@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+
+use crate::{block::BlockType, pos::BlockPos};
+
+// Undo/redo for block edits. Every change made through World::set_blocks is recorded,
+// and one call to set_blocks (a click, a fill, a paste...) is one entry in the history.
+
+pub const DEFAULT_HISTORY_SIZE: usize = 100;
+
+#[derive(Clone, Copy, Debug)]
+pub struct BlockChange {
+    pub pos: BlockPos,
+    pub old: BlockType,
+    pub new: BlockType,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ChangeSet {
+    pub changes: Vec<BlockChange>,
+}
+
+impl ChangeSet {
+    // The changes to apply to go back to the state before this set, latest first
+    pub fn reverted(&self) -> impl Iterator<Item = (BlockPos, BlockType)> + '_ {
+        self.changes.iter().rev().map(|change| (change.pos, change.old))
+    }
+
+    pub fn applied(&self) -> impl Iterator<Item = (BlockPos, BlockType)> + '_ {
+        self.changes.iter().map(|change| (change.pos, change.new))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct History {
+    undo_stack: VecDeque<ChangeSet>,
+    redo_stack: Vec<ChangeSet>,
+    current: Option<ChangeSet>,
+    max_size: usize,
+    // Set while undoing or redoing so those changes don't get recorded as new actions
+    paused: bool,
+}
+
+impl History {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            current: None,
+            max_size,
+            paused: false,
+        }
+    }
+
+    pub fn begin_action(&mut self) {
+        if !self.paused && self.current.is_none() {
+            self.current = Some(ChangeSet::default());
+        }
+    }
+
+    pub fn record(&mut self, pos: BlockPos, old: BlockType, new: BlockType) {
+        if let Some(current) = &mut self.current {
+            current.changes.push(BlockChange { pos, old, new });
+        }
+    }
+
+    pub fn end_action(&mut self) {
+        let Some(current) = self.current.take() else {
+            return;
+        };
+        if current.changes.is_empty() {
+            return;
+        }
+        self.redo_stack.clear();
+        self.undo_stack.push_back(current);
+        while self.undo_stack.len() > self.max_size {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    // The caller applies the returned set, then hands it back with finish_undo
+    pub fn start_undo(&mut self) -> Option<ChangeSet> {
+        let set = self.undo_stack.pop_back()?;
+        self.paused = true;
+        Some(set)
+    }
+
+    pub fn finish_undo(&mut self, set: ChangeSet) {
+        self.paused = false;
+        self.redo_stack.push(set);
+    }
+
+    pub fn start_redo(&mut self) -> Option<ChangeSet> {
+        let set = self.redo_stack.pop()?;
+        self.paused = true;
+        Some(set)
+    }
+
+    pub fn finish_redo(&mut self, set: ChangeSet) {
+        self.paused = false;
+        self.undo_stack.push_back(set);
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.current = None;
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_SIZE)
+    }
+}
@@ -1,11 +1,11 @@
-use std::{iter, sync::Arc};
+use std::{iter, sync::{Arc, Mutex}};
 
 use wgpu::util::DeviceExt;
 use winit::{
     application::ApplicationHandler,
     event::*,
     event_loop::{ActiveEventLoop, EventLoop},
-    keyboard::{KeyCode, PhysicalKey},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
     window::Window,
 };
 
@@ -20,10 +20,40 @@ mod model;
 mod resources;
 mod texture;
 
-mod world;
+pub mod world;
 mod texture_atlas;
-mod block;
-mod chunk;
+mod renderer;
+pub mod biome;
+pub mod block;
+pub mod cave;
+pub mod chunk;
+pub mod generator;
+pub mod command;
+pub mod dimension;
+pub mod physics;
+pub mod pos;
+pub mod preview;
+
+pub mod nbt;
+pub mod ore;
+pub mod block_mapping;
+pub mod anvil;
+pub mod volume;
+pub mod edit;
+pub mod events;
+pub mod feature;
+pub mod heightmap;
+pub mod history;
+pub mod terrain;
+pub mod tick;
+pub mod entity;
+pub mod fluid;
+pub mod save;
+pub mod seed;
+pub mod vox;
+pub mod water;
+pub mod schem;
+pub mod structure;
 
 mod gui;
 
@@ -164,10 +194,19 @@ pub struct State {
 
     // obj_model: model::Model,
 
-    world: world::World,
+    dimensions: dimension::Dimensions,
+    world_renderer: renderer::WorldRenderer,
 
     mouse_pressed: bool,
     // cursor_locked: bool,
+
+    world_menu: gui::WorldMenu,
+    edit_menu: gui::EditMenu,
+    selection: edit::Selection,
+    clipboard: Option<volume::BlockVolume>,
+    modifiers: ModifiersState,
+    block_stats: Arc<Mutex<events::BlockStats>>,
+    console: gui::Console,
 }
 
 impl State {
@@ -304,7 +343,7 @@ impl State {
 
         // let camera_controller = camera::CameraController::new(4.0, 0.4);
 
-        let player = player::Player::new([0.0, 100.0, 10.0], &config);
+        let mut player = player::Player::new([0.0, 100.0, 10.0], &config);
 
         let mut camera_uniform = CameraUniform::new();
         // camera_uniform.update_view_proj(&camera);
@@ -441,7 +480,12 @@ impl State {
         //         .await
         //         .unwrap();
 
-        let world = world::World::new(&device, &queue, 0x1f6c2);
+        let mut world = world::World::new(0x1f6c2);
+        let world_renderer = renderer::WorldRenderer::new(&device, &queue);
+        let block_stats = Arc::new(Mutex::new(events::BlockStats::default()));
+        subscribe_stats(&mut world, &block_stats);
+        player.respawn(world.spawn);
+        let dimensions = dimension::Dimensions::new(dimension::OVERWORLD, world, dimension::Sky::default());
 
         let mut egui_renderer = gui::EguiRenderer::new(
             &device,
@@ -500,35 +544,35 @@ impl State {
             
             let vertices = vec![
                 // Front face
-                BlockVertex { position: [-0.5, -0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[3]] },
-                BlockVertex { position: [ 0.5, -0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[3]] },
-                BlockVertex { position: [ 0.5,  0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[1]] },
-                BlockVertex { position: [-0.5,  0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[1]] },
+                BlockVertex { position: [-0.5, -0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [ 0.5, -0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [ 0.5,  0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[1]], color: block::NO_TINT },
+                BlockVertex { position: [-0.5,  0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[1]], color: block::NO_TINT },
                 // Back face
-                BlockVertex { position: [ 0.5, -0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[3]] },
-                BlockVertex { position: [-0.5, -0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[3]] },
-                BlockVertex { position: [-0.5,  0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[1]] },
-                BlockVertex { position: [ 0.5,  0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[1]] },
+                BlockVertex { position: [ 0.5, -0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [-0.5, -0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [-0.5,  0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[1]], color: block::NO_TINT },
+                BlockVertex { position: [ 0.5,  0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[1]], color: block::NO_TINT },
                 // Left face
-                BlockVertex { position: [-0.5, -0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[3]] },
-                BlockVertex { position: [-0.5, -0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[3]] },
-                BlockVertex { position: [-0.5,  0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[1]] },
-                BlockVertex { position: [-0.5,  0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[1]] },
+                BlockVertex { position: [-0.5, -0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [-0.5, -0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [-0.5,  0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[1]], color: block::NO_TINT },
+                BlockVertex { position: [-0.5,  0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[1]], color: block::NO_TINT },
                 // Right face
-                BlockVertex { position: [ 0.5, -0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[3]] },
-                BlockVertex { position: [ 0.5, -0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[3]] },
-                BlockVertex { position: [ 0.5,  0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[1]] },
-                BlockVertex { position: [ 0.5,  0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[1]] },
+                BlockVertex { position: [ 0.5, -0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [ 0.5, -0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [ 0.5,  0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[1]], color: block::NO_TINT },
+                BlockVertex { position: [ 0.5,  0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[1]], color: block::NO_TINT },
                 // Top face
-                BlockVertex { position: [-0.5,  0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[3]] },
-                BlockVertex { position: [ 0.5,  0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[3]] },
-                BlockVertex { position: [ 0.5,  0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[1]] },
-                BlockVertex { position: [-0.5,  0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[1]] },
+                BlockVertex { position: [-0.5,  0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [ 0.5,  0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [ 0.5,  0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[1]], color: block::NO_TINT },
+                BlockVertex { position: [-0.5,  0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[1]], color: block::NO_TINT },
                 // Bottom face
-                BlockVertex { position: [-0.5, -0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[3]] },
-                BlockVertex { position: [ 0.5, -0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[3]] },
-                BlockVertex { position: [ 0.5, -0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[1]] },
-                BlockVertex { position: [-0.5, -0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[1]] },
+                BlockVertex { position: [-0.5, -0.5, -0.5], tex_coords: [tex_coords[0], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [ 0.5, -0.5, -0.5], tex_coords: [tex_coords[2], tex_coords[3]], color: block::NO_TINT },
+                BlockVertex { position: [ 0.5, -0.5,  0.5], tex_coords: [tex_coords[2], tex_coords[1]], color: block::NO_TINT },
+                BlockVertex { position: [-0.5, -0.5,  0.5], tex_coords: [tex_coords[0], tex_coords[1]], color: block::NO_TINT },
             ];
             
             let indices: Vec<u32> = (0..6)
@@ -585,7 +629,7 @@ impl State {
 
         egui_renderer.set_block_render_resources(
             ui_render_pipeline,
-            world.texture_atlas.diffuse_bind_group.clone(),
+            world_renderer.texture_atlas.diffuse_bind_group.clone(),
             ui_camera_bind_group,
             block_meshes,
         );
@@ -615,9 +659,17 @@ impl State {
             // instance_buffer,
             depth_texture,
             // obj_model,
-            world: world,
+            dimensions,
+            world_renderer,
             mouse_pressed: false,
             // cursor_locked: false,
+            world_menu: gui::WorldMenu::default(),
+            edit_menu: gui::EditMenu::default(),
+            selection: edit::Selection::default(),
+            clipboard: None,
+            modifiers: ModifiersState::empty(),
+            block_stats,
+            console: gui::Console::default(),
         })
     }
 
@@ -672,8 +724,8 @@ impl State {
                         self.lock_cursor();
                     }
 
-                    if let Some(pos) = self.player.get_block_pointed_at(&self.world.chunks) {
-                        self.world.break_block(&self.device, pos);
+                    if let Some(pos) = self.player.get_block_pointed_at(self.dimensions.world()) {
+                        self.dimensions.world_mut().break_block(pos);
                     }
 
                 }
@@ -685,8 +737,12 @@ impl State {
                     //     self.lock_cursor();
                     // }
                     
-                    if let Some(pos) = self.player.get_block_placement_pos(&self.world.chunks) {
-                        self.world.place_block(&self.device, pos, self.player.selected_block);
+                    // Not into the player's own body
+                    if let Some(pos) = self.player.get_block_placement_pos(self.dimensions.world())
+                        && !(block::properties(self.player.selected_block).solid
+                            && physics::Aabb::block(pos).intersects(&self.player.body.aabb()))
+                    {
+                        self.dimensions.world_mut().place_block(pos, self.player.selected_block);
                     }
                 }
             }
@@ -694,10 +750,6 @@ impl State {
         }
     }
 
-    fn handle_mouse_scroll(&mut self, delta: &MouseScrollDelta) {
-        self.player.camera_controller.handle_mouse_scroll(delta);
-    }
-
     fn lock_cursor(&mut self) {
         self.player.cursor_locked = true;
         self.window.set_cursor_visible(false);
@@ -739,9 +791,244 @@ impl State {
         }
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn import_region(&mut self, region_path: &std::path::Path, mapping_path: Option<&std::path::Path>) -> anyhow::Result<usize> {
+        let mapping = load_block_mapping(mapping_path)?;
+        self.dimensions.world_mut().import_anvil_region(region_path, &mapping)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn paste_structure(&mut self) -> anyhow::Result<[usize; 3]> {
+        let block_mapping = load_block_mapping(non_empty_path(&self.world_menu.mapping_path).as_deref())?;
+        let color_mapping = load_color_mapping(non_empty_path(&self.world_menu.color_mapping_path).as_deref())?;
+        let path = std::path::PathBuf::from(self.world_menu.structure_path.trim());
+        let structure = volume::load(&path, &block_mapping, &color_mapping)?;
+
+        let origin = self.player.get_block_placement_pos(self.dimensions.world())
+            .ok_or_else(|| anyhow::anyhow!("no block targeted"))?;
+        self.dimensions.world_mut().paste(origin, &structure, &self.edit_menu.transform);
+        Ok(structure.size)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_structure(&mut self) -> anyhow::Result<[usize; 3]> {
+        let block_mapping = load_block_mapping(non_empty_path(&self.world_menu.mapping_path).as_deref())?;
+        let color_mapping = load_color_mapping(non_empty_path(&self.world_menu.color_mapping_path).as_deref())?;
+        let path = std::path::PathBuf::from(self.world_menu.structure_path.trim());
+
+        let structure = self.dimensions.world().copy(&self.selection)
+            .ok_or_else(|| anyhow::anyhow!("set both corners of the selection first"))?;
+        volume::save(&path, &structure, &block_mapping, &color_mapping)?;
+        Ok(structure.size)
+    }
+
+    fn handle_world_menu_action(&mut self, action: gui::WorldMenuAction) {
+        if let gui::WorldMenuAction::NewWorld = action {
+            self.world_menu.status = match self.new_world() {
+                Ok(seed) => format!("Created a new world with seed {}", seed),
+                Err(e) => format!("Can't create the world: {:#}", e),
+            };
+            return;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.world_menu.status = match action {
+                gui::WorldMenuAction::SaveWorld | gui::WorldMenuAction::LoadWorld => {
+                    let path = std::path::PathBuf::from(self.world_menu.save_path.trim());
+                    if matches!(action, gui::WorldMenuAction::SaveWorld) {
+                        match self.dimensions.save(&path) {
+                            Ok(()) => format!("Saved to {}", path.display()),
+                            Err(e) => format!("Save failed: {:#}", e),
+                        }
+                    } else {
+                        match self.load_save(&path) {
+                            Ok(()) => format!("Loaded {}", path.display()),
+                            Err(e) => format!("Load failed: {:#}", e),
+                        }
+                    }
+                }
+                gui::WorldMenuAction::NewWorld => unreachable!(),
+                gui::WorldMenuAction::ImportRegion => {
+                    let region_path = std::path::PathBuf::from(self.world_menu.region_path.trim());
+                    let mapping_path = non_empty_path(&self.world_menu.mapping_path);
+                    match self.import_region(&region_path, mapping_path.as_deref()) {
+                        Ok(count) => format!("Imported {} chunks", count),
+                        Err(e) => format!("Import failed: {:#}", e),
+                    }
+                }
+                gui::WorldMenuAction::PasteStructure => match self.paste_structure() {
+                    Ok(size) => format!("Pasted a {}x{}x{} structure", size[0], size[1], size[2]),
+                    Err(e) => format!("Paste failed: {:#}", e),
+                },
+                gui::WorldMenuAction::ExportStructure => match self.export_structure() {
+                    Ok(size) => format!("Exported a {}x{}x{} structure", size[0], size[1], size[2]),
+                    Err(e) => format!("Export failed: {:#}", e),
+                },
+            };
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = action;
+    }
+
+    // Replaces every dimension with a new overworld
+    fn new_world(&mut self) -> anyhow::Result<i64> {
+        let seed = self.world_menu.new_seed.trim();
+        let seed = if seed.is_empty() { 0 } else { seed::parse(seed) };
+        let generator = self.world_menu.generator_settings()?;
+        let mut world = world::World::with_settings(world::WorldSettings { seed, generator, ..Default::default() });
+        subscribe_stats(&mut world, &self.block_stats);
+        self.dimensions = dimension::Dimensions::new(dimension::OVERWORLD, world, dimension::Sky::default());
+        self.world_renderer.clear(self.dimensions.world_mut());
+        self.selection = edit::Selection::default();
+        self.player.respawn(self.dimensions.world().spawn);
+        Ok(seed)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_save(&mut self, path: &std::path::Path) -> anyhow::Result<()> {
+        let known: Vec<String> = self.dimensions.iter().map(|d| d.name.clone()).collect();
+        self.dimensions.load(path)?;
+        // Dimensions added by the save don't have the stats subscriber yet
+        let added: Vec<String> = self.dimensions.iter().map(|d| d.name.clone()).filter(|name| !known.contains(name)).collect();
+        for name in added {
+            subscribe_stats(&mut self.dimensions.get_mut(&name).unwrap().world, &self.block_stats);
+        }
+        self.world_renderer.clear(self.dimensions.world_mut());
+        self.player.respawn(self.dimensions.world().spawn);
+        Ok(())
+    }
+
+    fn run_command(&mut self, line: &str) {
+        let output = match command::parse(line).and_then(|command| self.execute_command(command)) {
+            Ok(output) => output,
+            Err(e) => format!("Error: {:#}", e),
+        };
+        self.console.print(output);
+    }
+
+    fn execute_command(&mut self, command: command::Command) -> anyhow::Result<String> {
+        use command::Command;
+
+        Ok(match command {
+            Command::Help => command::HELP.to_string(),
+            Command::ListDimensions => {
+                let active = &self.dimensions.active().name;
+                self.dimensions
+                    .iter()
+                    .map(|d| if &d.name == active { format!("{} (active)", d.name) } else { d.name.clone() })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            Command::GoToDimension(name) => {
+                self.dimensions.switch_to(&name)?;
+                self.world_renderer.clear(self.dimensions.world_mut());
+                // The same coordinates could be in the ground or over the void of the other dimension
+                self.player.respawn(self.dimensions.world().spawn);
+                format!("Moved to {}", name)
+            }
+            Command::CreateDimension { name, seed, generator } => {
+                // Without a seed, each new dimension gets the next one after the active world's
+                let seed = seed.unwrap_or(self.dimensions.world().seed.wrapping_add(self.dimensions.iter().count() as i64));
+                let settings = world::WorldSettings { seed, generator, ..Default::default() };
+                let dimension = self.dimensions.create(&name, settings, dimension::Sky::default())?;
+                subscribe_stats(&mut dimension.world, &self.block_stats);
+                format!("Created {} with seed {} and the {} generator", name, seed, dimension.world.generator_settings.name())
+            }
+            Command::Teleport([x, y, z]) => {
+                command::check_height(y, self.dimensions.world().height)?;
+                self.player.teleport([x, y, z]);
+                format!("Teleported to {}, {}, {}", x, y, z)
+            }
+            Command::GoToSpawn => {
+                self.player.respawn(self.dimensions.world().spawn);
+                "Moved to the spawn point".to_string()
+            }
+            Command::SetSpawn(pos) => {
+                let spawn = match pos {
+                    Some([x, y, z]) => pos::BlockPos::new(x, y, z),
+                    None => {
+                        pos::BlockPos::containing(self.player.body.position)
+                    }
+                };
+                command::check_block_height(spawn.y, self.dimensions.world().height)?;
+                self.dimensions.world_mut().spawn = spawn;
+                format!("Set the spawn point to {}, {}, {}", spawn.x, spawn.y, spawn.z)
+            }
+        })
+    }
+
+    fn handle_edit_action(&mut self, action: gui::EditAction) {
+        use gui::EditAction;
+
+        let menu = &self.edit_menu;
+        let changed = match action {
+            EditAction::SetPos1 | EditAction::SetPos2 => {
+                let target = self.player.get_block_pointed_at(self.dimensions.world());
+                if target.is_none() {
+                    self.edit_menu.status = "No block targeted".to_string();
+                    return;
+                }
+                if matches!(action, EditAction::SetPos1) {
+                    self.selection.pos1 = target;
+                } else {
+                    self.selection.pos2 = target;
+                }
+                self.edit_menu.status.clear();
+                return;
+            }
+            EditAction::Undo | EditAction::Redo => {
+                let done = if matches!(action, EditAction::Undo) {
+                    self.dimensions.world_mut().undo()
+                } else {
+                    self.dimensions.world_mut().redo()
+                };
+                self.edit_menu.status = if done { String::new() } else { "Nothing to undo or redo".to_string() };
+                return;
+            }
+            EditAction::Copy | EditAction::Cut => {
+                self.clipboard = if matches!(action, EditAction::Cut) {
+                    self.dimensions.world_mut().cut(&self.selection)
+                } else {
+                    self.dimensions.world().copy(&self.selection)
+                };
+                self.edit_menu.status = match &self.clipboard {
+                    Some(_) => "Copied the selection".to_string(),
+                    None => "Set both corners of the selection first".to_string(),
+                };
+                return;
+            }
+            _ if self.selection.region().is_none() && !matches!(action, EditAction::Paste) => {
+                self.edit_menu.status = "Set both corners of the selection first".to_string();
+                return;
+            }
+            EditAction::Fill => self.dimensions.world_mut().fill(&self.selection, menu.block),
+            EditAction::Replace => self.dimensions.world_mut().replace(&self.selection, menu.replace_from, menu.block),
+            EditAction::Hollow => self.dimensions.world_mut().hollow(&self.selection),
+            EditAction::Walls => self.dimensions.world_mut().walls(&self.selection, menu.block),
+            EditAction::Stack => {
+                let direction = gui::STACK_DIRECTIONS[menu.stack_direction].1;
+                self.dimensions.world_mut().stack(&self.selection, menu.stack_count, direction)
+            }
+            EditAction::Paste => {
+                let (Some(clipboard), Some(origin)) = (&self.clipboard, self.player.get_block_placement_pos(self.dimensions.world())) else {
+                    self.edit_menu.status = "Nothing to paste or no block targeted".to_string();
+                    return;
+                };
+                self.dimensions.world_mut().paste(origin, clipboard, &menu.transform)
+            }
+        };
+        self.edit_menu.status = format!("{} blocks changed", changed);
+    }
+
     fn update(&mut self, dt: instant::Duration) {
         // bad, use a staging buffer for the camera ?
-        self.player.camera_controller.update_camera(&mut self.player.camera, dt);
+        self.player.update(self.dimensions.world(), dt);
+        // Falling out of the world brings the player back to the spawn point
+        let world = self.dimensions.world();
+        if self.player.body.position[1] < (world.height.min_y - entity::VOID_DEPTH) as f32 {
+            self.player.respawn(world.spawn);
+        }
         self.camera_uniform
             .update_view_proj(&self.player.camera, &self.player.projection);
         self.queue.write_buffer(
@@ -750,6 +1037,9 @@ impl State {
             bytemuck::cast_slice(&[self.camera_uniform]),
         );
 
+        self.dimensions.update_ticks(dt.as_secs_f32());
+        self.world_renderer.update(&self.device, self.dimensions.world_mut());
+
         // for instance in &mut self.instances {
         //     let amount = cgmath::Quaternion::from_angle_y(cgmath::Rad(ROTATION_SPEED));
         //     let current = instance.rotation;
@@ -787,6 +1077,7 @@ impl State {
             });
 
         {
+            let sky = self.dimensions.active().sky;
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -794,9 +1085,9 @@ impl State {
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
+                            r: sky.color[0] as f64,
+                            g: sky.color[1] as f64,
+                            b: sky.color[2] as f64,
                             a: 1.0,
                         }),
                         store: wgpu::StoreOp::Store,
@@ -817,14 +1108,8 @@ impl State {
 
             // render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
             render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.world.texture_atlas.diffuse_bind_group, &[]);
             render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-
-            self.world.chunk_buffers.iter().filter(|cb| cb.vertex_buffer.size() > 0 && cb.indices_buffer.size() > 0).for_each(|cb| {
-                render_pass.set_vertex_buffer(0, cb.vertex_buffer.slice(..));
-                render_pass.set_index_buffer(cb.indices_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                render_pass.draw_indexed(0..cb.num_elements, 0, 0..1);
-            });
+            self.world_renderer.draw(&mut render_pass);
 
             // render_pass.set_vertex_buffer(0, self.world.chunk_buffers[0].vertex_buffer.slice(..));
             // render_pass.set_index_buffer(self.world.chunk_buffers[0].indices_buffer.slice(..), wgpu::IndexFormat::Uint32);
@@ -846,6 +1131,10 @@ impl State {
             size_in_pixels: [self.config.width, self.config.height],
             pixels_per_point: self.window.scale_factor() as f32,
         };
+
+        let mut world_menu_action = None;
+        let mut edit_action = None;
+        let mut command = None;
         
         self.egui_renderer.draw(
             &self.device,
@@ -875,21 +1164,42 @@ impl State {
                                 dir.x, dir.y, dir.z
                             ));
                             ui.separator();
-                            ui.label(format!("Chunks loaded: {}", self.world.chunks.len()));
+                            ui.label(format!("Dimension: {}", self.dimensions.active().name));
+                            let seed = self.dimensions.world().seed;
+                            ui.horizontal(|ui| {
+                                ui.label(format!("Seed: {}", seed));
+                                if ui.small_button("Copy").clicked() {
+                                    ui.ctx().copy_text(seed.to_string());
+                                }
+                            });
+                            let feet = pos::BlockPos::containing(self.player.camera.position.into());
+                            let biome = self.dimensions.world().biome_at(feet);
+                            ui.label(format!("Biome: {}", biome.map_or("-", |biome| biome.name())));
+                            ui.label(format!("Chunks loaded: {}", self.dimensions.world().chunks.len()));
+                            let stats = *self.block_stats.lock().unwrap();
+                            ui.label(format!("Blocks broken: {}, placed: {}, edited: {}", stats.broken, stats.placed, stats.edited));
                             ui.separator();
                             ui.label("Controls:");
                             ui.label("  WASD - Move");
-                            ui.label("  Space - Jump");
+                            ui.label("  Space - Jump, fly up");
+                            ui.label("  Shift - Fly down");
+                            ui.label("  F - Toggle flying");
                             ui.label("  Mouse - Look around");
                             ui.label("  Left Click - Break block");
                             ui.label("  Right Click - Place block");
                             ui.label("  P - Toggle cursor lock");
+                            ui.label("  / - Open the console");
+                            ui.label("  Ctrl+Z / Ctrl+Y - Undo / Redo");
                             ui.label("  ESC - Exit");
                             ui.separator();
                             ui.label(format!("Cursor: {}", if self.player.cursor_locked { "Locked" } else { "Unlocked" }));
 
                             // ui.add(egui::Image::new(egui::include_image!("../res/texture_atlas.png")));
                         });
+
+                    world_menu_action = self.world_menu.show(ctx);
+                    edit_action = self.edit_menu.show(ctx, &self.selection, self.clipboard.as_ref());
+                    command = self.console.show(ctx);
                     }
 
                 if self.player.show_inventory {
@@ -984,10 +1294,26 @@ impl State {
         self.queue.submit(iter::once(encoder.finish()));
         output.present();
 
+        if let Some(action) = world_menu_action {
+            self.handle_world_menu_action(action);
+        }
+        if let Some(action) = edit_action {
+            self.handle_edit_action(action);
+        }
+        if let Some(line) = command {
+            self.run_command(&line);
+        }
+
         Ok(())
     }
 
     fn handle_key(&mut self, event_loop: &ActiveEventLoop, code: KeyCode, is_pressed: bool) {
+        if code == KeyCode::Slash && is_pressed && self.player.cursor_locked {
+            self.unlock_cursor();
+            self.console.focus = true;
+            return;
+        }
+
         if code == KeyCode::KeyP && is_pressed {
             if self.player.cursor_locked {
                 self.unlock_cursor();
@@ -996,6 +1322,16 @@ impl State {
             }
             return;
         }
+
+        // Other keys still reach the player so holding Ctrl doesn't stop movement
+        if self.modifiers.control_key() && is_pressed && matches!(code, KeyCode::KeyZ | KeyCode::KeyY) {
+            if code == KeyCode::KeyZ && !self.modifiers.shift_key() {
+                self.dimensions.world_mut().undo();
+            } else {
+                self.dimensions.world_mut().redo();
+            }
+            return;
+        }
         
         if !self.player.process_keyboard(code, is_pressed) {
             match (code, is_pressed) {
@@ -1004,6 +1340,10 @@ impl State {
             }
         }
 
+        // Releases also arrive while typing in a text field, they mustn't grab the cursor
+        if !is_pressed {
+            return;
+        }
         if self.player.show_inventory {
             self.unlock_cursor();
         } else {
@@ -1012,11 +1352,78 @@ impl State {
     }
 }
 
+fn subscribe_stats(world: &mut world::World, stats: &Arc<Mutex<events::BlockStats>>) {
+    let stats = stats.clone();
+    world.subscribe(move |event| stats.lock().unwrap().record(event));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn non_empty_path(path: &str) -> Option<std::path::PathBuf> {
+    let path = path.trim();
+    (!path.is_empty()).then(|| std::path::PathBuf::from(path))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_block_mapping(path: Option<&std::path::Path>) -> anyhow::Result<block_mapping::BlockMapping> {
+    use anyhow::Context;
+
+    match path {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+            block_mapping::BlockMapping::parse(&text)
+        }
+        None => Ok(block_mapping::BlockMapping::default()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_color_mapping(path: Option<&std::path::Path>) -> anyhow::Result<vox::ColorMapping> {
+    use anyhow::Context;
+
+    match path {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+            vox::ColorMapping::parse(&text)
+        }
+        None => Ok(vox::ColorMapping::default()),
+    }
+}
+
+// Command line flags for the native build
+#[derive(Clone, Debug, Default)]
+pub struct LaunchOptions {
+    pub import_regions: Vec<std::path::PathBuf>,
+    pub block_mapping: Option<std::path::PathBuf>,
+}
+
+impl LaunchOptions {
+    const USAGE: &'static str = "usage: bassicraft2 [--import-region <r.x.z.mca>]... [--block-mapping <mapping.txt>]";
+
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--import-region" => {
+                    let path = args.next().ok_or_else(|| anyhow::anyhow!("--import-region needs a path\n{}", Self::USAGE))?;
+                    options.import_regions.push(path.into());
+                }
+                "--block-mapping" => {
+                    let path = args.next().ok_or_else(|| anyhow::anyhow!("--block-mapping needs a path\n{}", Self::USAGE))?;
+                    options.block_mapping = Some(path.into());
+                }
+                _ => anyhow::bail!("unknown argument `{}`\n{}", arg, Self::USAGE),
+            }
+        }
+        Ok(options)
+    }
+}
+
 pub struct App {
     #[cfg(target_arch = "wasm32")]
     proxy: Option<winit::event_loop::EventLoopProxy<State>>,
     state: Option<State>,
     last_time: instant::Instant,
+    launch_options: LaunchOptions,
 }
 
 impl App {
@@ -1028,6 +1435,7 @@ impl App {
             proxy,
             state: None,
             last_time: instant::Instant::now(),
+            launch_options: LaunchOptions::default(),
         }
     }
 }
@@ -1058,7 +1466,17 @@ impl ApplicationHandler<State> for App {
         {
             // If we are not on web we can use pollster to
             // await the
-            self.state = Some(pollster::block_on(State::new(window)).unwrap());
+            let mut state = pollster::block_on(State::new(window)).unwrap();
+
+            let mapping_path = self.launch_options.block_mapping.as_deref();
+            for region_path in &self.launch_options.import_regions {
+                match state.import_region(region_path, mapping_path) {
+                    Ok(count) => log::info!("Imported {} chunks from {}", count, region_path.display()),
+                    Err(e) => log::error!("Unable to import {}: {:#}", region_path.display(), e),
+                }
+            }
+
+            self.state = Some(state);
         }
 
         #[cfg(target_arch = "wasm32")]
@@ -1158,9 +1576,11 @@ impl ApplicationHandler<State> for App {
                     state.handle_mouse_button(button, btn_state.is_pressed());
                 }
             }
-            WindowEvent::MouseWheel { delta, .. } => {
-                state.handle_mouse_scroll(&delta);
+            WindowEvent::ModifiersChanged(modifiers) => {
+                state.modifiers = modifiers.state();
             }
+            // Presses don't reach the player while typing in a text field, releases always do so
+            // a key held when the field took focus doesn't stay down
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -1169,7 +1589,9 @@ impl ApplicationHandler<State> for App {
                         ..
                     },
                 ..
-            } => state.handle_key(event_loop, code, key_state.is_pressed()),
+            } if !key_state.is_pressed() || !state.egui_renderer.context.wants_keyboard_input() => {
+                state.handle_key(event_loop, code, key_state.is_pressed());
+            }
             _ => {}
         }
     }
@@ -1190,6 +1612,10 @@ pub fn run() -> anyhow::Result<()> {
         #[cfg(target_arch = "wasm32")]
         &event_loop,
     );
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        app.launch_options = LaunchOptions::from_args(std::env::args().skip(1))?;
+    }
     event_loop.run_app(&mut app)?;
 
     Ok(())
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use anyhow::{anyhow, bail, Result};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::GzEncoder;
+
+// Reader and writer for Minecraft's Named Binary Tag format (big endian, Java edition)
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    pub fn id(&self) -> u8 {
+        match self {
+            Tag::End => 0,
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float(_) => 5,
+            Tag::Double(_) => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(map) => map.get(name),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&HashMap<String, Tag>> {
+        match self {
+            Tag::Compound(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // Any integer tag widened to i64, NBT writers aren't consistent about the width they use
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(v) => Some(v as i64),
+            Tag::Short(v) => Some(v as i64),
+            Tag::Int(v) => Some(v as i64),
+            Tag::Long(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        self.as_i64().map(|v| v as i32)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Tag::Float(v) => Some(v as f64),
+            Tag::Double(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match self {
+            Tag::ByteArray(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_int_array(&self) -> Option<&[i32]> {
+        match self {
+            Tag::IntArray(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Tag::LongArray(a) => Some(a),
+            _ => None,
+        }
+    }
+}
+
+// Decompresses gzip or zlib data if needed, then reads the root compound.
+// Returns the root name along with the tag.
+pub fn read_compressed(bytes: &[u8]) -> Result<(String, Tag)> {
+    let data = decompress(bytes)?;
+    read(&data)
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    match bytes {
+        [0x1f, 0x8b, ..] => {
+            GzDecoder::new(bytes).read_to_end(&mut data)?;
+        }
+        [0x78, ..] => {
+            ZlibDecoder::new(bytes).read_to_end(&mut data)?;
+        }
+        _ => data.extend_from_slice(bytes),
+    }
+    Ok(data)
+}
+
+pub fn read(bytes: &[u8]) -> Result<(String, Tag)> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let id = reader.u8()?;
+    if id != 10 {
+        bail!("NBT root is not a compound (tag id {})", id);
+    }
+    let name = reader.string()?;
+    let tag = reader.payload(id, 0)?;
+    Ok((name, tag))
+}
+
+// Gzip is what Minecraft uses for standalone files (.schem, level.dat)
+pub fn write_compressed(name: &str, tag: &Tag) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&write(name, tag))?;
+    Ok(encoder.finish()?)
+}
+
+pub fn write(name: &str, tag: &Tag) -> Vec<u8> {
+    let mut out = vec![tag.id()];
+    write_string(&mut out, name);
+    write_payload(&mut out, tag);
+    out
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    let bytes = &s.as_bytes()[..s.len().min(u16::MAX as usize)];
+    out.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn write_payload(out: &mut Vec<u8>, tag: &Tag) {
+    match tag {
+        Tag::End => {}
+        Tag::Byte(v) => out.push(*v as u8),
+        Tag::Short(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::Int(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::Long(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::Float(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::Double(v) => out.extend_from_slice(&v.to_be_bytes()),
+        Tag::ByteArray(a) => {
+            out.extend_from_slice(&(a.len() as i32).to_be_bytes());
+            out.extend(a.iter().map(|&b| b as u8));
+        }
+        Tag::String(s) => write_string(out, s),
+        Tag::List(list) => {
+            out.push(list.first().map(Tag::id).unwrap_or(0));
+            out.extend_from_slice(&(list.len() as i32).to_be_bytes());
+            for item in list {
+                write_payload(out, item);
+            }
+        }
+        Tag::Compound(map) => {
+            // Sorted so the same data always gives the same bytes
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, value) in entries {
+                out.push(value.id());
+                write_string(out, name);
+                write_payload(out, value);
+            }
+            out.push(0);
+        }
+        Tag::IntArray(a) => {
+            out.extend_from_slice(&(a.len() as i32).to_be_bytes());
+            for v in a {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+        Tag::LongArray(a) => {
+            out.extend_from_slice(&(a.len() as i32).to_be_bytes());
+            for v in a {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+    }
+}
+
+const MAX_DEPTH: usize = 512;
+
+// The fewest bytes a payload of this tag id can take, at least 1 so it can divide
+fn min_payload_size(id: u8) -> usize {
+    match id {
+        2 | 8 => 2,
+        3 | 5 | 7 | 11 | 12 => 4,
+        4 | 6 => 8,
+        // Item id and length
+        9 => 5,
+        _ => 1,
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| anyhow!("unexpected end of NBT data at byte {}", self.pos))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(i16::from_be_bytes(self.array()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_be_bytes(self.array()?))
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        let len = self.i32()?;
+        if len < 0 {
+            bail!("negative NBT length {}", len);
+        }
+        Ok(len as usize)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = u16::from_be_bytes(self.array()?) as usize;
+        // Java writes "modified UTF-8", which only differs from UTF-8 for NUL and astral characters
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn payload(&mut self, id: u8, depth: usize) -> Result<Tag> {
+        if depth > MAX_DEPTH {
+            bail!("NBT nested too deeply");
+        }
+        let tag = match id {
+            0 => Tag::End,
+            1 => Tag::Byte(self.u8()? as i8),
+            2 => Tag::Short(self.i16()?),
+            3 => Tag::Int(self.i32()?),
+            4 => Tag::Long(self.i64()?),
+            5 => Tag::Float(f32::from_bits(self.i32()? as u32)),
+            6 => Tag::Double(f64::from_bits(self.i64()? as u64)),
+            7 => {
+                let len = self.len()?;
+                Tag::ByteArray(self.take(len)?.iter().map(|&b| b as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let item_id = self.u8()?;
+                let len = self.len()?;
+                // End items take no bytes, so nothing else would stop a huge count of them
+                if item_id == 0 && len > 0 {
+                    bail!("NBT list of {} end tags at byte {}", len, self.pos);
+                }
+                if len > (self.bytes.len() - self.pos) / min_payload_size(item_id) {
+                    bail!("NBT list of {} items is longer than the data left at byte {}", len, self.pos);
+                }
+                let mut list = Vec::with_capacity(len.min(4096));
+                for _ in 0..len {
+                    list.push(self.payload(item_id, depth + 1)?);
+                }
+                Tag::List(list)
+            }
+            10 => {
+                let mut map = HashMap::new();
+                loop {
+                    let item_id = self.u8()?;
+                    if item_id == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    let value = self.payload(item_id, depth + 1)?;
+                    map.insert(name, value);
+                }
+                Tag::Compound(map)
+            }
+            11 => {
+                let len = self.len()?;
+                let mut array = Vec::with_capacity(len.min(4096));
+                for _ in 0..len {
+                    array.push(self.i32()?);
+                }
+                Tag::IntArray(array)
+            }
+            12 => {
+                let len = self.len()?;
+                let mut array = Vec::with_capacity(len.min(4096));
+                for _ in 0..len {
+                    array.push(self.i64()?);
+                }
+                Tag::LongArray(array)
+            }
+            _ => bail!("unknown NBT tag id {} at byte {}", id, self.pos),
+        };
+        Ok(tag)
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    biome::position_hash,
+    block::{self, BlockType},
+    block_mapping::BlockMapping,
+    chunk::{WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    pos::ChunkPos,
+    tick::Random,
+};
+
+// Ore veins and pockets of gravel or dirt, put into the stone once the caves are carved so
+// ores show in the cave walls. Each kind of vein gets its own random generator seeded by
+// the chunk, so adding a kind doesn't move the others. A vein is a blob grown a block at a
+// time from its start, and is cut off at the chunk border.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    // Every height in the range is as likely
+    Uniform,
+    // Most common in the middle of the range, rarer towards the ends
+    Triangle,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VeinSettings {
+    // A block name like "minecraft:iron_ore"
+    #[serde(serialize_with = "serialize_block", deserialize_with = "deserialize_block")]
+    pub block: BlockType,
+    // Blocks each vein tries to place
+    pub size: u32,
+    // Veins per chunk
+    pub count: u32,
+    pub min_y: i32,
+    pub max_y: i32,
+    pub distribution: Distribution,
+}
+
+impl VeinSettings {
+    pub fn new(block: BlockType, size: u32, count: u32, [min_y, max_y]: [i32; 2], distribution: Distribution) -> Self {
+        Self { block, size, count, min_y, max_y, distribution }
+    }
+
+    // The default veins, rarer and deeper for the more valuable ores
+    pub fn defaults() -> Vec<Self> {
+        use Distribution::*;
+        vec![
+            Self::new(block::DIRT, 32, 6, [0, 160], Uniform),
+            Self::new(block::GRAVEL, 32, 6, [-64, 160], Uniform),
+            Self::new(block::COAL_ORE, 16, 20, [0, 192], Triangle),
+            Self::new(block::IRON_ORE, 9, 10, [-64, 72], Triangle),
+            Self::new(block::GOLD_ORE, 9, 4, [-64, 32], Triangle),
+            Self::new(block::LAPIS_ORE, 7, 2, [-64, 64], Triangle),
+            Self::new(block::REDSTONE_ORE, 8, 6, [-64, 16], Uniform),
+            Self::new(block::DIAMOND_ORE, 8, 2, [-64, 16], Triangle),
+        ]
+    }
+}
+
+// Bounds for settings files, every vein is grown for every chunk
+const MAX_VEIN_SIZE: u32 = 256;
+const MAX_VEIN_COUNT: u32 = 256;
+
+pub fn check(veins: &[VeinSettings]) -> Result<()> {
+    for vein in veins {
+        let name = BlockMapping::default().name_of(vein.block);
+        if vein.size > MAX_VEIN_SIZE || vein.count > MAX_VEIN_COUNT {
+            bail!("{} veins can be at most {} blocks and {} per chunk", name, MAX_VEIN_SIZE, MAX_VEIN_COUNT);
+        }
+        if vein.min_y > vein.max_y {
+            bail!("{} veins have a min_y over their max_y", name);
+        }
+    }
+    Ok(())
+}
+
+fn serialize_block<S: Serializer>(block_type: &BlockType, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&BlockMapping::default().name_of(*block_type))
+}
+
+fn deserialize_block<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BlockType, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    BlockMapping::default().lookup(&name).ok_or_else(|| serde::de::Error::custom(format!("unknown block {:?}", name)))
+}
+
+#[derive(Clone, Debug)]
+pub struct Ores {
+    seed: u32,
+    veins: Vec<VeinSettings>,
+}
+
+impl Ores {
+    pub fn new(seed: u32, veins: Vec<VeinSettings>) -> Self {
+        Self { seed, veins }
+    }
+
+    // Veins only replace stone
+    pub fn place(&self, pos: ChunkPos, height: WorldHeight, blocks: &mut [Vec<Vec<BlockType>>]) {
+        for (index, vein) in self.veins.iter().enumerate() {
+            let seed = self.seed.wrapping_add(600).wrapping_add(index as u32);
+            let mut random = Random::new(position_hash(seed, pos.x, pos.z));
+            let min_y = vein.min_y.max(height.min_y);
+            let max_y = vein.max_y.min(height.max_y() - 1);
+            if min_y > max_y {
+                continue;
+            }
+            for _ in 0..vein.count {
+                let span = (max_y - min_y + 1) as f64;
+                let offset = match vein.distribution {
+                    Distribution::Uniform => random.next_f64(),
+                    Distribution::Triangle => (random.next_f64() + random.next_f64()) / 2.0,
+                };
+                let mut point = [
+                    random.below(CHUNK_X_SIZE as u32) as i32,
+                    min_y + (offset * span) as i32,
+                    random.below(CHUNK_Z_SIZE as u32) as i32,
+                ];
+                for _ in 0..vein.size {
+                    let [x, y, z] = point;
+                    if (0..CHUNK_X_SIZE as i32).contains(&x)
+                        && (0..CHUNK_Z_SIZE as i32).contains(&z)
+                        && let Some(i) = height.index(y)
+                    {
+                        let block = &mut blocks[x as usize][i][z as usize];
+                        if *block == block::STONE {
+                            *block = vein.block;
+                        }
+                    }
+                    // A step to one of the 26 neighbours
+                    for coordinate in &mut point {
+                        *coordinate += random.below(3) as i32 - 1;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    block,
+    pos::BlockPos,
+    world::World,
+};
+
+// Movement of the player's body through the world. The body is a box standing on its feet
+// position, moved at a fixed timestep so jumps are as high whatever the frame rate. Each
+// step moves it along y, then x, then z, cutting each move short at the first solid block
+// in the way, so it slides along walls instead of stopping. A walk blocked by a ledge up to
+// STEP_HEIGHT high climbs onto it. Blocks in chunks that aren't loaded are empty.
+
+// Seconds per physics step
+pub const TIMESTEP: f32 = 1.0 / 60.0;
+// Of the body, in blocks
+pub const WIDTH: f32 = 0.6;
+pub const HEIGHT: f32 = 1.8;
+// Like Minecraft, a bit over a slab. Full block ledges take a jump.
+pub const STEP_HEIGHT: f32 = 0.6;
+
+// In blocks per second, or per second squared
+const GRAVITY: f32 = 32.0;
+const TERMINAL_SPEED: f32 = 78.0;
+// Reaches a bit over one block
+const JUMP_SPEED: f32 = 9.0;
+const WALK_SPEED: f32 = 4.3;
+const FLY_SPEED: f32 = 10.0;
+
+// Stops blocks that only touch the body from counting as overlapping it
+const EPSILON: f32 = 1e-4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    pub fn block(pos: BlockPos) -> Self {
+        let min = [pos.x as f32, pos.y as f32, pos.z as f32];
+        Self { min, max: min.map(|c| c + 1.0) }
+    }
+
+    pub fn offset(self, by: [f32; 3]) -> Self {
+        Self { min: std::array::from_fn(|i| self.min[i] + by[i]), max: std::array::from_fn(|i| self.max[i] + by[i]) }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|i| self.overlaps_on(other, i))
+    }
+
+    fn overlaps_on(&self, other: &Aabb, axis: usize) -> bool {
+        self.min[axis] < other.max[axis] - EPSILON && self.max[axis] > other.min[axis] + EPSILON
+    }
+
+    // How far of `delta` along `axis` this box can move before running into `other`
+    fn clip(&self, other: &Aabb, axis: usize, delta: f32) -> f32 {
+        if !(0..3).filter(|&i| i != axis).all(|i| self.overlaps_on(other, i)) {
+            return delta;
+        }
+        if delta > 0.0 && self.max[axis] <= other.min[axis] + EPSILON {
+            delta.min(other.min[axis] - self.max[axis])
+        } else if delta < 0.0 && self.min[axis] >= other.max[axis] - EPSILON {
+            delta.max(other.max[axis] - self.min[axis])
+        } else {
+            delta
+        }
+    }
+}
+
+// What the player asks the body to do for a step
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MoveInput {
+    // The horizontal direction to walk in, at most 1 long
+    pub walk: [f32; 2],
+    // Jumps, or goes up while flying
+    pub up: bool,
+    // Goes down while flying
+    pub down: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Body {
+    // The middle of the bottom of the box
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub on_ground: bool,
+    // No gravity, up and down move straight, it still collides
+    pub flying: bool,
+}
+
+impl Body {
+    pub fn new(position: [f32; 3]) -> Self {
+        Self { position, velocity: [0.0; 3], on_ground: false, flying: false }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        let [x, y, z] = self.position;
+        let half = WIDTH / 2.0;
+        Aabb { min: [x - half, y, z - half], max: [x + half, y + HEIGHT, z + half] }
+    }
+
+    // Moves the body by one TIMESTEP
+    pub fn step(&mut self, world: &World, input: MoveInput) {
+        let speed = if self.flying { FLY_SPEED } else { WALK_SPEED };
+        self.velocity[0] = input.walk[0] * speed;
+        self.velocity[2] = input.walk[1] * speed;
+        if self.flying {
+            self.velocity[1] = (input.up as i32 - input.down as i32) as f32 * FLY_SPEED;
+        } else if input.up && self.on_ground {
+            self.velocity[1] = JUMP_SPEED;
+        } else {
+            self.velocity[1] = (self.velocity[1] - GRAVITY * TIMESTEP).max(-TERMINAL_SPEED);
+        }
+
+        let delta = self.velocity.map(|v| v * TIMESTEP);
+        let moved = self.move_by(world, delta);
+        self.on_ground = delta[1] < 0.0 && moved[1] > delta[1];
+        for (i, velocity) in self.velocity.iter_mut().enumerate() {
+            if moved[i] != delta[i] {
+                *velocity = 0.0;
+            }
+        }
+    }
+
+    // Moves as far as the blocks allow, returns how far it went
+    pub fn move_by(&mut self, world: &World, delta: [f32; 3]) -> [f32; 3] {
+        let start = self.aabb();
+        let blocks = solid_blocks(world, start, delta);
+        let mut moved = sweep(&blocks, start, delta);
+
+        // Blocked while walking on the ground: try again from STEP_HEIGHT up, then back down
+        let blocked = moved[0] != delta[0] || moved[2] != delta[2];
+        let grounded = self.on_ground || (delta[1] < 0.0 && moved[1] > delta[1]);
+        if blocked && grounded && !self.flying {
+            let up = sweep(&blocks, start, [0.0, STEP_HEIGHT, 0.0])[1];
+            let raised = start.offset([0.0, up, 0.0]);
+            let across = sweep(&blocks, raised, [delta[0], 0.0, delta[2]]);
+            let down = sweep(&blocks, raised.offset(across), [0.0, -up, 0.0])[1];
+            let stepped = [across[0], up + down, across[2]];
+            if stepped[0].powi(2) + stepped[2].powi(2) > moved[0].powi(2) + moved[2].powi(2) {
+                moved = stepped;
+            }
+        }
+
+        for (position, moved) in self.position.iter_mut().zip(moved) {
+            *position += moved;
+        }
+        moved
+    }
+}
+
+// Along y, then x, then z
+fn sweep(blocks: &[Aabb], mut aabb: Aabb, delta: [f32; 3]) -> [f32; 3] {
+    let mut moved = [0.0; 3];
+    for axis in [1, 0, 2] {
+        let distance = blocks.iter().fold(delta[axis], |distance, block| aabb.clip(block, axis, distance));
+        moved[axis] = distance;
+        let mut offset = [0.0; 3];
+        offset[axis] = distance;
+        aabb = aabb.offset(offset);
+    }
+    moved
+}
+
+// The solid blocks the body could touch on its way, stepping up included
+fn solid_blocks(world: &World, aabb: Aabb, delta: [f32; 3]) -> Vec<Aabb> {
+    let reach = |i: usize| {
+        let extra = if i == 1 { STEP_HEIGHT } else { 0.0 };
+        let min = (aabb.min[i] + delta[i].min(0.0) - extra).floor() as i32;
+        let max = (aabb.max[i] + delta[i].max(0.0) + extra).floor() as i32;
+        min..=max
+    };
+    let mut blocks = Vec::new();
+    for x in reach(0) {
+        for y in reach(1) {
+            for z in reach(2) {
+                let pos = BlockPos::new(x, y, z);
+                if world.get_block_type(pos).is_some_and(|b| block::properties(b).solid) {
+                    blocks.push(Aabb::block(pos));
+                }
+            }
+        }
+    }
+    blocks
+}
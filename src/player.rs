@@ -1,13 +1,21 @@
+use instant::Duration;
 use winit::keyboard::KeyCode;
 use winit::event::*;
 
+use crate::block;
 use crate::camera;
-use crate::chunk;
+use crate::physics::{self, Body, MoveInput};
+use crate::pos::BlockPos;
+use crate::world::World;
 
 pub struct Player {
     pub camera: camera::Camera,
     pub projection: camera::Projection,
     pub camera_controller: camera::CameraController,
+    // The camera follows it at EYE_HEIGHT
+    pub body: Body,
+    // Time the physics hasn't stepped through yet, less than a TIMESTEP after each update
+    time_behind: f32,
 
     pub selected_block: u32,
     pub hotbar: [u32; 8],
@@ -18,19 +26,26 @@ pub struct Player {
 }
 
 pub const MAX_BLOCK_POINT_DISTANCE: f32 = 9.0;
+// From the feet to the camera
+pub const EYE_HEIGHT: f32 = 1.62;
+// After a long frame the physics catches up at most this many seconds
+const MAX_CATCH_UP: f32 = 0.25;
 
 impl Player {
-    pub fn new(pos: [f32; 3], config: &wgpu::SurfaceConfiguration) -> Player {
-        let camera = camera::Camera::new(pos, cgmath::Deg(-90.0), cgmath::Deg(-20.0));
+    // `feet` is the bottom of the body
+    pub fn new(feet: [f32; 3], config: &wgpu::SurfaceConfiguration) -> Player {
+        let camera = camera::Camera::new([feet[0], feet[1] + EYE_HEIGHT, feet[2]], cgmath::Deg(-90.0), cgmath::Deg(-20.0));
         let projection =
             camera::Projection::new(config.width, config.height, cgmath::Deg(45.0), 0.1, 1000.0);
 
-        let camera_controller = camera::CameraController::new(4.0, 0.4);
+        let camera_controller = camera::CameraController::new(0.4);
 
         Self {
             camera: camera,
             projection: projection,
             camera_controller: camera_controller,
+            body: Body::new(feet),
+            time_behind: 0.0,
             selected_block: 1,
             hotbar: core::array::from_fn(|i| (i + 1) as u32),
             selected_hotbar_slot: 0,
@@ -39,104 +54,72 @@ impl Player {
         }
     }
 
-    pub fn get_block_pointed_at(&self, chunks: &Vec<chunk::Chunk>) -> Option<[i32; 3]> {
-        let max_distance = MAX_BLOCK_POINT_DISTANCE;
-        let step = 0.05;
-        let mut distance = 0.0;
+    // Stands the player in the middle of the `spawn` block
+    pub fn respawn(&mut self, spawn: BlockPos) {
+        self.teleport([spawn.x as f32 + 0.5, spawn.y as f32, spawn.z as f32 + 0.5]);
+    }
 
-        let origin = self.camera.position;
-        let direction = self.camera.direction();
+    // Puts the feet at `feet`, stopped
+    pub fn teleport(&mut self, feet: [f32; 3]) {
+        self.body.position = feet;
+        self.body.velocity = [0.0; 3];
+        self.body.on_ground = false;
+        self.sync_camera();
+    }
 
-        const CHUNK_X_SIZE: i32 = 16;
-        const CHUNK_Z_SIZE: i32 = 16;
+    // Turns the camera, then runs the physics steps that fit in the time since the last update
+    pub fn update(&mut self, world: &World, dt: Duration) {
+        self.camera_controller.update_camera(&mut self.camera, dt);
+
+        let controller = &self.camera_controller;
+        let input = MoveInput {
+            walk: controller.movement(&self.camera),
+            up: controller.amount_up > 0.0,
+            down: controller.amount_down > 0.0,
+        };
+        self.time_behind = (self.time_behind + dt.as_secs_f32()).min(MAX_CATCH_UP);
+        while self.time_behind >= physics::TIMESTEP {
+            self.body.step(world, input);
+            self.time_behind -= physics::TIMESTEP;
+        }
+        self.sync_camera();
+    }
 
-        while distance < max_distance {
-            let point = [
-                origin.x + direction.x * distance,
-                origin.y + direction.y * distance,
-                origin.z + direction.z * distance,
-            ];
-            let world_block_pos = [
-                point[0].floor() as i32,
-                point[1].floor() as i32,
-                point[2].floor() as i32,
-            ];
-
-            let chunk_x = world_block_pos[0].div_euclid(CHUNK_X_SIZE);
-            let chunk_z = world_block_pos[2].div_euclid(CHUNK_Z_SIZE);
-
-            let local_x = world_block_pos[0].rem_euclid(CHUNK_X_SIZE);
-            let local_y = world_block_pos[1];
-            let local_z = world_block_pos[2].rem_euclid(CHUNK_Z_SIZE);
-
-            for chunk in chunks {
-                if chunk.pos[0] == chunk_x && chunk.pos[1] == chunk_z {
-                    if let Some(block) = chunk.get_block([local_x, local_y, local_z]) {
-                        if !block.is_air() {
-                            return Some(world_block_pos);
-                        }
-                    }
-                    break;
-                }
-            }
+    fn sync_camera(&mut self) {
+        let [x, y, z] = self.body.position;
+        self.camera.position = cgmath::Point3::new(x, y + EYE_HEIGHT, z);
+    }
 
-            distance += step;
-        }
+    pub fn get_block_pointed_at(&self, world: &World) -> Option<BlockPos> {
+        self.ray_blocks(0.05).find(|&pos| world.get_block_type(pos).is_some_and(|block| block != block::AIR))
+    }
 
+    // The last block the ray went through before hitting one, where a new block would go
+    pub fn get_block_placement_pos(&self, world: &World) -> Option<BlockPos> {
+        let mut last_empty_pos = None;
+        for pos in self.ray_blocks(0.1) {
+            match world.get_block_type(pos) {
+                Some(block) if block != block::AIR => return last_empty_pos,
+                _ => last_empty_pos = Some(pos),
+            }
+        }
         None
     }
 
-    pub fn get_block_placement_pos(&self, chunks: &Vec<chunk::Chunk>) -> Option<[i32; 3]> {
-        let max_distance = MAX_BLOCK_POINT_DISTANCE;
-        let step = 0.1;
-        let mut distance = 0.0;
-
+    // The blocks along the view direction, sampled every `step` blocks up to MAX_BLOCK_POINT_DISTANCE
+    fn ray_blocks(&self, step: f32) -> impl Iterator<Item = BlockPos> + use<> {
         let origin = self.camera.position;
         let direction = self.camera.direction();
+        let steps = (MAX_BLOCK_POINT_DISTANCE / step).ceil() as usize;
 
-        const CHUNK_X_SIZE: i32 = 16;
-        const CHUNK_Z_SIZE: i32 = 16;
-
-        let mut last_empty_pos: Option<[i32; 3]> = None;
-
-        while distance < max_distance {
-            let point = [
+        (0..steps).map(move |i| {
+            let distance = i as f32 * step;
+            BlockPos::containing([
                 origin.x + direction.x * distance,
                 origin.y + direction.y * distance,
                 origin.z + direction.z * distance,
-            ];
-            let world_block_pos = [
-                point[0].floor() as i32,
-                point[1].floor() as i32,
-                point[2].floor() as i32,
-            ];
-
-            let chunk_x = world_block_pos[0].div_euclid(CHUNK_X_SIZE);
-            let chunk_z = world_block_pos[2].div_euclid(CHUNK_Z_SIZE);
-
-            let local_x = world_block_pos[0].rem_euclid(CHUNK_X_SIZE);
-            let local_y = world_block_pos[1];
-            let local_z = world_block_pos[2].rem_euclid(CHUNK_Z_SIZE);
-
-            for chunk in chunks {
-                if chunk.pos[0] == chunk_x && chunk.pos[1] == chunk_z {
-                    if let Some(block) = chunk.get_block([local_x, local_y, local_z]) {
-                        if !block.is_air() {
-                            return last_empty_pos;
-                        } else {
-                            last_empty_pos = Some(world_block_pos);
-                        }
-                    } else {
-                        last_empty_pos = Some(world_block_pos);
-                    }
-                    break;
-                }
-            }
-
-            distance += step;
-        }
-
-        None
+            ])
+        })
     }
 
     pub fn change_selected_block(&mut self, num: usize) {
@@ -184,6 +167,13 @@ impl Player {
                 self.change_selected_block(key as usize - KeyCode::Digit1 as usize);
                 true
             }
+            KeyCode::KeyF => {
+                if !state {
+                    return false
+                }
+                self.body.flying = !self.body.flying;
+                true
+            }
             KeyCode::KeyE => {
                 if !state {
                     return false
@@ -0,0 +1,246 @@
+use std::{fmt, ops::{Add, Sub}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::{CHUNK_X_SIZE, CHUNK_Z_SIZE};
+
+// Coordinates of a block in the world, of a chunk column, and of a block inside its chunk.
+// Going from blocks to chunks rounds towards negative infinity, so block x = -1 is in
+// chunk x = -1 at local x = 15, not in chunk 0.
+
+const CHUNK_X: i32 = CHUNK_X_SIZE as i32;
+const CHUNK_Z: i32 = CHUNK_Z_SIZE as i32;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+// x and z are in 0..16, y is the same as the world y
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LocalPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+// In the same order as the faces of a Block
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    South,
+    West,
+    East,
+    Up,
+    Down,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::North,
+        Direction::South,
+        Direction::West,
+        Direction::East,
+        Direction::Up,
+        Direction::Down,
+    ];
+    pub const HORIZONTAL: [Direction; 4] = [Direction::West, Direction::East, Direction::North, Direction::South];
+
+    pub fn offset(self) -> [i32; 3] {
+        match self {
+            Direction::North => [0, 0, -1],
+            Direction::South => [0, 0, 1],
+            Direction::West => [-1, 0, 0],
+            Direction::East => [1, 0, 0],
+            Direction::Up => [0, 1, 0],
+            Direction::Down => [0, -1, 0],
+        }
+    }
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+            Direction::East => Direction::West,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Direction::Up | Direction::Down)
+    }
+}
+
+impl BlockPos {
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    // The block a point is in
+    pub fn containing(point: [f32; 3]) -> Self {
+        Self::new(point[0].floor() as i32, point[1].floor() as i32, point[2].floor() as i32)
+    }
+
+    pub fn offset(self, x: i32, y: i32, z: i32) -> Self {
+        Self::new(self.x + x, self.y + y, self.z + z)
+    }
+
+    pub fn neighbor(self, direction: Direction) -> Self {
+        let [x, y, z] = direction.offset();
+        self.offset(x, y, z)
+    }
+
+    pub fn neighbors(self) -> impl Iterator<Item = BlockPos> {
+        Direction::ALL.into_iter().map(move |direction| self.neighbor(direction))
+    }
+
+    pub fn up(self) -> Self {
+        self.neighbor(Direction::Up)
+    }
+
+    pub fn down(self) -> Self {
+        self.neighbor(Direction::Down)
+    }
+
+    pub fn chunk(self) -> ChunkPos {
+        ChunkPos::new(self.x.div_euclid(CHUNK_X), self.z.div_euclid(CHUNK_Z))
+    }
+
+    pub fn local(self) -> LocalPos {
+        LocalPos::new(self.x.rem_euclid(CHUNK_X), self.y, self.z.rem_euclid(CHUNK_Z))
+    }
+}
+
+impl ChunkPos {
+    pub const fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    // The block at local 0, 0, 0
+    pub fn min_block(self) -> BlockPos {
+        BlockPos::new(self.x * CHUNK_X, 0, self.z * CHUNK_Z)
+    }
+
+    pub fn block(self, local: LocalPos) -> BlockPos {
+        self.min_block().offset(local.x, local.y, local.z)
+    }
+
+    // Vertical directions give back the same chunk
+    pub fn neighbor(self, direction: Direction) -> Self {
+        let [x, _, z] = direction.offset();
+        Self::new(self.x + x, self.z + z)
+    }
+
+    pub fn contains(self, pos: BlockPos) -> bool {
+        pos.chunk() == self
+    }
+}
+
+impl LocalPos {
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl From<[i32; 3]> for BlockPos {
+    fn from([x, y, z]: [i32; 3]) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl From<BlockPos> for [i32; 3] {
+    fn from(pos: BlockPos) -> Self {
+        [pos.x, pos.y, pos.z]
+    }
+}
+
+impl From<[i32; 2]> for ChunkPos {
+    fn from([x, z]: [i32; 2]) -> Self {
+        Self::new(x, z)
+    }
+}
+
+impl From<ChunkPos> for [i32; 2] {
+    fn from(pos: ChunkPos) -> Self {
+        [pos.x, pos.z]
+    }
+}
+
+impl Add for BlockPos {
+    type Output = BlockPos;
+
+    fn add(self, other: BlockPos) -> BlockPos {
+        self.offset(other.x, other.y, other.z)
+    }
+}
+
+impl Sub for BlockPos {
+    type Output = BlockPos;
+
+    fn sub(self, other: BlockPos) -> BlockPos {
+        self.offset(-other.x, -other.y, -other.z)
+    }
+}
+
+impl fmt::Display for BlockPos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}, {}", self.x, self.y, self.z)
+    }
+}
+
+impl fmt::Display for ChunkPos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}", self.x, self.z)
+    }
+}
+
+// An axis aligned box of blocks, both corners included
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BlockRegion {
+    pub min: BlockPos,
+    pub max: BlockPos,
+}
+
+impl BlockRegion {
+    // The corners can be given in any order
+    pub fn new(a: BlockPos, b: BlockPos) -> Self {
+        Self {
+            min: BlockPos::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: BlockPos::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    pub fn size(&self) -> [i32; 3] {
+        [self.max.x - self.min.x + 1, self.max.y - self.min.y + 1, self.max.z - self.min.z + 1]
+    }
+
+    pub fn contains(&self, pos: BlockPos) -> bool {
+        (self.min.x..=self.max.x).contains(&pos.x)
+            && (self.min.y..=self.max.y).contains(&pos.y)
+            && (self.min.z..=self.max.z).contains(&pos.z)
+    }
+
+    // Every block in x, y, z order
+    pub fn iter(&self) -> impl Iterator<Item = BlockPos> + use<> {
+        let (min, max) = (self.min, self.max);
+        (min.x..=max.x).flat_map(move |x| {
+            (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| BlockPos::new(x, y, z)))
+        })
+    }
+
+    // Every chunk the region touches
+    pub fn chunks(&self) -> impl Iterator<Item = ChunkPos> + use<> {
+        let (min, max) = (self.min.chunk(), self.max.chunk());
+        (min.x..=max.x).flat_map(move |x| (min.z..=max.z).map(move |z| ChunkPos::new(x, z)))
+    }
+}
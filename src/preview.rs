@@ -0,0 +1,132 @@
+use image::{Rgb, RgbImage};
+
+use crate::{
+    biome::Biome,
+    block::{self, BlockType},
+    chunk::{Chunk, WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    fluid::Fluid,
+    generator::TerrainGenerator,
+    heightmap::Heightmap,
+    pos::{BlockPos, ChunkPos},
+    vox::ColorMapping,
+};
+
+// Top-down maps of what a generator makes, for tuning terrain without starting the game.
+// Chunks come straight from the generator, one at a time, and are dropped once their columns
+// are read, so nothing needs a World, a window or a GPU. Trees and the other features that
+// spill into neighbouring chunks are left out. North is up, one pixel per block.
+
+// The area to draw, in blocks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    // The north west corner
+    pub min: [i32; 2],
+    pub size: [u32; 2],
+}
+
+impl Region {
+    pub fn around(center: [i32; 2], size: [u32; 2]) -> Self {
+        Self { min: [center[0] - size[0] as i32 / 2, center[1] - size[1] as i32 / 2], size }
+    }
+}
+
+pub struct Maps {
+    // Grey from the bottom of the world to the top, lit from the north west
+    pub height: RgbImage,
+    // The colour of the top block, lit the same way, with deeper water darker
+    pub surface: RgbImage,
+    pub biomes: RgbImage,
+    // A horizontal cut at one y: blocks in their colour, caves black and the open air white
+    pub caves: RgbImage,
+}
+
+// What the maps need from one column
+#[derive(Clone, Copy, Default)]
+struct Column {
+    top: i32,
+    // The highest solid block, under any water
+    floor: i32,
+    top_block: BlockType,
+    biome: Biome,
+    slice: BlockType,
+}
+
+pub fn render(generator: &dyn TerrainGenerator, height: WorldHeight, region: Region, cave_y: i32, colors: &ColorMapping) -> Maps {
+    let [width, depth] = region.size;
+    let mut columns = vec![Column::default(); width as usize * depth as usize];
+
+    let first = BlockPos::new(region.min[0], 0, region.min[1]).chunk();
+    let last = BlockPos::new(region.min[0] + width as i32 - 1, 0, region.min[1] + depth as i32 - 1).chunk();
+    for cx in first.x..=last.x {
+        for cz in first.z..=last.z {
+            let chunk = Chunk::new(ChunkPos::new(cx, cz), height, generator);
+            let origin = chunk.pos.min_block();
+            for x in 0..CHUNK_X_SIZE {
+                for z in 0..CHUNK_Z_SIZE {
+                    let [px, pz] = [origin.x + x as i32 - region.min[0], origin.z + z as i32 - region.min[1]];
+                    if px < 0 || pz < 0 || px >= width as i32 || pz >= depth as i32 {
+                        continue;
+                    }
+                    let top = chunk.heightmaps.get(Heightmap::WorldSurface, x, z).unwrap_or(height.min_y);
+                    let block_at = |y: i32| height.index(y).map_or(block::AIR, |i| chunk.blocks[x][i][z]);
+                    columns[pz as usize * width as usize + px as usize] = Column {
+                        top,
+                        floor: chunk.heightmaps.get(Heightmap::OceanFloor, x, z).unwrap_or(height.min_y),
+                        top_block: block_at(top),
+                        biome: chunk.biomes[x * CHUNK_Z_SIZE + z],
+                        slice: block_at(cave_y),
+                    };
+                }
+            }
+        }
+    }
+
+    // Brighter on slopes facing north west, darker facing away
+    let shade = |px: u32, pz: u32| {
+        let top = |x: u32, z: u32| columns[z as usize * width as usize + x as usize].top;
+        let slope = top(px, pz) * 2 - top(px.saturating_sub(1), pz) - top(px, pz.saturating_sub(1));
+        (1.0 + slope as f32 * 0.05).clamp(0.75, 1.25)
+    };
+    let lit = |color: [u8; 3], shade: f32| Rgb(color.map(|c| (c as f32 * shade).min(255.0) as u8));
+    let column = |px: u32, pz: u32| columns[pz as usize * width as usize + px as usize];
+
+    let height_map = RgbImage::from_fn(width, depth, |px, pz| {
+        let level = (column(px, pz).top - height.min_y) as f32 / height.height as f32;
+        lit([(level * 255.0) as u8; 3], shade(px, pz))
+    });
+    let surface = RgbImage::from_fn(width, depth, |px, pz| {
+        let Column { top, floor, top_block, biome, .. } = column(px, pz);
+        let tint = biome.tint(top_block);
+        let color = colors.color_of(top_block);
+        let shade = if Fluid::of(top_block).is_some() { (1.0 - (top - floor) as f32 * 0.04).max(0.4) } else { shade(px, pz) };
+        lit(std::array::from_fn(|i| (color[i] as f32 * tint[i]) as u8), shade)
+    });
+    let biomes = RgbImage::from_fn(width, depth, |px, pz| Rgb(biome_color(column(px, pz).biome)));
+    let caves = RgbImage::from_fn(width, depth, |px, pz| {
+        let Column { top, slice, .. } = column(px, pz);
+        if slice != block::AIR {
+            Rgb(colors.color_of(slice))
+        } else if cave_y > top {
+            Rgb([255, 255, 255])
+        } else {
+            Rgb([0, 0, 0])
+        }
+    });
+
+    Maps { height: height_map, surface, biomes, caves }
+}
+
+// Far enough apart to tell neighbours apart at a glance
+pub fn biome_color(biome: Biome) -> [u8; 3] {
+    match biome {
+        Biome::Plains => [141, 179, 96],
+        Biome::Forest => [5, 102, 33],
+        Biome::Desert => [250, 148, 24],
+        Biome::Savanna => [189, 178, 95],
+        Biome::Taiga => [11, 102, 89],
+        Biome::SnowyPlains => [240, 250, 255],
+        Biome::Swamp => [47, 85, 74],
+        Biome::Mountains => [128, 128, 128],
+        Biome::Ocean => [0, 0, 160],
+    }
+}
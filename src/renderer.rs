@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    chunk::Mesh,
+    pos::ChunkPos,
+    texture_atlas::TextureAtlas,
+    world::World,
+};
+
+// Everything the GPU needs to draw a World, kept in sync with it by `update` once per frame.
+// Only the chunks the world remeshed since the last update get uploaded again.
+
+#[derive(Clone, Debug)]
+pub struct ChunkBuffer {
+    pub vertex_buffer: wgpu::Buffer,
+    pub indices_buffer: wgpu::Buffer,
+    pub num_elements: u32,
+}
+
+impl ChunkBuffer {
+    pub fn new(device: &wgpu::Device, mesh: &Mesh) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("chunkbuffer vertex buffer"),
+            contents: bytemuck::cast_slice(&mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let indices_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("chunkbuffer indices buffer"),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        Self {
+            vertex_buffer,
+            indices_buffer,
+            num_elements: mesh.num_elements,
+        }
+    }
+
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.indices_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_elements, 0, 0..1);
+    }
+}
+
+#[derive(Debug)]
+pub struct WorldRenderer {
+    pub texture_atlas: TextureAtlas,
+    // Empty meshes have no buffer
+    chunk_buffers: HashMap<ChunkPos, ChunkBuffer>,
+    entity_buffer: Option<ChunkBuffer>,
+}
+
+impl WorldRenderer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self {
+            texture_atlas: TextureAtlas::new(device, queue),
+            chunk_buffers: HashMap::new(),
+            entity_buffer: None,
+        }
+    }
+
+    // Drops everything uploaded, before drawing another world
+    pub fn clear(&mut self, world: &mut World) {
+        self.chunk_buffers.clear();
+        self.entity_buffer = None;
+        world.mark_all_chunks_remeshed();
+    }
+
+    pub fn update(&mut self, device: &wgpu::Device, world: &mut World) {
+        for pos in world.take_remeshed_chunks() {
+            let mesh = world.chunks.iter().find(|c| c.pos == pos).map(|c| &c.mesh);
+            match mesh {
+                Some(mesh) if mesh.num_elements > 0 => {
+                    self.chunk_buffers.insert(pos, ChunkBuffer::new(device, mesh));
+                }
+                _ => {
+                    self.chunk_buffers.remove(&pos);
+                }
+            }
+        }
+
+        // Entities move every tick, their mesh is small enough to rebuild each frame
+        if world.entities.is_empty() {
+            self.entity_buffer = None;
+        } else {
+            self.entity_buffer = Some(ChunkBuffer::new(device, &world.entity_mesh()));
+        }
+    }
+
+    // The pipeline and camera have to be set already, the atlas goes in bind group 0
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_bind_group(0, &self.texture_atlas.diffuse_bind_group, &[]);
+        for chunk_buffer in self.chunk_buffers.values() {
+            chunk_buffer.draw(render_pass);
+        }
+        if let Some(entity_buffer) = &self.entity_buffer {
+            entity_buffer.draw(render_pass);
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::{
+    anvil::ImportedChunk,
+    block::BlockType,
+    chunk::{WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    dimension::{self, Dimension, Dimensions, Sky, OVERWORLD},
+    entity::{Entity, EntityKind},
+    generator::GeneratorSettings,
+    nbt::{self, Tag},
+    pos::{BlockPos, ChunkPos},
+    tick::{Random, ScheduledTick},
+    world::{World, WorldSettings},
+};
+
+// Saves are gzipped NBT like the other formats we read, with one compound per dimension
+// holding its name, sky and world. Version 1 saves are a single world and load as the
+// overworld. Blocks are stored per chunk
+// as run-length encoded [count, id] pairs in x, y, z order, since most of a chunk is long
+// runs of air or stone. The tick state and entities are saved too so crops, scheduled
+// updates and falling blocks resume where they stopped, along with the features waiting
+// for chunks that aren't generated yet. Saves from before the world height
+// was configurable have no MinY and Height and are 0..256, and ones without a Generator
+// use the default terrain.
+
+const SAVE_VERSION: i32 = 2;
+
+pub struct Save {
+    pub active: String,
+    pub dimensions: Vec<SavedDimension>,
+}
+
+pub struct SavedDimension {
+    pub name: String,
+    pub sky: Sky,
+    pub world: SavedWorld,
+}
+
+pub struct SavedWorld {
+    pub seed: i64,
+    pub height: WorldHeight,
+    pub generator: GeneratorSettings,
+    pub chunks: Vec<ImportedChunk>,
+    pub tick_count: u64,
+    pub random_state: u64,
+    // Sorted in the order they have to run
+    pub scheduled_ticks: Vec<ScheduledTick>,
+    pub entities: Vec<Entity>,
+    // Blocks of trees and such waiting for their chunk to be generated
+    pub pending_features: Vec<(BlockPos, BlockType)>,
+    // Saves from before spawn points were kept don't have one
+    pub spawn: Option<BlockPos>,
+}
+
+pub fn write(dimensions: &Dimensions) -> Result<Vec<u8>> {
+    let saved = dimensions.iter().map(|dimension| {
+        let mut compound = world_compound(&dimension.world);
+        compound.insert("Name".to_string(), Tag::String(dimension.name.clone()));
+        compound.insert("SkyColor".to_string(), Tag::List(dimension.sky.color.iter().map(|&c| Tag::Float(c)).collect()));
+        Tag::Compound(compound)
+    }).collect();
+
+    let mut root = HashMap::new();
+    root.insert("Version".to_string(), Tag::Int(SAVE_VERSION));
+    root.insert("Active".to_string(), Tag::String(dimensions.active().name.clone()));
+    root.insert("Dimensions".to_string(), Tag::List(saved));
+
+    nbt::write_compressed("Save", &Tag::Compound(root))
+}
+
+pub fn read(bytes: &[u8]) -> Result<Save> {
+    let (_, root) = nbt::read_compressed(bytes)?;
+    let version = root.get("Version").and_then(Tag::as_i32).ok_or_else(|| anyhow!("not a world save"))?;
+    if version > SAVE_VERSION {
+        bail!("world was saved by a newer version (save version {})", version);
+    }
+    if version == 1 {
+        return Ok(Save {
+            active: OVERWORLD.to_string(),
+            dimensions: vec![SavedDimension { name: OVERWORLD.to_string(), sky: Sky::default(), world: read_world(&root)? }],
+        });
+    }
+
+    let mut dimensions = Vec::new();
+    for dimension in root.get("Dimensions").and_then(Tag::as_list).unwrap_or_default() {
+        let name = dimension.get("Name").and_then(Tag::as_str).ok_or_else(|| anyhow!("dimension has no name"))?;
+        dimension::check_name(name)?;
+        if dimensions.iter().any(|d: &SavedDimension| d.name == name) {
+            bail!("save has two dimensions named {}", name);
+        }
+        let color: Vec<f32> = dimension.get("SkyColor").and_then(Tag::as_list).unwrap_or_default().iter()
+            .filter_map(|c| c.as_f64().map(|c| c as f32))
+            .collect();
+        let sky = match color[..] {
+            [r, g, b] => Sky { color: [r, g, b] },
+            _ => Sky::default(),
+        };
+        let world = read_world(dimension).with_context(|| format!("failed to read dimension {}", name))?;
+        dimensions.push(SavedDimension { name: name.to_string(), sky, world });
+    }
+    let Some(first) = dimensions.first() else {
+        bail!("save has no dimensions");
+    };
+    let active = root.get("Active").and_then(Tag::as_str).unwrap_or(&first.name).to_string();
+    if !dimensions.iter().any(|d| d.name == active) {
+        bail!("save has no dimension named {}", active);
+    }
+
+    Ok(Save { active, dimensions })
+}
+
+fn world_compound(world: &World) -> HashMap<String, Tag> {
+    let chunks = world.chunks.iter().map(|chunk| {
+        let block_types = chunk.blocks.iter().flatten().flatten().copied();
+        let mut compound = HashMap::new();
+        compound.insert("Pos".to_string(), Tag::IntArray(vec![chunk.pos.x, chunk.pos.z]));
+        compound.insert("Blocks".to_string(), Tag::IntArray(run_length_encode(block_types)));
+        Tag::Compound(compound)
+    }).collect();
+
+    let mut scheduled = world.ticks.scheduled.clone().into_sorted_vec();
+    // into_sorted_vec sorts the Reverse wrappers, so the latest tick comes first
+    scheduled.reverse();
+    let scheduled_ticks = scheduled.into_iter().map(|std::cmp::Reverse(tick)| {
+        let mut compound = HashMap::new();
+        compound.insert("Pos".to_string(), Tag::IntArray(vec![tick.pos.x, tick.pos.y, tick.pos.z]));
+        compound.insert("Time".to_string(), Tag::Long(tick.time as i64));
+        compound.insert("Block".to_string(), Tag::Int(tick.block_type as i32));
+        Tag::Compound(compound)
+    }).collect();
+
+    let entities = world.entities.iter().map(|entity| {
+        let mut compound = HashMap::new();
+        compound.insert("Kind".to_string(), Tag::Byte(entity.kind as i8));
+        compound.insert("Block".to_string(), Tag::Int(entity.block_type as i32));
+        compound.insert("Pos".to_string(), Tag::List(entity.pos.iter().map(|&v| Tag::Float(v)).collect()));
+        compound.insert("Velocity".to_string(), Tag::Float(entity.velocity));
+        compound.insert("Age".to_string(), Tag::Long(entity.age as i64));
+        Tag::Compound(compound)
+    }).collect();
+
+    let pending_features = world.pending_features.values().flatten().map(|&(pos, block_type)| {
+        let mut compound = HashMap::new();
+        compound.insert("Pos".to_string(), Tag::IntArray(vec![pos.x, pos.y, pos.z]));
+        compound.insert("Block".to_string(), Tag::Int(block_type as i32));
+        Tag::Compound(compound)
+    }).collect();
+
+    let mut root = HashMap::new();
+    root.insert("Seed".to_string(), Tag::Long(world.seed));
+    root.insert("MinY".to_string(), Tag::Int(world.height.min_y));
+    root.insert("Height".to_string(), Tag::Int(world.height.height as i32));
+    root.insert("Generator".to_string(), Tag::String(world.generator_settings.to_string()));
+    root.insert("TickCount".to_string(), Tag::Long(world.ticks.tick_count as i64));
+    root.insert("RandomState".to_string(), Tag::Long(world.ticks.random.state as i64));
+    root.insert("Chunks".to_string(), Tag::List(chunks));
+    root.insert("ScheduledTicks".to_string(), Tag::List(scheduled_ticks));
+    root.insert("Entities".to_string(), Tag::List(entities));
+    root.insert("PendingFeatures".to_string(), Tag::List(pending_features));
+    root.insert("Spawn".to_string(), Tag::IntArray(vec![world.spawn.x, world.spawn.y, world.spawn.z]));
+    root
+}
+
+fn read_world(root: &Tag) -> Result<SavedWorld> {
+    let long = |name: &str| root.get(name).and_then(Tag::as_i64).ok_or_else(|| anyhow!("save has no {}", name));
+
+    let height = match (root.get("MinY").and_then(Tag::as_i32), root.get("Height").and_then(Tag::as_i32)) {
+        (Some(min_y), Some(height)) => {
+            if min_y % 16 != 0 || height <= 0 || height % 16 != 0 {
+                bail!("invalid world height {}..{}", min_y, min_y + height);
+            }
+            WorldHeight::new(min_y, height as usize)
+        }
+        _ => WorldHeight::LEGACY,
+    };
+    let generator = match root.get("Generator").and_then(Tag::as_str) {
+        Some(generator) => GeneratorSettings::parse(generator)?,
+        None => GeneratorSettings::Default,
+    };
+
+    let mut chunks = Vec::new();
+    for chunk in root.get("Chunks").and_then(Tag::as_list).unwrap_or_default() {
+        let pos = chunk.get("Pos").and_then(Tag::as_int_array)
+            .filter(|pos| pos.len() == 2)
+            .ok_or_else(|| anyhow!("chunk has no position"))?;
+        let runs = chunk.get("Blocks").and_then(Tag::as_int_array)
+            .ok_or_else(|| anyhow!("chunk {}, {} has no blocks", pos[0], pos[1]))?;
+        let block_types = run_length_decode(runs, height)
+            .ok_or_else(|| anyhow!("chunk {}, {} has the wrong number of blocks", pos[0], pos[1]))?;
+        chunks.push(ImportedChunk { pos: ChunkPos::new(pos[0], pos[1]), block_types });
+    }
+
+    let mut scheduled_ticks = Vec::new();
+    for (order, tick) in root.get("ScheduledTicks").and_then(Tag::as_list).unwrap_or_default().iter().enumerate() {
+        let (Some(pos), Some(time), Some(block_type)) = (
+            tick.get("Pos").and_then(Tag::as_int_array).filter(|pos| pos.len() == 3),
+            tick.get("Time").and_then(Tag::as_i64),
+            tick.get("Block").and_then(Tag::as_i64),
+        ) else {
+            bail!("invalid scheduled tick");
+        };
+        scheduled_ticks.push(ScheduledTick {
+            time: time as u64,
+            order: order as u64,
+            pos: BlockPos::new(pos[0], pos[1], pos[2]),
+            block_type: block_type as BlockType,
+        });
+    }
+
+    let mut entities = Vec::new();
+    for entity in root.get("Entities").and_then(Tag::as_list).unwrap_or_default() {
+        let pos: Vec<f32> = entity.get("Pos").and_then(Tag::as_list).unwrap_or_default().iter()
+            .filter_map(|v| v.as_f64().map(|v| v as f32))
+            .collect();
+        let (Some(kind), Some(block_type), &[x, y, z]) = (
+            entity.get("Kind").and_then(Tag::as_i64),
+            entity.get("Block").and_then(Tag::as_i64),
+            &pos[..],
+        ) else {
+            bail!("invalid entity");
+        };
+        entities.push(Entity {
+            kind: if kind == EntityKind::Item as i64 { EntityKind::Item } else { EntityKind::FallingBlock },
+            block_type: block_type as BlockType,
+            pos: [x, y, z],
+            velocity: entity.get("Velocity").and_then(Tag::as_f64).unwrap_or(0.0) as f32,
+            age: entity.get("Age").and_then(Tag::as_i64).unwrap_or(0) as u64,
+        });
+    }
+
+    let mut pending_features = Vec::new();
+    for feature in root.get("PendingFeatures").and_then(Tag::as_list).unwrap_or_default() {
+        let (Some(pos), Some(block_type)) = (
+            feature.get("Pos").and_then(Tag::as_int_array).filter(|pos| pos.len() == 3),
+            feature.get("Block").and_then(Tag::as_i64),
+        ) else {
+            bail!("invalid pending feature");
+        };
+        pending_features.push((BlockPos::new(pos[0], pos[1], pos[2]), block_type as BlockType));
+    }
+
+    // Saves from when seeds were 32 bit store them as an Int
+    let seed = match root.get("Seed") {
+        Some(&Tag::Int(seed)) => seed as u32 as i64,
+        _ => long("Seed")?,
+    };
+
+    Ok(SavedWorld {
+        seed,
+        height,
+        generator,
+        chunks,
+        tick_count: long("TickCount")? as u64,
+        random_state: long("RandomState")? as u64,
+        scheduled_ticks,
+        entities,
+        pending_features,
+        spawn: root.get("Spawn").and_then(Tag::as_int_array).and_then(|pos| match *pos {
+            [x, y, z] => Some(BlockPos::new(x, y, z)),
+            _ => None,
+        }),
+    })
+}
+
+fn run_length_encode(block_types: impl Iterator<Item = BlockType>) -> Vec<i32> {
+    let mut runs: Vec<i32> = Vec::new();
+    for block_type in block_types {
+        match runs.as_mut_slice() {
+            [.., count, last] if *last == block_type as i32 => *count += 1,
+            _ => runs.extend_from_slice(&[1, block_type as i32]),
+        }
+    }
+    runs
+}
+
+fn run_length_decode(runs: &[i32], height: WorldHeight) -> Option<Vec<Vec<Vec<BlockType>>>> {
+    let len = CHUNK_X_SIZE * height.height * CHUNK_Z_SIZE;
+    let mut flat = Vec::with_capacity(len);
+    for run in runs.chunks_exact(2) {
+        let count = run[0].max(0) as usize;
+        // Checked first so a corrupt count can't make us allocate more than a chunk
+        if flat.len() + count > len {
+            return None;
+        }
+        flat.extend(std::iter::repeat_n(run[1] as BlockType, count));
+    }
+    if flat.len() != len {
+        return None;
+    }
+    let mut flat = flat.into_iter();
+    Some((0..CHUNK_X_SIZE).map(|_| {
+        (0..height.height).map(|_| flat.by_ref().take(CHUNK_Z_SIZE).collect()).collect()
+    }).collect())
+}
+
+impl Dimensions {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &std::path::Path) -> Result<()> {
+        std::fs::write(path, write(self)?).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(&mut self, path: &std::path::Path) -> Result<()> {
+        let bytes = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let saved = read(&bytes)?;
+        self.restore(saved)
+    }
+
+    // The dimensions become the ones of the save. A dimension with the same name as a saved
+    // one keeps its world's subscribers, the ones that aren't in the save are dropped.
+    pub fn restore(&mut self, saved: Save) -> Result<()> {
+        let mut old = std::mem::take(&mut self.dimensions);
+        for dimension in saved.dimensions {
+            let world = match old.iter().position(|d| d.name == dimension.name) {
+                Some(index) => {
+                    let mut world = old.swap_remove(index).world;
+                    world.restore(dimension.world);
+                    world
+                }
+                None => World::from_saved(dimension.world),
+            };
+            self.dimensions.push(Dimension { name: dimension.name, sky: dimension.sky, world });
+        }
+        self.switch_to(&saved.active)
+    }
+}
+
+impl World {
+    // A world with only the chunks of the save
+    pub fn from_saved(saved: SavedWorld) -> Self {
+        let mut world = World::with_settings(WorldSettings {
+            seed: saved.seed,
+            radius: 0,
+            height: saved.height,
+            generator: saved.generator,
+        });
+        world.import_chunks(saved.chunks);
+
+        let ticks = &mut world.ticks;
+        ticks.tick_count = saved.tick_count;
+        ticks.random = Random::new(saved.random_state);
+        for tick in saved.scheduled_ticks {
+            ticks.schedule(tick.pos, tick.block_type, tick.time.saturating_sub(saved.tick_count));
+        }
+        world.entities = saved.entities;
+
+        for (pos, block_type) in saved.pending_features {
+            world.pending_features.entry(pos.chunk()).or_default().push((pos, block_type));
+        }
+        world.spawn = saved.spawn.unwrap_or_else(|| world.find_spawn());
+        world
+    }
+
+    // Replaces the whole world with the save, only the subscribers are kept. They're told
+    // about the chunks of the old world going away and the saved ones being loaded.
+    pub fn restore(&mut self, saved: SavedWorld) {
+        self.unload_all_chunks();
+        let mut events = std::mem::take(&mut self.events);
+        let mut restored = World::from_saved(saved);
+        events.append(&mut restored.events);
+        *self = restored;
+        self.events = events;
+    }
+}
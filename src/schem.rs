@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::block;
+use crate::block_mapping::BlockMapping;
+use crate::nbt::{self, Tag};
+use crate::volume::BlockVolume;
+
+// Sponge schematics (.schem), versions 1 to 3 are read, version 2 is written since
+// it's the one every tool (WorldEdit, Amulet, MCEdit forks) can load.
+
+// Minecraft 1.20.1, only used as a hint for the tools reading our files
+const DATA_VERSION: i32 = 3465;
+// 256 MiB of block types, sizes in the header are checked against it before allocating
+const MAX_BLOCKS: usize = 512 * 512 * 256;
+
+pub fn read(bytes: &[u8], mapping: &BlockMapping) -> Result<BlockVolume> {
+    let (_, root) = nbt::read_compressed(bytes)?;
+
+    // Version 3 wraps everything in a "Schematic" compound and moves the blocks in a "Blocks" compound
+    let (schematic, palette, data) = match root.get("Schematic") {
+        Some(schematic) => {
+            let blocks = schematic.get("Blocks").ok_or_else(|| anyhow!("schematic has no Blocks"))?;
+            (schematic, blocks.get("Palette"), blocks.get("Data"))
+        }
+        None => (&root, root.get("Palette"), root.get("BlockData")),
+    };
+
+    let dimension = |name: &str| -> Result<usize> {
+        let value = schematic.get(name).and_then(Tag::as_i64).ok_or_else(|| anyhow!("schematic has no {}", name))?;
+        // Shorts are signed in NBT but sizes go up to 65535
+        Ok(value as u16 as usize)
+    };
+    let size = [dimension("Width")?, dimension("Height")?, dimension("Length")?];
+
+    let palette = palette.and_then(Tag::as_compound).ok_or_else(|| anyhow!("schematic has no palette"))?;
+    let mut block_types = HashMap::new();
+    for (name, index) in palette {
+        let index = index.as_i64().ok_or_else(|| anyhow!("palette entry {} isn't a number", name))?;
+        block_types.insert(index as usize, mapping.get(name));
+    }
+
+    let data = data.and_then(Tag::as_byte_array).ok_or_else(|| anyhow!("schematic has no block data"))?;
+    let block_count = size.iter().product::<usize>();
+    if block_count > MAX_BLOCKS {
+        bail!("schematic is too large ({}x{}x{} blocks)", size[0], size[1], size[2]);
+    }
+    // Every block takes at least one byte of varint
+    if data.len() < block_count {
+        bail!("block data is shorter than the schematic");
+    }
+    let mut volume = BlockVolume::new(size);
+    let mut bytes = data.iter().map(|&b| b as u8);
+    for i in 0..volume.blocks.len() {
+        let index = read_varint(&mut bytes)?;
+        volume.blocks[i] = block_types.get(&index).copied().unwrap_or(mapping.placeholder);
+    }
+
+    Ok(volume)
+}
+
+pub fn write(volume: &BlockVolume, mapping: &BlockMapping) -> Result<Vec<u8>> {
+    if volume.size.iter().any(|&s| s > u16::MAX as usize) {
+        bail!("schematics can't be larger than 65535 blocks on a side");
+    }
+
+    let mut palette: HashMap<String, Tag> = HashMap::new();
+    let mut indices = HashMap::new();
+    let mut data = Vec::with_capacity(volume.blocks.len());
+    for &block_type in &volume.blocks {
+        // Block state like fluid levels has no equivalent in the palette names we write
+        let block_type = block::id_of(block_type);
+        let index = *indices.entry(block_type).or_insert_with(|| {
+            let index = palette.len();
+            palette.insert(mapping.name_of(block_type), Tag::Int(index as i32));
+            index
+        });
+        write_varint(&mut data, index);
+    }
+
+    let mut schematic = HashMap::new();
+    schematic.insert("Version".to_string(), Tag::Int(2));
+    schematic.insert("DataVersion".to_string(), Tag::Int(DATA_VERSION));
+    schematic.insert("Width".to_string(), Tag::Short(volume.size[0] as u16 as i16));
+    schematic.insert("Height".to_string(), Tag::Short(volume.size[1] as u16 as i16));
+    schematic.insert("Length".to_string(), Tag::Short(volume.size[2] as u16 as i16));
+    schematic.insert("Offset".to_string(), Tag::IntArray(vec![0, 0, 0]));
+    schematic.insert("PaletteMax".to_string(), Tag::Int(palette.len() as i32));
+    schematic.insert("Palette".to_string(), Tag::Compound(palette));
+    schematic.insert("BlockData".to_string(), Tag::ByteArray(data.into_iter().map(|b| b as i8).collect()));
+    schematic.insert("BlockEntities".to_string(), Tag::List(Vec::new()));
+
+    nbt::write_compressed("Schematic", &Tag::Compound(schematic))
+}
+
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Result<usize> {
+    let mut value = 0usize;
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next().ok_or_else(|| anyhow!("block data is shorter than the schematic"))?;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("varint in block data is too long")
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        if value < 0x80 {
+            out.push(value as u8);
+            return;
+        }
+        out.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+}
@@ -0,0 +1,32 @@
+// World seeds. A seed is an i64 like Minecraft's, typed in as a number or as any other text,
+// which is hashed into one. The hash is FNV-1a over the UTF-8 bytes so a text seed gives the
+// same world on every build, unlike the std hashers whose output can change between
+// releases. The noise and the generators take 32 bit seeds made from the full one, and the
+// cave math goes through libm instead of the platform's sin and cos so the wasm build carves
+// the same tunnels as the native one.
+
+use crate::biome;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// A number is used as it is, anything else is hashed, surrounding whitespace doesn't count
+pub fn parse(text: &str) -> i64 {
+    let text = text.trim();
+    text.parse().unwrap_or_else(|_| hash_text(text))
+}
+
+pub fn hash_text(text: &str) -> i64 {
+    text.bytes().fold(FNV_OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME)) as i64
+}
+
+// The seed the generators are built with. Seeds in 0..=u32::MAX map to themselves, so worlds
+// from before seeds were 64 bit keep generating the same terrain. Any other seed is mixed
+// before it's cut to 32 bits, so nearby seeds like -1 and 0 don't get related terrain. Some
+// seeds still share a terrain, as 2^64 of them go into 2^32, but they're scattered.
+pub fn noise_seed(seed: i64) -> u32 {
+    match u32::try_from(seed) {
+        Ok(seed) => seed,
+        Err(_) => biome::mix(seed as u64) as u32,
+    }
+}
@@ -62,11 +62,13 @@ var<uniform> camera: CameraUniform;
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec3<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec3<f32>,
 }
 
 @vertex
@@ -75,6 +77,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
+    out.color = model.color;
     out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.0);
     return out;
 }
@@ -88,5 +91,6 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    let texel = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    return vec4<f32>(texel.rgb * in.color, texel.a);
 }
@@ -0,0 +1,473 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    biome::position_hash,
+    block::{self, BlockType},
+    block_mapping::BlockMapping,
+    chunk::{WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    pos::{BlockPos, ChunkPos},
+    tick::Random,
+};
+
+// Hand-built structures scattered over the world by the default generator. Templates are
+// JSON files (see res/structures) drawing each layer of the structure with characters from
+// a palette. A template can have jigsaws, points on its edge where a piece from a pool of
+// templates is attached, so a structure can be put together from several pieces.
+//
+// Each kind of structure splits the world into squares of `spacing` chunks and starts at
+// most one structure in a random chunk of each square, away from the square's far edges so
+// two are always `separation` chunks apart. Like cave tunnels, every chunk lays out the
+// structures starting close enough to reach it and writes the part that falls inside it,
+// so a structure comes out the same whichever chunk is generated first. Structures on the
+// surface sit on the ground under them, with a foundation filling any gap below.
+
+// How far in chunks a structure can reach from the chunk it starts in
+const REACH: i32 = 4;
+// The most blocks of foundation under a structure
+const FOUNDATION_DEPTH: i32 = 12;
+
+// Underground structures start in -MAX_DEPTH..MAX_DEPTH, as low and high as Minecraft lets a
+// dimension go
+const MAX_DEPTH: i32 = 2032;
+
+const BUILTIN_TEMPLATES: [&str; 6] = [
+    include_str!("../res/structures/ruin.json"),
+    include_str!("../res/structures/hut.json"),
+    include_str!("../res/structures/dungeon.json"),
+    include_str!("../res/structures/camp_center.json"),
+    include_str!("../res/structures/camp_hut.json"),
+    include_str!("../res/structures/camp_tent.json"),
+];
+
+// Seen from above, the template's -z side is north
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Facing {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Facing {
+    const CLOCKWISE: [Facing; 4] = [Facing::North, Facing::East, Facing::South, Facing::West];
+
+    // Quarter turns clockwise seen from above, like StructureTemplate::rotate
+    pub fn rotated(self, quarter_turns: u32) -> Facing {
+        Self::CLOCKWISE[(self as usize + quarter_turns as usize) % 4]
+    }
+
+    pub fn opposite(self) -> Facing {
+        self.rotated(2)
+    }
+
+    // [x, z]
+    pub fn offset(self) -> [i32; 2] {
+        match self {
+            Facing::North => [0, -1],
+            Facing::East => [1, 0],
+            Facing::South => [0, 1],
+            Facing::West => [-1, 0],
+        }
+    }
+}
+
+// A piece from `pool` can be attached here, with a jigsaw of its own facing back
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Jigsaw {
+    pub pos: [i32; 3],
+    pub facing: Facing,
+    pub pool: String,
+}
+
+// A template as it's written
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TemplateFile {
+    pub name: String,
+    // Block names of the characters of the layers, a space leaves the terrain there as it is
+    pub palette: BTreeMap<char, String>,
+    // From the bottom up, each one rows along z of characters along x
+    pub layers: Vec<Vec<String>>,
+    #[serde(default)]
+    pub jigsaws: Vec<Jigsaw>,
+    // Fills the air and water under the bottom layer
+    #[serde(default)]
+    pub foundation: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TemplateFile", into = "TemplateFile")]
+pub struct StructureTemplate {
+    pub name: String,
+    pub size: [i32; 3],
+    // From the min corner, with the blocks of every character but spaces
+    pub blocks: Vec<([i32; 3], BlockType)>,
+    pub jigsaws: Vec<Jigsaw>,
+    pub foundation: Option<BlockType>,
+    file: TemplateFile,
+}
+
+impl TryFrom<TemplateFile> for StructureTemplate {
+    type Error = anyhow::Error;
+
+    fn try_from(file: TemplateFile) -> Result<Self> {
+        let mapping = BlockMapping::default();
+        let lookup = |name: &str| mapping.lookup(name).ok_or_else(|| anyhow!("unknown block {:?} in template {}", name, file.name));
+        let mut palette = HashMap::new();
+        for (&character, name) in &file.palette {
+            if character == ' ' {
+                bail!("spaces can't be in the palette of template {}, they leave the terrain as it is", file.name);
+            }
+            palette.insert(character, lookup(name)?);
+        }
+
+        let size_z = file.layers.first().map_or(0, Vec::len);
+        let size_x = file.layers.first().and_then(|layer| layer.first()).map_or(0, |row| row.chars().count());
+        if size_x == 0 || size_z == 0 {
+            bail!("template {} is empty", file.name);
+        }
+        let mut blocks = Vec::new();
+        for (y, layer) in file.layers.iter().enumerate() {
+            if layer.len() != size_z || layer.iter().any(|row| row.chars().count() != size_x) {
+                bail!("layer {} of template {} isn't {} by {}", y, file.name, size_x, size_z);
+            }
+            for (z, row) in layer.iter().enumerate() {
+                for (x, character) in row.chars().enumerate().filter(|&(_, character)| character != ' ') {
+                    let &block_type = palette.get(&character)
+                        .ok_or_else(|| anyhow!("{:?} in template {} isn't in its palette", character, file.name))?;
+                    blocks.push(([x as i32, y as i32, z as i32], block_type));
+                }
+            }
+        }
+
+        let size = [size_x as i32, file.layers.len() as i32, size_z as i32];
+        if let Some(jigsaw) = file.jigsaws.iter().find(|jigsaw| (0..3).any(|i| !(0..size[i]).contains(&jigsaw.pos[i]))) {
+            bail!("jigsaw at {:?} is outside template {}", jigsaw.pos, file.name);
+        }
+        Ok(Self {
+            name: file.name.clone(),
+            size,
+            blocks,
+            jigsaws: file.jigsaws.clone(),
+            foundation: file.foundation.as_deref().map(lookup).transpose()?,
+            file,
+        })
+    }
+}
+
+impl From<StructureTemplate> for TemplateFile {
+    fn from(template: StructureTemplate) -> Self {
+        template.file
+    }
+}
+
+impl StructureTemplate {
+    pub fn from_json(text: &str) -> Result<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        use anyhow::Context;
+
+        let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_json(&text).with_context(|| format!("invalid structure template in {}", path.display()))
+    }
+
+    pub fn builtin() -> Vec<StructureTemplate> {
+        BUILTIN_TEMPLATES.iter().map(|text| Self::from_json(text).unwrap()).collect()
+    }
+
+    pub fn rotated_size(&self, quarter_turns: u32) -> [i32; 3] {
+        let [x, y, z] = self.size;
+        if quarter_turns % 2 == 1 { [z, y, x] } else { [x, y, z] }
+    }
+
+    // Where an offset ends up with the template turned clockwise seen from above, like BlockVolume::rotated_y
+    pub fn rotate(&self, [x, y, z]: [i32; 3], quarter_turns: u32) -> [i32; 3] {
+        let [size_x, _, size_z] = self.size;
+        match quarter_turns % 4 {
+            1 => [size_z - 1 - z, y, x],
+            2 => [size_x - 1 - x, y, size_z - 1 - z],
+            3 => [z, y, size_x - 1 - x],
+            _ => [x, y, z],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Placement {
+    // On the ground, never under the sea
+    Surface,
+    // With the bottom somewhere in min_y..=max_y
+    Underground { min_y: i32, max_y: i32 },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StructureKind {
+    pub name: String,
+    // The template of the first piece
+    pub start: String,
+    // The templates of each pool jigsaws can name
+    #[serde(default)]
+    pub pools: BTreeMap<String, Vec<String>>,
+    // How many pieces away from the first one jigsaws are followed
+    #[serde(default)]
+    pub max_depth: u32,
+    // In chunks
+    pub spacing: i32,
+    pub separation: i32,
+    // Keeps kinds with the same spacing from starting in the same chunks
+    pub salt: u32,
+    pub placement: Placement,
+}
+
+impl StructureKind {
+    pub fn defaults() -> Vec<Self> {
+        let kind = |name: &str, spacing, separation, salt, placement| StructureKind {
+            name: name.to_string(),
+            start: name.to_string(),
+            pools: BTreeMap::new(),
+            max_depth: 0,
+            spacing,
+            separation,
+            salt,
+            placement,
+        };
+        vec![
+            kind("ruin", 10, 3, 1001, Placement::Surface),
+            kind("hut", 14, 5, 1002, Placement::Surface),
+            kind("dungeon", 6, 2, 1003, Placement::Underground { min_y: -48, max_y: 32 }),
+            StructureKind {
+                start: "camp_center".to_string(),
+                pools: BTreeMap::from([("camp_buildings".to_string(), vec!["camp_hut".to_string(), "camp_tent".to_string()])]),
+                max_depth: 1,
+                ..kind("camp", 20, 8, 1004, Placement::Surface)
+            },
+        ]
+    }
+}
+
+// Fails on kinds that can't be placed, `templates` are added to the built-in ones
+pub fn check(kinds: &[StructureKind], templates: &[StructureTemplate]) -> Result<()> {
+    let builtin = StructureTemplate::builtin();
+    let exists = |name: &String| builtin.iter().chain(templates).any(|template| &template.name == name);
+    for kind in kinds {
+        if kind.spacing <= 0 || kind.separation < 0 || kind.separation >= kind.spacing {
+            bail!("structure {} needs a spacing over its separation", kind.name);
+        }
+        if let Some(name) = std::iter::once(&kind.start).chain(kind.pools.values().flatten()).find(|name| !exists(name)) {
+            bail!("structure {} uses unknown template {}", kind.name, name);
+        }
+        if let Placement::Underground { min_y, max_y } = kind.placement
+            && !(-MAX_DEPTH <= min_y && min_y <= max_y && max_y < MAX_DEPTH) {
+            bail!("structure {} needs min_y <= max_y, both in {}..{}", kind.name, -MAX_DEPTH, MAX_DEPTH);
+        }
+    }
+    Ok(())
+}
+
+// A template turned and put somewhere
+#[derive(Clone, Copy, Debug)]
+pub struct Piece<'a> {
+    pub template: &'a StructureTemplate,
+    pub rotation: u32,
+    pub min: BlockPos,
+}
+
+impl Piece<'_> {
+    // Included
+    pub fn max(&self) -> BlockPos {
+        let [x, y, z] = self.template.rotated_size(self.rotation);
+        self.min.offset(x - 1, y - 1, z - 1)
+    }
+
+    pub fn at(&self, offset: [i32; 3]) -> BlockPos {
+        let [x, y, z] = self.template.rotate(offset, self.rotation);
+        self.min.offset(x, y, z)
+    }
+
+    pub fn overlaps(&self, other: &Piece) -> bool {
+        let (a, b) = (self.max(), other.max());
+        self.min.x <= b.x && other.min.x <= a.x && self.min.y <= b.y && other.min.y <= a.y && self.min.z <= b.z && other.min.z <= a.z
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = (BlockPos, BlockType)> + '_ {
+        self.template.blocks.iter().map(|&(offset, block_type)| (self.at(offset), block_type))
+    }
+
+    // Writes the part of the piece inside a chunk
+    fn write(&self, pos: ChunkPos, height: WorldHeight, blocks: &mut [Vec<Vec<BlockType>>]) {
+        let origin = pos.min_block();
+        let index = |at: BlockPos| {
+            let (x, z) = (at.x - origin.x, at.z - origin.z);
+            let inside = (0..CHUNK_X_SIZE as i32).contains(&x) && (0..CHUNK_Z_SIZE as i32).contains(&z);
+            inside.then(|| height.index(at.y).map(|y| [x as usize, y, z as usize])).flatten()
+        };
+        for (at, block_type) in self.blocks() {
+            if let Some([x, y, z]) = index(at) {
+                blocks[x][y][z] = block_type;
+            }
+        }
+
+        let Some(foundation) = self.template.foundation else {
+            return;
+        };
+        for &(offset, _) in self.template.blocks.iter().filter(|(offset, _)| offset[1] == 0) {
+            let bottom = self.at(offset);
+            for y in (bottom.y - FOUNDATION_DEPTH..bottom.y).rev() {
+                let Some([x, y, z]) = index(BlockPos::new(bottom.x, y, bottom.z)) else {
+                    break;
+                };
+                if blocks[x][y][z] != block::AIR && blocks[x][y][z] != block::WATER {
+                    break;
+                }
+                blocks[x][y][z] = foundation;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Structures {
+    seed: u32,
+    kinds: Vec<StructureKind>,
+    templates: HashMap<String, StructureTemplate>,
+}
+
+impl Structures {
+    // `templates` are added to the built-in ones, replacing those with the same name
+    pub fn new(seed: u32, kinds: Vec<StructureKind>, templates: Vec<StructureTemplate>) -> Self {
+        let templates = StructureTemplate::builtin().into_iter().chain(templates)
+            .map(|template| (template.name.clone(), template))
+            .collect();
+        Self { seed, kinds, templates }
+    }
+
+    pub fn kinds(&self) -> &[StructureKind] {
+        &self.kinds
+    }
+
+    // The chunk a structure can start in, for a square of `spacing` chunks
+    pub fn start_chunk(&self, kind: &StructureKind, square_x: i32, square_z: i32) -> ChunkPos {
+        let mut random = Random::new(position_hash(self.seed.wrapping_add(kind.salt), square_x, square_z));
+        let range = (kind.spacing - kind.separation) as u32;
+        ChunkPos::new(
+            square_x * kind.spacing + random.below(range) as i32,
+            square_z * kind.spacing + random.below(range) as i32,
+        )
+    }
+
+    // The pieces of the structure starting in `start`, if it can start there
+    pub fn layout(
+        &self,
+        kind: &StructureKind,
+        start: ChunkPos,
+        height: WorldHeight,
+        sea_level: i32,
+        surface_at: &dyn Fn(i32, i32) -> i32,
+    ) -> Option<Vec<Piece<'_>>> {
+        let template = self.templates.get(&kind.start)?;
+        let mut random = Random::new(position_hash(self.seed.wrapping_add(kind.salt).wrapping_add(1), start.x, start.z));
+        let rotation = random.below(4);
+        let [size_x, size_y, size_z] = template.rotated_size(rotation);
+        let center = start.min_block().offset(CHUNK_X_SIZE as i32 / 2, 0, CHUNK_Z_SIZE as i32 / 2);
+        let (x, z) = (center.x - size_x / 2, center.z - size_z / 2);
+        let y = match kind.placement {
+            Placement::Surface => {
+                // Averaged over the corners and middle so it settles into slopes
+                let corners = [[0, 0], [size_x - 1, 0], [0, size_z - 1], [size_x - 1, size_z - 1], [size_x / 2, size_z / 2]];
+                let ground = corners.iter().map(|&[dx, dz]| surface_at(x + dx, z + dz)).sum::<i32>().div_euclid(5);
+                if ground <= sea_level {
+                    return None;
+                }
+                ground
+            }
+            Placement::Underground { min_y, max_y } => min_y + random.below((max_y - min_y + 1).max(1) as u32) as i32,
+        };
+        if y < height.min_y || y + size_y > height.max_y() {
+            return None;
+        }
+
+        let fits = |piece: &Piece, pieces: &[Piece]| {
+            let (min, max) = (piece.min.chunk(), piece.max().chunk());
+            let near = |chunk: ChunkPos| (chunk.x - start.x).abs() <= REACH && (chunk.z - start.z).abs() <= REACH;
+            near(min) && near(max) && piece.min.y >= height.min_y && piece.max().y < height.max_y()
+                && !pieces.iter().any(|other| other.overlaps(piece))
+        };
+        let first = Piece { template, rotation, min: BlockPos::new(x, y, z) };
+        if !fits(&first, &[]) {
+            return None;
+        }
+        let mut pieces = vec![first];
+        let mut open: VecDeque<_> = template.jigsaws.iter()
+            .map(|jigsaw| (first.at(jigsaw.pos), jigsaw.facing.rotated(rotation), &jigsaw.pool, 1))
+            .collect();
+
+        while let Some((at, facing, pool, depth)) = open.pop_front() {
+            let Some(names) = kind.pools.get(pool).filter(|names| depth <= kind.max_depth && !names.is_empty()) else {
+                continue;
+            };
+            let [dx, dz] = facing.offset();
+            let target = at.offset(dx, 0, dz);
+            let (first_name, first_rotation) = (random.below(names.len() as u32) as usize, random.below(4));
+            'candidates: for i in 0..names.len() {
+                let Some(template) = self.templates.get(&names[(first_name + i) % names.len()]) else {
+                    continue;
+                };
+                for turn in 0..4 {
+                    let rotation = (first_rotation + turn) % 4;
+                    for (j, jigsaw) in template.jigsaws.iter().enumerate() {
+                        if jigsaw.facing.rotated(rotation) != facing.opposite() {
+                            continue;
+                        }
+                        let [jx, jy, jz] = template.rotate(jigsaw.pos, rotation);
+                        let piece = Piece { template, rotation, min: target.offset(-jx, -jy, -jz) };
+                        if !fits(&piece, &pieces) {
+                            continue;
+                        }
+                        for other in template.jigsaws.iter().enumerate().filter(|&(k, _)| k != j).map(|(_, other)| other) {
+                            open.push_back((piece.at(other.pos), other.facing.rotated(rotation), &other.pool, depth + 1));
+                        }
+                        pieces.push(piece);
+                        break 'candidates;
+                    }
+                }
+            }
+        }
+        Some(pieces)
+    }
+
+    // Writes the parts of the structures reaching into a chunk
+    pub fn place(
+        &self,
+        pos: ChunkPos,
+        height: WorldHeight,
+        sea_level: i32,
+        surface_at: &dyn Fn(i32, i32) -> i32,
+        blocks: &mut [Vec<Vec<BlockType>>],
+    ) {
+        for kind in &self.kinds {
+            let squares = |c: i32| (c - REACH).div_euclid(kind.spacing)..=(c + REACH).div_euclid(kind.spacing);
+            for square_x in squares(pos.x) {
+                for square_z in squares(pos.z) {
+                    let start = self.start_chunk(kind, square_x, square_z);
+                    if (start.x - pos.x).abs() > REACH || (start.z - pos.z).abs() > REACH {
+                        continue;
+                    }
+                    for piece in self.layout(kind, start, height, sea_level, surface_at).unwrap_or_default() {
+                        if piece.min.chunk().x <= pos.x && pos.x <= piece.max().chunk().x
+                            && piece.min.chunk().z <= pos.z && pos.z <= piece.max().chunk().z
+                        {
+                            piece.write(pos, height, blocks);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
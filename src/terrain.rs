@@ -0,0 +1,260 @@
+use anyhow::{bail, Context, Result};
+use noise::{NoiseFn, OpenSimplex};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cave::{self, CaveSettings},
+    ore::{self, VeinSettings},
+    structure::{self, StructureKind, StructureTemplate},
+    water::DEFAULT_SEA_LEVEL,
+};
+
+// The noise the default generator shapes its terrain with. Each biome gives a base height,
+// an amplitude for the rolling hills and a ruggedness for the ridged mountains, and this
+// turns them into a surface height:
+//
+//   base + amplitude * shape(hills) + ruggedness * ridge_height * ridges
+//
+// where hills is fBm noise in -1..1, shape is a spline that can flatten it into plains or
+// plateaus, and ridges is ridged noise in 0..1. Both are sampled at a position moved
+// around by domain warping so hills and ridges don't line up with the noise grid.
+// Everything is in TerrainSettings along with the caves, ores and structures, which can be
+// loaded from a JSON file.
+
+// OpenSimplex stays within about -0.55..0.55, this stretches it to -1..1. Every noise
+// sampled for worldgen (terrain, climate, caves) is scaled by it.
+pub const NOISE_STRETCH: f64 = 1.8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseKind {
+    // Octaves added together, in -1..1
+    Fbm,
+    // Octaves folded into sharp crests, in 0..1
+    Ridged,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseSettings {
+    pub kind: NoiseKind,
+    pub octaves: u32,
+    // In blocks per noise unit, for the first octave
+    pub scale: f64,
+    // Frequency multiplier from one octave to the next
+    pub lacunarity: f64,
+    // Amplitude multiplier from one octave to the next
+    pub persistence: f64,
+}
+
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self { kind: NoiseKind::Fbm, octaves: 4, scale: 64.0, lacunarity: 2.0, persistence: 0.5 }
+    }
+}
+
+// Past this the octaves are finer than a block and only cost time
+const MAX_OCTAVES: u32 = 16;
+
+impl NoiseSettings {
+    // Settings that would make heights NaN or infinite, or take forever to sample, are errors
+    fn check(&self, name: &str) -> Result<()> {
+        if !(1..=MAX_OCTAVES).contains(&self.octaves) {
+            bail!("{} noise needs 1 to {} octaves, not {}", name, MAX_OCTAVES, self.octaves);
+        }
+        let positive = |value: f64| value.is_finite() && value > 0.0;
+        if !(positive(self.scale) && positive(self.lacunarity)) {
+            bail!("{} noise needs a scale and lacunarity over 0", name);
+        }
+        if !self.persistence.is_finite() {
+            bail!("{} noise needs a finite persistence", name);
+        }
+        Ok(())
+    }
+}
+
+// A stack of octaves, each its own OpenSimplex so they don't line up
+#[derive(Clone, Debug)]
+pub struct FractalNoise {
+    settings: NoiseSettings,
+    octaves: Vec<OpenSimplex>,
+    // Of all the octaves' amplitudes, to bring the total back into range
+    total_amplitude: f64,
+}
+
+impl FractalNoise {
+    pub fn new(seed: u32, settings: &NoiseSettings) -> Self {
+        let octaves = (0..settings.octaves.max(1)).map(|i| OpenSimplex::new(seed.wrapping_add(i))).collect::<Vec<_>>();
+        let total_amplitude = (0..octaves.len()).map(|i| settings.persistence.powi(i as i32)).sum();
+        Self { settings: settings.clone(), octaves, total_amplitude }
+    }
+
+    pub fn get(&self, x: f64, z: f64) -> f64 {
+        let mut frequency = 1.0 / self.settings.scale;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        for octave in &self.octaves {
+            let value = (octave.get([x * frequency, z * frequency]) * NOISE_STRETCH).clamp(-1.0, 1.0);
+            total += amplitude * match self.settings.kind {
+                NoiseKind::Fbm => value,
+                NoiseKind::Ridged => (1.0 - value.abs()).powi(2),
+            };
+            frequency *= self.settings.lacunarity;
+            amplitude *= self.settings.persistence;
+        }
+        total / self.total_amplitude
+    }
+}
+
+// Maps a value through points joined by straight lines, values past the ends get the end points
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Spline {
+    // [input, output] in increasing input order
+    pub points: Vec<[f64; 2]>,
+}
+
+impl Spline {
+    pub fn at(&self, value: f64) -> f64 {
+        let Some(first) = self.points.first() else {
+            return value;
+        };
+        if value <= first[0] {
+            return first[1];
+        }
+        for pair in self.points.windows(2) {
+            let ([x0, y0], [x1, y1]) = (pair[0], pair[1]);
+            if value <= x1 {
+                let t = if x1 > x0 { (value - x0) / (x1 - x0) } else { 1.0 };
+                return y0 + (y1 - y0) * t;
+            }
+        }
+        self.points.last().unwrap()[1]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WarpSettings {
+    // How far in blocks positions can be moved
+    pub strength: f64,
+    pub noise: NoiseSettings,
+}
+
+impl Default for WarpSettings {
+    fn default() -> Self {
+        Self { strength: 24.0, noise: NoiseSettings { octaves: 2, scale: 128.0, ..Default::default() } }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainSettings {
+    // Multiplies the amplitude and ridge height of every biome
+    pub amplification: f64,
+    pub hills: NoiseSettings,
+    pub shape: Spline,
+    pub ridges: NoiseSettings,
+    // Blocks the ridges add where the ruggedness is 1
+    pub ridge_height: f64,
+    pub warp: WarpSettings,
+    // Low terrain is filled with water up to this y
+    pub sea_level: i32,
+    pub caves: CaveSettings,
+    pub ores: Vec<VeinSettings>,
+    pub structures: Vec<StructureKind>,
+    // Added to the built-in structure templates
+    pub templates: Vec<StructureTemplate>,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            amplification: 1.0,
+            hills: NoiseSettings::default(),
+            // Gentle lowlands, a shelf of plateaus a bit above the middle, then steeper tops
+            shape: Spline { points: vec![[-1.0, -0.8], [-0.2, -0.2], [0.2, 0.4], [0.5, 0.5], [1.0, 1.2]] },
+            ridges: NoiseSettings { kind: NoiseKind::Ridged, octaves: 4, scale: 96.0, lacunarity: 2.0, persistence: 0.45 },
+            ridge_height: 40.0,
+            warp: WarpSettings::default(),
+            sea_level: DEFAULT_SEA_LEVEL,
+            caves: CaveSettings::default(),
+            ores: VeinSettings::defaults(),
+            structures: StructureKind::defaults(),
+            templates: Vec::new(),
+        }
+    }
+}
+
+impl TerrainSettings {
+    pub fn amplified() -> Self {
+        Self { amplification: 3.0, ..Default::default() }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_json(&text).with_context(|| format!("invalid terrain settings in {}", path.display()))
+    }
+
+    // Fields left out keep their default
+    pub fn from_json(text: &str) -> Result<Self> {
+        let settings: Self = serde_json::from_str(text)?;
+        settings.hills.check("hills")?;
+        settings.ridges.check("ridges")?;
+        settings.warp.noise.check("warp")?;
+        if ![settings.amplification, settings.ridge_height, settings.warp.strength].iter().all(|v| v.is_finite()) {
+            bail!("amplification, ridge height and warp strength have to be finite");
+        }
+        if settings.shape.points.iter().flatten().any(|v| !v.is_finite()) {
+            bail!("shape points have to be finite");
+        }
+        cave::check(&settings.caves)?;
+        ore::check(&settings.ores)?;
+        structure::check(&settings.structures, &settings.templates)?;
+        Ok(settings)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+// The noise of a world, built from its seed and settings
+#[derive(Clone, Debug)]
+pub struct TerrainNoise {
+    pub settings: TerrainSettings,
+    hills: FractalNoise,
+    ridges: FractalNoise,
+    warp_x: FractalNoise,
+    warp_z: FractalNoise,
+}
+
+impl TerrainNoise {
+    // Each noise gets seeds of its own. Their octaves take the seeds after it, at most
+    // MAX_OCTAVES of them, so the noises start 100 apart and past the climate noises (1 to 3)
+    // and the salts the other stages and the default structures use, which are under 2000.
+    pub fn new(seed: u32, settings: TerrainSettings) -> Self {
+        Self {
+            hills: FractalNoise::new(seed.wrapping_add(2000), &settings.hills),
+            ridges: FractalNoise::new(seed.wrapping_add(2100), &settings.ridges),
+            warp_x: FractalNoise::new(seed.wrapping_add(2200), &settings.warp.noise),
+            warp_z: FractalNoise::new(seed.wrapping_add(2300), &settings.warp.noise),
+            settings,
+        }
+    }
+
+    // Where a column samples the hills and ridges
+    fn warp(&self, x: f64, z: f64) -> [f64; 2] {
+        let strength = self.settings.warp.strength;
+        [x + self.warp_x.get(x, z) * strength, z + self.warp_z.get(x, z) * strength]
+    }
+
+    // The surface y of a column, from the blended biome profile there
+    pub fn height(&self, x: i32, z: i32, [base, amplitude, ruggedness]: [f64; 3]) -> f64 {
+        let settings = &self.settings;
+        let [x, z] = self.warp(x as f64, z as f64);
+        let hills = settings.shape.at(self.hills.get(x, z)) * amplitude;
+        let ridges = if ruggedness > 0.0 { self.ridges.get(x, z) * ruggedness * settings.ridge_height } else { 0.0 };
+        base + (hills + ridges) * settings.amplification
+    }
+}
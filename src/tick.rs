@@ -0,0 +1,283 @@
+use std::{cmp::Reverse, collections::{BinaryHeap, HashSet}};
+
+use crate::{
+    block::{self, BlockType},
+    chunk::{CHUNK_X_SIZE, CHUNK_Z_SIZE, SECTION_HEIGHT},
+    entity::Entity,
+    fluid::Fluid,
+    events::BlockChangeCause,
+    pos::{BlockPos, ChunkPos, LocalPos},
+    world::World,
+};
+
+// The world is simulated at a fixed rate, independent of the frame rate. Each tick :
+//   - blocks next to the ones that changed since the last tick are notified,
+//   - scheduled ticks that are due run, in the order they were scheduled,
+//   - a few random blocks of each chunk section get a random tick,
+//   - entities move.
+
+pub const TICKS_PER_SECOND: u32 = 20;
+pub const TICK_DURATION: f32 = 1.0 / TICKS_PER_SECOND as f32;
+// Same as Minecraft's default randomTickSpeed
+pub const RANDOM_TICKS_PER_SECTION: u32 = 3;
+// Don't try to catch up with more than this many ticks after a long frame
+const MAX_TICKS_PER_FRAME: u32 = 10;
+
+// Ticks between a falling block losing its support and starting to fall
+const FALL_DELAY: u64 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ScheduledTick {
+    pub time: u64,
+    // Breaks ties between ticks due at the same time so they run in the order they were scheduled
+    pub order: u64,
+    pub pos: BlockPos,
+    // The tick is dropped if the block was replaced by another one in the meantime
+    pub block_type: BlockType,
+}
+
+#[derive(Clone, Debug)]
+pub struct TickScheduler {
+    pub tick_count: u64,
+    pub random: Random,
+    pub scheduled: BinaryHeap<Reverse<ScheduledTick>>,
+    // A block can only have one pending tick, scheduling it again does nothing
+    pending: HashSet<(BlockPos, BlockType)>,
+    next_order: u64,
+    accumulator: f32,
+    // Positions changed since the last tick, their neighbours get notified
+    changed: Vec<BlockPos>,
+}
+
+impl TickScheduler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            tick_count: 0,
+            random: Random::new(seed),
+            scheduled: BinaryHeap::new(),
+            pending: HashSet::new(),
+            next_order: 0,
+            accumulator: 0.0,
+            changed: Vec::new(),
+        }
+    }
+
+    // Returns how many ticks should run for a frame that took `dt` seconds
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt;
+        let ticks = (self.accumulator / TICK_DURATION) as u32;
+        self.accumulator -= ticks as f32 * TICK_DURATION;
+        ticks.min(MAX_TICKS_PER_FRAME)
+    }
+
+    pub fn schedule(&mut self, pos: BlockPos, block_type: BlockType, delay: u64) {
+        if !self.pending.insert((pos, block_type)) {
+            return;
+        }
+        self.scheduled.push(Reverse(ScheduledTick {
+            time: self.tick_count + delay,
+            order: self.next_order,
+            pos,
+            block_type,
+        }));
+        self.next_order += 1;
+    }
+
+    pub fn block_changed(&mut self, pos: BlockPos) {
+        self.changed.push(pos);
+    }
+
+    fn pop_due(&mut self) -> Option<ScheduledTick> {
+        match self.scheduled.peek() {
+            Some(Reverse(tick)) if tick.time <= self.tick_count => {
+                let Reverse(tick) = self.scheduled.pop()?;
+                self.pending.remove(&(tick.pos, tick.block_type));
+                Some(tick)
+            }
+            _ => None,
+        }
+    }
+}
+
+// xorshift64*, the world needs its own generator so a saved world keeps ticking the same way
+#[derive(Clone, Copy, Debug)]
+pub struct Random {
+    pub state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    // In 0..n
+    pub fn below(&mut self, n: u32) -> u32 {
+        (self.next_u64() % n as u64) as u32
+    }
+
+    // In 0..1
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl World {
+    // Runs every tick due for a frame that took `dt` seconds
+    pub fn update_ticks(&mut self, dt: f32) {
+        let ticks = self.ticks.advance(dt);
+        self.run_ticks(ticks);
+    }
+
+    // Runs `count` ticks right away, then remeshes what changed
+    pub fn run_ticks(&mut self, count: u32) {
+        let mut dirty_chunks = Vec::new();
+        for _ in 0..count {
+            self.tick(&mut dirty_chunks);
+        }
+        self.remesh_chunks(&dirty_chunks);
+    }
+
+    fn tick(&mut self, dirty_chunks: &mut Vec<ChunkPos>) {
+        self.ticks.tick_count += 1;
+
+        // The changed block itself is updated too, so sand placed over air starts falling
+        for pos in std::mem::take(&mut self.ticks.changed) {
+            self.block_updated(pos);
+            for neighbor in pos.neighbors() {
+                self.block_updated(neighbor);
+            }
+        }
+
+        while let Some(tick) = self.ticks.pop_due() {
+            if self.get_block_type(tick.pos) == Some(tick.block_type) {
+                self.scheduled_tick(tick.pos, tick.block_type, dirty_chunks);
+            }
+        }
+
+        for chunk_index in 0..self.chunks.len() {
+            let chunk_pos = self.chunks[chunk_index].pos;
+            let height = self.chunks[chunk_index].height;
+            for section in 0..height.sections() {
+                for _ in 0..RANDOM_TICKS_PER_SECTION {
+                    let random = &mut self.ticks.random;
+                    let local = LocalPos::new(
+                        random.below(CHUNK_X_SIZE as u32) as i32,
+                        height.min_y + (section * SECTION_HEIGHT) as i32 + random.below(SECTION_HEIGHT as u32) as i32,
+                        random.below(CHUNK_Z_SIZE as u32) as i32,
+                    );
+                    let Some(found) = self.chunks[chunk_index].get_block(local) else {
+                        continue;
+                    };
+                    if found == block::AIR {
+                        continue;
+                    }
+                    self.random_tick(chunk_pos.block(local), found, dirty_chunks);
+                }
+            }
+        }
+
+        self.tick_entities(dirty_chunks);
+
+        self.dispatch_events();
+    }
+
+    pub fn set_block_from_tick(&mut self, pos: BlockPos, block_type: BlockType, dirty_chunks: &mut Vec<ChunkPos>) {
+        self.set_block_type(pos, block_type, BlockChangeCause::Tick, dirty_chunks);
+    }
+
+    pub fn is_solid(&self, pos: BlockPos) -> bool {
+        self.get_block_type(pos).is_some_and(|b| block::properties(b).solid)
+    }
+
+    // Sugar cane can grow on top of itself, other plants need a solid block under them
+    fn has_support(&self, pos: BlockPos, block_type: BlockType) -> bool {
+        let below = pos.down();
+        self.is_solid(below) || (block_type == block::SUGAR_CANE && self.get_block_type(below) == Some(block::SUGAR_CANE))
+    }
+
+    fn block_updated(&mut self, pos: BlockPos) {
+        let Some(block_type) = self.get_block_type(pos) else {
+            return;
+        };
+        let properties = block::properties(block_type);
+        // Plants pop off on the next tick when the block holding them is gone
+        if properties.plant && !self.has_support(pos, block_type) {
+            self.ticks.schedule(pos, block_type, 1);
+        }
+        if properties.falling && !self.is_solid(pos.down()) {
+            self.ticks.schedule(pos, block_type, FALL_DELAY);
+        }
+        if let Some(fluid) = Fluid::of(block_type) {
+            self.ticks.schedule(pos, block_type, fluid.tick_delay());
+        }
+    }
+
+    fn scheduled_tick(&mut self, pos: BlockPos, block_type: BlockType, dirty_chunks: &mut Vec<ChunkPos>) {
+        let properties = block::properties(block_type);
+        if properties.plant && !self.has_support(pos, block_type) {
+            self.set_block_from_tick(pos, block::AIR, dirty_chunks);
+        }
+        if properties.falling && !self.is_solid(pos.down()) {
+            self.set_block_from_tick(pos, block::AIR, dirty_chunks);
+            self.entities.push(Entity::falling_block(pos, block_type));
+        }
+        if Fluid::of(block_type).is_some() {
+            self.fluid_tick(pos, block_type, dirty_chunks);
+        }
+    }
+
+    fn random_tick(&mut self, pos: BlockPos, block_type: BlockType, dirty_chunks: &mut Vec<ChunkPos>) {
+        match block_type {
+            block::GRASS => self.grass_tick(pos, dirty_chunks),
+            block::LEAVES => self.leaves_tick(pos, dirty_chunks),
+            block::WHEAT..block::WHEAT_RIPE => {
+                let on_farmland = self.get_block_type(pos.down()) == Some(block::FARMLAND);
+                if on_farmland && self.ticks.random.below(3) == 0 {
+                    self.set_block_from_tick(pos, block_type + 1, dirty_chunks);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Covered grass dies, otherwise it spreads to a nearby dirt block that has nothing on top
+    fn grass_tick(&mut self, pos: BlockPos, dirty_chunks: &mut Vec<ChunkPos>) {
+        if self.is_solid(pos.up()) {
+            self.set_block_from_tick(pos, block::DIRT, dirty_chunks);
+            return;
+        }
+        for _ in 0..4 {
+            let random = &mut self.ticks.random;
+            let target = pos.offset(
+                random.below(3) as i32 - 1,
+                random.below(5) as i32 - 3,
+                random.below(3) as i32 - 1,
+            );
+            if self.get_block_type(target) == Some(block::DIRT) && !self.is_solid(target.up()) {
+                self.set_block_from_tick(target, block::GRASS, dirty_chunks);
+            }
+        }
+    }
+
+    // Leaves with no log within 4 blocks decay
+    fn leaves_tick(&mut self, pos: BlockPos, dirty_chunks: &mut Vec<ChunkPos>) {
+        const RANGE: i32 = 4;
+        for x in -RANGE..=RANGE {
+            for y in -RANGE..=RANGE {
+                for z in -RANGE..=RANGE {
+                    if self.get_block_type(pos.offset(x, y, z)) == Some(block::LOG) {
+                        return;
+                    }
+                }
+            }
+        }
+        self.set_block_from_tick(pos, block::AIR, dirty_chunks);
+    }
+}
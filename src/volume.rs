@@ -0,0 +1,94 @@
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::{bail, Context, Result};
+
+use crate::block::{self, BlockType};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{block_mapping::BlockMapping, schem, vox::{self, ColorMapping}};
+
+// A box of blocks detached from the world, used for structure files and copy/paste.
+// Stored in y, z, x order like Sponge schematics.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockVolume {
+    pub size: [usize; 3],
+    pub blocks: Vec<BlockType>,
+}
+
+impl BlockVolume {
+    pub fn new(size: [usize; 3]) -> Self {
+        Self {
+            size,
+            blocks: vec![block::AIR; size[0] * size[1] * size[2]],
+        }
+    }
+
+    pub fn index(&self, pos: [usize; 3]) -> usize {
+        (pos[1] * self.size[2] + pos[2]) * self.size[0] + pos[0]
+    }
+
+    pub fn set(&mut self, pos: [usize; 3], block_type: BlockType) {
+        if pos[0] >= self.size[0] || pos[1] >= self.size[1] || pos[2] >= self.size[2] {
+            return;
+        }
+        let index = self.index(pos);
+        self.blocks[index] = block_type;
+    }
+
+    // Every position in the volume with its block, in storage order
+    pub fn iter(&self) -> impl Iterator<Item = ([usize; 3], BlockType)> + '_ {
+        let [size_x, _, size_z] = self.size;
+        self.blocks.iter().enumerate().map(move |(i, &block_type)| {
+            ([i % size_x, i / (size_x * size_z), (i / size_x) % size_z], block_type)
+        })
+    }
+
+    // Quarter turns clockwise around the y axis, seen from above
+    pub fn rotated_y(&self, quarter_turns: u32) -> BlockVolume {
+        let [size_x, size_y, size_z] = self.size;
+        let mut rotated = match quarter_turns % 4 {
+            1 | 3 => BlockVolume::new([size_z, size_y, size_x]),
+            _ => BlockVolume::new(self.size),
+        };
+        for (pos, block_type) in self.iter() {
+            let [x, y, z] = pos;
+            let new_pos = match quarter_turns % 4 {
+                1 => [size_z - 1 - z, y, x],
+                2 => [size_x - 1 - x, y, size_z - 1 - z],
+                3 => [z, y, size_x - 1 - x],
+                _ => pos,
+            };
+            rotated.set(new_pos, block_type);
+        }
+        rotated
+    }
+
+    // Flips the volume along an axis, 0 = x, 1 = y, 2 = z
+    pub fn mirrored(&self, axis: usize) -> BlockVolume {
+        let mut mirrored = BlockVolume::new(self.size);
+        for (mut pos, block_type) in self.iter() {
+            pos[axis] = self.size[axis] - 1 - pos[axis];
+            mirrored.set(pos, block_type);
+        }
+        mirrored
+    }
+}
+
+// Structure files are picked by extension, .vox or .schem
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(path: &std::path::Path, block_mapping: &BlockMapping, color_mapping: &ColorMapping) -> Result<BlockVolume> {
+    let bytes = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("vox") => vox::read(&bytes, color_mapping),
+        Some("schem") => schem::read(&bytes, block_mapping),
+        _ => bail!("unknown structure format for {}, expected .vox or .schem", path.display()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(path: &std::path::Path, volume: &BlockVolume, block_mapping: &BlockMapping, color_mapping: &ColorMapping) -> Result<()> {
+    let bytes = match path.extension().and_then(|e| e.to_str()) {
+        Some("vox") => vox::write(volume, color_mapping)?,
+        Some("schem") => schem::write(volume, block_mapping)?,
+        _ => bail!("unknown structure format for {}, expected .vox or .schem", path.display()),
+    };
+    std::fs::write(path, bytes).with_context(|| format!("failed to write {}", path.display()))
+}
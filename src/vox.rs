@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::block::{self, BlockType};
+use crate::volume::BlockVolume;
+
+// MagicaVoxel .vox models. Only the first model of a file is read, the scene graph
+// (nTRN/nGRP/nSHP chunks) is ignored. MagicaVoxel is z-up so y and z are swapped on
+// the way in and out.
+
+const MAX_VOX_SIZE: usize = 256;
+
+// Voxel colours are turned into blocks by picking the closest colour in this table
+#[derive(Clone, Debug)]
+pub struct ColorMapping {
+    pub colors: Vec<([u8; 3], BlockType)>,
+}
+
+impl Default for ColorMapping {
+    fn default() -> Self {
+        use block::*;
+
+        // Average colour of each block's texture in the atlas
+        let table: &[(u32, BlockType)] = &[
+            (0x75b049, GRASS), (0x7d7d7d, STONE), (0x856042, DIRT), (0x9c7f4e, PLANKS),
+            (0x926356, BRICKS), (0xa95c47, TNT), (0x7a7a7a, COBBLESTONE), (0x535353, BEDROCK),
+            (0xdbd3a0, SAND), (0x7e7c7a, GRAVEL), (0x665131, LOG), (0xdbdbdb, IRON_BLOCK),
+            (0xf9ec4e, GOLD_BLOCK), (0x61dbd5, DIAMOND_BLOCK), (0x8f8b7c, GOLD_ORE),
+            (0x87827e, IRON_ORE), (0x737373, COAL_ORE), (0x6b5839, BOOKSHELF),
+            (0x677967, MOSSY_COBBLESTONE), (0x14121d, OBSIDIAN), (0xb6b639, SPONGE),
+            (0x818c8f, DIAMOND_ORE), (0x846b6b, REDSTONE_ORE), (0x247318, LEAVES),
+            (0xdddddd, WOOL), (0xeffbfb, SNOW), (0x7dadff, ICE), (0x0c5c16, CACTUS),
+            (0x9ea4b0, CLAY), (0x6f3634, NETHERRACK), (0x544033, SOUL_SAND),
+            (0x8f7645, GLOWSTONE), (0x1d47a5, LAPIS_BLOCK), (0x667086, LAPIS_ORE),
+            (0xd8d19d, SANDSTONE), (0x295dff, WATER), (0xf54100, LAVA),
+        ];
+
+        Self {
+            colors: table.iter().map(|&(rgb, id)| (rgb_from_u32(rgb), id)).collect(),
+        }
+    }
+}
+
+impl ColorMapping {
+    // Same format as the block mapping, entries are added on top of the default table :
+    //   ff0000 = 8
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut mapping = Self::default();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (color, id) = line.split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected `rrggbb = block id`", line_number + 1))?;
+            let color = u32::from_str_radix(color.trim(), 16)
+                .with_context(|| format!("line {}: invalid colour", line_number + 1))?;
+            let id: BlockType = id.trim().parse()
+                .with_context(|| format!("line {}: invalid block id", line_number + 1))?;
+            // In front so an exact match wins over the default entry for the same id
+            mapping.colors.insert(0, (rgb_from_u32(color), id));
+        }
+        Ok(mapping)
+    }
+
+    pub fn nearest(&self, rgb: [u8; 3]) -> BlockType {
+        let distance = |other: [u8; 3]| -> i32 {
+            (0..3).map(|i| (rgb[i] as i32 - other[i] as i32).pow(2)).sum()
+        };
+        self.colors.iter()
+            .min_by_key(|(color, _)| distance(*color))
+            .map(|&(_, id)| id)
+            .unwrap_or(block::UNKNOWN)
+    }
+
+    pub fn color_of(&self, id: BlockType) -> [u8; 3] {
+        let id = block::id_of(id);
+        self.colors.iter()
+            .find(|&&(_, block_type)| block_type == id)
+            .map(|&(color, _)| color)
+            .unwrap_or([0x80, 0x80, 0x80])
+    }
+}
+
+fn rgb_from_u32(rgb: u32) -> [u8; 3] {
+    [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]
+}
+
+pub fn read(bytes: &[u8], mapping: &ColorMapping) -> Result<BlockVolume> {
+    let mut reader = ChunkReader { bytes, pos: 0 };
+    if reader.take(4)? != b"VOX " {
+        bail!("not a MagicaVoxel file");
+    }
+    let _version = reader.u32()?;
+
+    let mut size = None;
+    let mut voxels: Option<&[u8]> = None;
+    let mut palette = default_palette();
+
+    while reader.pos < bytes.len() {
+        let id = reader.take(4)?;
+        let content_size = reader.u32()? as usize;
+        let _children_size = reader.u32()?;
+        // MAIN's content is empty and its children follow directly, so they're read by this same loop
+        let content = reader.take(content_size)?;
+
+        match id {
+            b"SIZE" if size.is_none() => {
+                let mut content = ChunkReader { bytes: content, pos: 0 };
+                size = Some([content.u32()? as usize, content.u32()? as usize, content.u32()? as usize]);
+            }
+            b"XYZI" if voxels.is_none() => voxels = Some(content),
+            b"RGBA" => {
+                for i in 0..255 {
+                    let rgba = content.get(i * 4..i * 4 + 4).ok_or_else(|| anyhow!("RGBA chunk is too short"))?;
+                    palette[i + 1] = [rgba[0], rgba[1], rgba[2]];
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let [size_x, size_y, size_z] = size.ok_or_else(|| anyhow!("file has no model"))?;
+    // Voxel coordinates are single bytes, anything bigger is a broken file
+    if [size_x, size_y, size_z].iter().any(|&s| s > MAX_VOX_SIZE) {
+        bail!("model is {}x{}x{}, MagicaVoxel models are at most {} blocks on a side", size_x, size_y, size_z, MAX_VOX_SIZE);
+    }
+    let voxels = voxels.ok_or_else(|| anyhow!("file has no voxel data"))?;
+    let count = u32::from_le_bytes(voxels.get(0..4).ok_or_else(|| anyhow!("XYZI chunk is empty"))?.try_into().unwrap()) as usize;
+
+    let mut block_types = HashMap::new();
+    let mut volume = BlockVolume::new([size_x, size_z, size_y]);
+    for voxel in voxels[4..].chunks_exact(4).take(count) {
+        let [x, y, z, color_index] = [0, 1, 2, 3].map(|i| voxel[i] as usize);
+        if y >= size_y {
+            continue;
+        }
+        let block_type = *block_types.entry(color_index)
+            .or_insert_with(|| mapping.nearest(palette[color_index]));
+        volume.set([x, z, size_y - 1 - y], block_type);
+    }
+
+    Ok(volume)
+}
+
+pub fn write(volume: &BlockVolume, mapping: &ColorMapping) -> Result<Vec<u8>> {
+    let [size_x, size_y, size_z] = volume.size;
+    if volume.size.iter().any(|&s| s > MAX_VOX_SIZE) {
+        bail!("MagicaVoxel models can't be larger than {} blocks on a side", MAX_VOX_SIZE);
+    }
+
+    // One palette slot per block type, slot 0 is reserved for empty voxels
+    let mut color_indices: HashMap<BlockType, u8> = HashMap::new();
+    let mut palette = [[0u8; 3]; 256];
+    let mut voxels = Vec::new();
+    for (pos, block_type) in volume.iter() {
+        if block_type == block::AIR {
+            continue;
+        }
+        let next_index = color_indices.len() + 1;
+        let color_index = match color_indices.get(&block_type) {
+            Some(&index) => index,
+            None if next_index < 256 => {
+                palette[next_index] = mapping.color_of(block_type);
+                color_indices.insert(block_type, next_index as u8);
+                next_index as u8
+            }
+            None => bail!("volume has more than 255 different blocks"),
+        };
+        voxels.extend_from_slice(&[pos[0] as u8, (size_z - 1 - pos[2]) as u8, pos[1] as u8, color_index]);
+    }
+
+    let mut children = Vec::new();
+    write_chunk(&mut children, b"SIZE", &[size_x as u32, size_z as u32, size_y as u32].map(u32::to_le_bytes).concat());
+    let mut xyzi = ((voxels.len() / 4) as u32).to_le_bytes().to_vec();
+    xyzi.extend_from_slice(&voxels);
+    write_chunk(&mut children, b"XYZI", &xyzi);
+    let rgba: Vec<u8> = (1..=256).flat_map(|i| {
+        let [r, g, b] = palette[i % 256];
+        [r, g, b, 0xff]
+    }).collect();
+    write_chunk(&mut children, b"RGBA", &rgba);
+
+    let mut out = b"VOX ".to_vec();
+    out.extend_from_slice(&150u32.to_le_bytes());
+    out.extend_from_slice(b"MAIN");
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    out.extend_from_slice(&children);
+    Ok(out)
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(content);
+}
+
+// Palette MagicaVoxel uses when a file has no RGBA chunk : a 6x6x6 colour cube
+// followed by red, green, blue and grey ramps
+fn default_palette() -> [[u8; 3]; 256] {
+    const CUBE: [u8; 6] = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+    let mut palette = [[0u8; 3]; 256];
+    let mut i = 1;
+    for r in CUBE {
+        for g in CUBE {
+            for b in CUBE {
+                if [r, g, b] != [0, 0, 0] {
+                    palette[i] = [r, g, b];
+                    i += 1;
+                }
+            }
+        }
+    }
+    for channel in 0..3 {
+        for v in RAMP {
+            let mut color = [0; 3];
+            color[channel] = v;
+            palette[i] = color;
+            i += 1;
+        }
+    }
+    for v in RAMP {
+        palette[i] = [v, v, v];
+        i += 1;
+    }
+    palette
+}
+
+struct ChunkReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ChunkReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| anyhow!("unexpected end of file at byte {}", self.pos))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    biome::{position_hash, BiomeProperties},
+    block::{self, BlockType},
+    chunk::{WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    pos::ChunkPos,
+    tick::Random,
+};
+
+// The sea and lakes of the default generator. Columns under sea level are filled with water
+// up to it, with gravel on the sea floor and sand beaches where the terrain comes up out of
+// the water. Lakes are bowls sunk into flat ground above sea level, each inside one chunk so
+// its water level doesn't depend on the chunks around it.
+
+pub const DEFAULT_SEA_LEVEL: i32 = 63;
+
+// Of a chunk having a lake
+const LAKE_CHANCE: f64 = 0.04;
+// The most the ground around a lake can rise over its water
+const LAKE_MAX_RELIEF: i32 = 3;
+
+// The surface and filler of a column whose top block is at `surface`
+pub fn shore_blocks(biome: &BiomeProperties, surface: i32, sea_level: i32) -> (BlockType, BlockType) {
+    if surface < sea_level - 2 {
+        (block::GRAVEL, biome.filler)
+    } else if surface <= sea_level + 1 {
+        (block::SAND, block::SAND)
+    } else {
+        (biome.surface, biome.filler)
+    }
+}
+
+// Sinks a lake into the chunk if it gets one, `surfaces` are lowered to the bed of the lake
+pub fn fill_lake(
+    seed: u32,
+    pos: ChunkPos,
+    height: WorldHeight,
+    sea_level: i32,
+    surfaces: &mut [[i32; CHUNK_Z_SIZE]; CHUNK_X_SIZE],
+    blocks: &mut [Vec<Vec<BlockType>>],
+) {
+    let mut random = Random::new(position_hash(seed.wrapping_add(800), pos.x, pos.z));
+    if random.next_f64() >= LAKE_CHANCE {
+        return;
+    }
+    let radius = [3.0 + random.next_f64() * 3.0, 3.0 + random.next_f64() * 3.0];
+    // Far enough from the chunk border for the banks to fit
+    let center = radius.map(|r| 8.0 + (random.next_f64() - 0.5) * 2.0 * (6.5 - r));
+    let depth = 3.0 + random.next_f64() * 2.0;
+    let distance = |x: usize, z: usize| {
+        let dx = (x as f64 + 0.5 - center[0]) / radius[0];
+        let dz = (z as f64 + 0.5 - center[1]) / radius[1];
+        dx * dx + dz * dz
+    };
+
+    // The lake and the columns around it, the water fills up to the lowest of them
+    let banks: Vec<i32> = (0..CHUNK_X_SIZE)
+        .flat_map(|x| (0..CHUNK_Z_SIZE).map(move |z| (x, z)))
+        .filter(|&(x, z)| {
+            let near = |v: usize, size: usize| v.saturating_sub(1)..(v + 2).min(size);
+            near(x, CHUNK_X_SIZE).any(|nx| near(z, CHUNK_Z_SIZE).any(|nz| distance(nx, nz) <= 1.0))
+        })
+        .map(|(x, z)| surfaces[x][z])
+        .collect();
+    let (Some(&level), Some(&highest)) = (banks.iter().min(), banks.iter().max()) else {
+        return;
+    };
+    if level <= sea_level + 1 || highest - level > LAKE_MAX_RELIEF {
+        return;
+    }
+
+    for (x, plane) in blocks.iter_mut().enumerate() {
+        for z in 0..CHUNK_Z_SIZE {
+            let d = distance(x, z);
+            if d > 1.0 {
+                continue;
+            }
+            let bed = level - ((1.0 - d) * depth).ceil().max(1.0) as i32;
+            for (row, y) in plane.iter_mut().zip(height.ys()) {
+                if y == bed {
+                    row[z] = block::SAND;
+                } else if y > bed && y <= level {
+                    row[z] = block::WATER;
+                } else if y > level && y <= surfaces[x][z] {
+                    row[z] = block::AIR;
+                }
+            }
+            surfaces[x][z] = bed;
+        }
+    }
+}
@@ -1,320 +1,419 @@
-use wgpu::util::DeviceExt;
-
-use noise::{OpenSimplex};
+use std::collections::HashMap;
 
 use crate::{
-    block::BlockVertex, 
-    chunk::{Chunk, CHUNK_X_SIZE, CHUNK_Y_SIZE, CHUNK_Z_SIZE}, 
-    texture_atlas::TextureAtlas
+    anvil::{self, ImportedChunk},
+    biome::Biome,
+    block::{self, BlockType},
+    block_mapping::BlockMapping,
+    chunk::{Chunk, WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    entity::Entity,
+    events::{BlockChangeCause, WorldEvent, WorldEvents},
+    feature,
+    generator::{GeneratorSettings, TerrainGenerator},
+    heightmap::Heightmap,
+    history::History,
+    pos::{BlockPos, BlockRegion, ChunkPos, Direction},
+    tick::TickScheduler,
+    volume::BlockVolume,
 };
 
-#[derive(Clone, Debug)]
-pub struct ChunkBuffer {
-    pub vertex_buffer: wgpu::Buffer,
-    pub indices_buffer: wgpu::Buffer,
-    pub num_elements: u32,
-}
+// The world only holds blocks and what acts on them, nothing here needs a GPU so it can
+// run headless in tests or on a server. Chunk meshes are built on the CPU, the renderer
+// picks up the chunks listed in `remeshed` and uploads them. Trees and other features
+// reaching into chunks that aren't generated yet wait in `pending_features` until they are.
 
-impl ChunkBuffer {
-    pub fn new(device: &wgpu::Device, vertices: Vec<BlockVertex>, indices: Vec<u32>, num_elements: u32) -> Self {
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("chunkbuffer vertex buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
+// In chunks around the origin
+pub const DEFAULT_WORLD_RADIUS: i32 = 5;
+// How far from the origin a spawn point is looked for, in blocks
+const SPAWN_SEARCH_RADIUS: i32 = 64;
 
-        let indices_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("chunkbuffer indices buffer"),
-            contents: bytemuck::cast_slice(&indices),
-            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-        });
+// What a new world is created with
+#[derive(Clone, Debug)]
+pub struct WorldSettings {
+    pub seed: i64,
+    // Chunks from -radius to radius - 1 are generated on both axes
+    pub radius: i32,
+    pub height: WorldHeight,
+    pub generator: GeneratorSettings,
+}
 
+impl Default for WorldSettings {
+    fn default() -> Self {
         Self {
-            vertex_buffer: vertex_buffer,
-            indices_buffer: indices_buffer,
-            num_elements: num_elements,
+            seed: 0,
+            radius: DEFAULT_WORLD_RADIUS,
+            height: WorldHeight::default(),
+            generator: GeneratorSettings::default(),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct World {
     pub chunks: Vec<Chunk>,
-    pub chunk_buffers: Vec<ChunkBuffer>,
 
-    pub noise_gen: OpenSimplex,
+    pub seed: i64,
+    pub generator_settings: GeneratorSettings,
+    pub generator: Box<dyn TerrainGenerator>,
+    pub height: WorldHeight,
+
+    pub history: History,
+    pub events: WorldEvents,
+    pub ticks: TickScheduler,
+    pub entities: Vec<Entity>,
+    // Feature blocks from generated chunks for the chunks next to them that aren't generated yet
+    pub pending_features: HashMap<ChunkPos, Vec<(BlockPos, BlockType)>>,
+    // Where players appear, the block their feet are in
+    pub spawn: BlockPos,
 
-    pub texture_atlas: TextureAtlas,
+    // Chunks whose mesh changed since the renderer last looked
+    remeshed: Vec<ChunkPos>,
 }
 
 impl World {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, seed: u32) -> Self {
-        let noise_gen = OpenSimplex::new(seed);
+    pub fn new(seed: i64) -> Self {
+        Self::with_settings(WorldSettings { seed, ..Default::default() })
+    }
+
+    pub fn with_settings(settings: WorldSettings) -> Self {
+        let WorldSettings { seed, radius, height, generator: generator_settings } = settings;
+        let generator = generator_settings.build(seed);
+
+        let mut world = Self {
+            chunks: Vec::new(),
 
-        let mut chunks = Vec::new();
-        let mut chunk_buffers = Vec::new();
+            seed,
+            generator_settings,
+            generator,
+            height,
 
-        const WORLD_SIZE: i32 = 5;
+            history: History::default(),
+            events: WorldEvents::default(),
+            ticks: TickScheduler::new(seed as u64),
+            entities: Vec::new(),
+            pending_features: HashMap::new(),
+            spawn: BlockPos::new(0, 0, 0),
 
-        for x in -WORLD_SIZE..WORLD_SIZE {
-            for y in -WORLD_SIZE..WORLD_SIZE {
-                let base_chunk = Chunk::new([x, y], noise_gen);
-                chunks.push(base_chunk);
+            remeshed: Vec::new(),
+        };
+        for x in -radius..radius {
+            for z in -radius..radius {
+                world.add_generated_chunk(ChunkPos::new(x, z));
             }
         }
-
-        for i in 0..chunks.len() {
-            let pos = chunks[i].pos;
-            
-            let left_idx = chunks.iter().position(|c| c.pos == [pos[0] - 1, pos[1]]);
-            let right_idx = chunks.iter().position(|c| c.pos == [pos[0] + 1, pos[1]]);
-            let back_idx = chunks.iter().position(|c| c.pos == [pos[0], pos[1] - 1]);
-            let front_idx = chunks.iter().position(|c| c.pos == [pos[0], pos[1] + 1]);
-            
-            let left_blocks = left_idx.map(|idx| Self::get_boundary_blocks(&chunks[idx], 3)); // right face of left chunk
-            let right_blocks = right_idx.map(|idx| Self::get_boundary_blocks(&chunks[idx], 2)); // left face of right chunk
-            let back_blocks = back_idx.map(|idx| Self::get_boundary_blocks(&chunks[idx], 1)); // front face of back chunk
-            let front_blocks = front_idx.map(|idx| Self::get_boundary_blocks(&chunks[idx], 0)); // back face of front chunk
-            
-            Self::update_chunk_faces_with_neighbor_blocks(
-                &mut chunks[i],
-                left_blocks,
-                right_blocks,
-                back_blocks,
-                front_blocks,
-            );
-
-            chunks[i].regenerate_mesh();
-            let mesh = chunks[i].mesh.clone();
-            let chunk_buffer = ChunkBuffer::new(device, mesh.vertices, mesh.indices, mesh.num_elements);
-            chunk_buffers.push(chunk_buffer);
+        for i in 0..world.chunks.len() {
+            world.update_chunk_mesh(i);
         }
+        world.spawn = world.find_spawn();
+        world
+    }
 
-        Self {
-            chunks: chunks,
-            chunk_buffers: chunk_buffers,
+    // The column closest to the origin with dry solid ground and room for a player above it.
+    // Leaves don't count, so players don't spawn on top of a tree. Falls back to the top of
+    // the origin column when the loaded chunks have nowhere better.
+    pub fn find_spawn(&self) -> BlockPos {
+        let safe = |x: i32, z: i32| {
+            let ground = self.height_at(x, z)?;
+            let dry = self.heightmap_at(Heightmap::MotionBlocking, x, z) == Some(ground);
+            let block_type = self.get_block_type(BlockPos::new(x, ground, z))?;
+            (dry && block::id_of(block_type) != block::LEAVES && ground + 2 < self.height.max_y())
+                .then(|| BlockPos::new(x, ground + 1, z))
+        };
+        // Rings around the origin, nearest first
+        let mut rings = (0..=SPAWN_SEARCH_RADIUS).flat_map(|r| {
+            (-r..=r).flat_map(move |x| (-r..=r).map(move |z| (x, z))).filter(move |&(x, z)| x.abs().max(z.abs()) == r)
+        });
+        rings.find_map(|(x, z)| safe(x, z)).unwrap_or_else(|| {
+            let top = self.heightmap_at(Heightmap::MotionBlocking, 0, 0).map_or(self.height.min_y, |y| y + 1);
+            BlockPos::new(0, top.min(self.height.max_y() - 2), 0)
+        })
+    }
 
-            noise_gen: noise_gen,
+    // Generates a chunk that isn't there yet, then remeshes it and the chunks its features reached
+    pub fn generate_chunk(&mut self, pos: ChunkPos) -> bool {
+        if self.find_chunk(pos).is_some() {
+            return false;
+        }
+        let mut to_update = self.add_generated_chunk(pos);
+        for neighbor in Direction::HORIZONTAL.map(|direction| pos.neighbor(direction)) {
+            if !to_update.contains(&neighbor) {
+                to_update.push(neighbor);
+            }
+        }
+        self.remesh_chunks(&to_update);
+        true
+    }
 
-            texture_atlas: TextureAtlas::new(device, queue),
+    pub fn break_block(&mut self, pos: BlockPos) {
+        if self.get_block_type(pos).is_some_and(|b| b != block::AIR) {
+            self.set_blocks([(pos, block::AIR)], BlockChangeCause::Player);
         }
     }
 
-    pub fn break_block(&mut self, device: &wgpu::Device, pos: [i32; 3]) {
-        if let Some((chunk_index, _)) = self.chunks.iter_mut().enumerate().find(|(_, c)| c.contains_block(pos)) {
-            let chunk_pos = self.chunks[chunk_index].pos;
-            let local_pos = self.chunks[chunk_index].get_local_pos(pos);
-            
-            self.chunks[chunk_index].break_block(pos);
-            self.update_chunk_mesh(device, chunk_index);
-
-            if local_pos[0] == 0 {
-                if let Some(idx) = self.find_chunk([chunk_pos[0] - 1, chunk_pos[1]]) {
-                    self.update_chunk_mesh(device, idx);
-                }
+    pub fn place_block(&mut self, pos: BlockPos, selected_block: u32) {
+        if self.get_block_type(pos) == Some(block::AIR) {
+            self.set_blocks([(pos, selected_block)], BlockChangeCause::Player);
+        }
+    }
+
+    // Sets every position given and remeshes the touched chunks, returns how many blocks changed.
+    // All the changes of one call are a single entry in the undo history.
+    pub fn set_blocks(&mut self, changes: impl IntoIterator<Item = (BlockPos, u32)>, cause: BlockChangeCause) -> usize {
+        let mut dirty_chunks = Vec::new();
+        let mut changed = 0;
+        self.history.begin_action();
+        for (pos, block_type) in changes {
+            if self.get_block_type(pos).is_some_and(|old| old != block_type) {
+                self.set_block_type(pos, block_type, cause, &mut dirty_chunks);
+                changed += 1;
             }
-            if local_pos[0] == 15 {
-                if let Some(idx) = self.find_chunk([chunk_pos[0] + 1, chunk_pos[1]]) {
-                    self.update_chunk_mesh(device, idx);
+        }
+        self.history.end_action();
+        self.remesh_chunks(&dirty_chunks);
+        changed
+    }
+
+    // Returns false when there was nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let Some(set) = self.history.start_undo() else {
+            return false;
+        };
+        let mut dirty_chunks = Vec::new();
+        for (pos, block_type) in set.reverted() {
+            self.set_block_type(pos, block_type, BlockChangeCause::Undo, &mut dirty_chunks);
+        }
+        self.history.finish_undo(set);
+        self.remesh_chunks(&dirty_chunks);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(set) = self.history.start_redo() else {
+            return false;
+        };
+        let mut dirty_chunks = Vec::new();
+        for (pos, block_type) in set.applied() {
+            self.set_block_type(pos, block_type, BlockChangeCause::Redo, &mut dirty_chunks);
+        }
+        self.history.finish_redo(set);
+        self.remesh_chunks(&dirty_chunks);
+        true
+    }
+    
+    // Replaces the chunks at the imported positions (or adds them), then rebuilds
+    // the faces of every imported chunk and of the chunks bordering them.
+    // The undo history is dropped since it may point at blocks that were replaced.
+    pub fn import_chunks(&mut self, imported: Vec<ImportedChunk>) {
+        let mut to_update = Vec::new();
+        self.history.clear();
+
+        for imported_chunk in imported {
+            let biomes = Chunk::generate_biomes(imported_chunk.pos, self.generator.as_ref());
+            let chunk = Chunk::from_block_types(imported_chunk.pos, self.height, imported_chunk.block_types, biomes);
+            self.pending_features.remove(&imported_chunk.pos);
+
+            match self.find_chunk(imported_chunk.pos) {
+                Some(idx) => {
+                    self.chunks[idx] = chunk;
+                    self.events.push(WorldEvent::ChunkUnloaded { pos: imported_chunk.pos });
                 }
+                None => self.chunks.push(chunk),
             }
-            if local_pos[2] == 0 {
-                if let Some(idx) = self.find_chunk([chunk_pos[0], chunk_pos[1] - 1]) {
-                    self.update_chunk_mesh(device, idx);
+            self.events.push(WorldEvent::ChunkLoaded { pos: imported_chunk.pos });
+
+            let pos = imported_chunk.pos;
+            for pos in std::iter::once(pos).chain(Direction::HORIZONTAL.map(|direction| pos.neighbor(direction))) {
+                if !to_update.contains(&pos) {
+                    to_update.push(pos);
                 }
             }
-            if local_pos[2] == 15 {
-                if let Some(idx) = self.find_chunk([chunk_pos[0], chunk_pos[1] + 1]) {
-                    self.update_chunk_mesh(device, idx);
-                }
+        }
+
+        for pos in to_update {
+            if let Some(idx) = self.find_chunk(pos) {
+                self.update_chunk_mesh(idx);
             }
         }
     }
 
-    pub fn place_block(&mut self, device: &wgpu::Device, pos: [i32; 3], selected_block: u32) {
-        if let Some((chunk_index, _)) = self.chunks.iter_mut().enumerate().find(|(_, c)| c.contains_position(pos)) {
-            let chunk_pos = self.chunks[chunk_index].pos;
-            let local_pos = self.chunks[chunk_index].get_local_pos(pos);
-            
-            self.chunks[chunk_index].place_block(pos, selected_block);
-            self.update_chunk_mesh(device, chunk_index);
-
-            if local_pos[0] == 0 {
-                if let Some(idx) = self.find_chunk([chunk_pos[0] - 1, chunk_pos[1]]) {
-                    self.update_chunk_mesh(device, idx);
-                }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn import_anvil_region(&mut self, path: &std::path::Path, mapping: &BlockMapping) -> anyhow::Result<usize> {
+        use anyhow::Context;
+
+        let bytes = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let imported = anvil::import_region(&bytes, mapping, self.height)?;
+        let count = imported.len();
+        self.import_chunks(imported);
+        Ok(count)
+    }
+
+    pub fn get_block_type(&self, pos: BlockPos) -> Option<u32> {
+        self.chunks[self.find_chunk(pos.chunk())?].get_block(pos.local())
+    }
+
+    // The y is ignored, biomes are the same all the way up a column
+    pub fn biome_at(&self, pos: BlockPos) -> Option<Biome> {
+        Some(self.chunks[self.find_chunk(pos.chunk())?].biome(pos.local()))
+    }
+
+    // The y of the highest solid block of a column, under any water. None when its chunk isn't
+    // loaded or there's nothing solid in it.
+    pub fn height_at(&self, x: i32, z: i32) -> Option<i32> {
+        self.heightmap_at(Heightmap::OceanFloor, x, z)
+    }
+
+    pub fn heightmap_at(&self, heightmap: Heightmap, x: i32, z: i32) -> Option<i32> {
+        let pos = BlockPos::new(x, 0, z);
+        let local = pos.local();
+        self.chunks[self.find_chunk(pos.chunk())?].heightmaps.get(heightmap, local.x as usize, local.z as usize)
+    }
+
+    // Sets a block without remeshing, the chunks that need a new mesh are added to `dirty_chunks`
+    pub fn set_block_type(&mut self, pos: BlockPos, block_type: u32, cause: BlockChangeCause, dirty_chunks: &mut Vec<ChunkPos>) {
+        let chunk_pos = pos.chunk();
+        let Some(idx) = self.find_chunk(chunk_pos) else {
+            return;
+        };
+        let Some(old) = self.get_block_type(pos) else {
+            return;
+        };
+        if !self.chunks[idx].set_block(pos.local(), block_type) {
+            return;
+        }
+        self.history.record(pos, old, block_type);
+        self.ticks.block_changed(pos);
+        self.events.push(WorldEvent::BlockChanged { pos, old, new: block_type, cause });
+
+        // Blocks on the edge of a chunk also change the faces of the chunk next to them
+        let mut mark = |pos: ChunkPos| {
+            if !dirty_chunks.contains(&pos) {
+                dirty_chunks.push(pos);
             }
-            if local_pos[0] == 15 {
-                if let Some(idx) = self.find_chunk([chunk_pos[0] + 1, chunk_pos[1]]) {
-                    self.update_chunk_mesh(device, idx);
-                }
+        };
+        mark(chunk_pos);
+        for direction in Direction::HORIZONTAL {
+            let neighbor = pos.neighbor(direction).chunk();
+            if neighbor != chunk_pos {
+                mark(neighbor);
             }
-            if local_pos[2] == 0 {
-                if let Some(idx) = self.find_chunk([chunk_pos[0], chunk_pos[1] - 1]) {
-                    self.update_chunk_mesh(device, idx);
-                }
+        }
+    }
+
+    pub fn remesh_chunks(&mut self, chunk_positions: &[ChunkPos]) {
+        for &pos in chunk_positions {
+            if let Some(idx) = self.find_chunk(pos) {
+                self.update_chunk_mesh(idx);
             }
-            if local_pos[2] == 15 {
-                if let Some(idx) = self.find_chunk([chunk_pos[0], chunk_pos[1] + 1]) {
-                    self.update_chunk_mesh(device, idx);
-                }
+        }
+    }
+
+    // Drops every chunk, the renderer drops their meshes on its next update
+    pub fn unload_all_chunks(&mut self) {
+        self.history.clear();
+        for chunk in std::mem::take(&mut self.chunks) {
+            self.events.push(WorldEvent::ChunkUnloaded { pos: chunk.pos });
+            if !self.remeshed.contains(&chunk.pos) {
+                self.remeshed.push(chunk.pos);
             }
         }
     }
-    
-    fn find_chunk(&self, pos: [i32; 2]) -> Option<usize> {
-        self.chunks.iter().position(|c| c.pos == pos)
+
+    // Makes the renderer upload every chunk again, when it starts drawing this world
+    pub fn mark_all_chunks_remeshed(&mut self) {
+        self.remeshed = self.chunks.iter().map(|chunk| chunk.pos).collect();
     }
 
-    fn get_boundary_blocks(chunk: &Chunk, face: usize) -> Vec<Vec<u32>> {
-        let mut blocks = vec![vec![0u32; CHUNK_Y_SIZE]; match face {
-            0 | 1 => CHUNK_X_SIZE,
-            _ => CHUNK_Z_SIZE,
-        }];
-        
-        match face {
-            0 => {
-                for x in 0..CHUNK_X_SIZE {
-                    for y in 0..CHUNK_Y_SIZE {
-                        blocks[x][y] = chunk.blocks[x][y][0].mat;
-                    }
-                }
-            }
-            1 => {
-                for x in 0..CHUNK_X_SIZE {
-                    for y in 0..CHUNK_Y_SIZE {
-                        blocks[x][y] = chunk.blocks[x][y][CHUNK_Z_SIZE - 1].mat;
-                    }
-                }
-            }
-            2 => {
-                for z in 0..CHUNK_Z_SIZE {
-                    for y in 0..CHUNK_Y_SIZE {
-                        blocks[z][y] = chunk.blocks[0][y][z].mat;
-                    }
-                }
-            }
-            3 => {
-                for z in 0..CHUNK_Z_SIZE {
-                    for y in 0..CHUNK_Y_SIZE {
-                        blocks[z][y] = chunk.blocks[CHUNK_X_SIZE - 1][y][z].mat;
-                    }
-                }
+    // The chunks remeshed since the last call, whether they still exist or not
+    pub fn take_remeshed_chunks(&mut self) -> Vec<ChunkPos> {
+        std::mem::take(&mut self.remeshed)
+    }
+
+    pub fn subscribe(&mut self, subscriber: impl FnMut(&WorldEvent) + Send + 'static) {
+        self.events.subscribe(subscriber);
+    }
+
+    // Hands the events queued since the last call to the subscribers, called at the end of every tick
+    pub fn dispatch_events(&mut self) {
+        self.events.dispatch();
+    }
+
+    // Blocks in unloaded chunks are exported as air
+    pub fn export_volume(&self, region: BlockRegion) -> BlockVolume {
+        let mut volume = BlockVolume::new(region.size().map(|size| size as usize));
+        for pos in region.iter() {
+            let block_type = self.get_block_type(pos).unwrap_or(0);
+            let local = pos - region.min;
+            volume.set([local.x as usize, local.y as usize, local.z as usize], block_type);
+        }
+        volume
+    }
+
+    // Adds a newly generated chunk without meshing anything, returns the chunks that changed
+    fn add_generated_chunk(&mut self, pos: ChunkPos) -> Vec<ChunkPos> {
+        let chunk = Chunk::new(pos, self.height, self.generator.as_ref());
+        // Decorated before the features of its neighbours are written in, so they don't stop its own
+        let features = self.generator.decorate(pos, self.height, &chunk.blocks);
+        self.chunks.push(chunk);
+        self.events.push(WorldEvent::ChunkGenerated { pos });
+
+        let pending = self.pending_features.remove(&pos).unwrap_or_default();
+        let mut changed = self.place_features(pending);
+        for pos in std::iter::once(pos).chain(self.place_features(features)) {
+            if !changed.contains(&pos) {
+                changed.push(pos);
             }
-            _ => {}
         }
-        
-        blocks
+        changed
     }
-    
-    fn update_chunk_faces_with_neighbor_blocks(
-        chunk: &mut Chunk,
-        left_blocks: Option<Vec<Vec<u32>>>,
-        right_blocks: Option<Vec<Vec<u32>>>,
-        back_blocks: Option<Vec<Vec<u32>>>,
-        front_blocks: Option<Vec<Vec<u32>>>,
-    ) {
-        use crate::chunk::{CHUNK_X_SIZE, CHUNK_Y_SIZE, CHUNK_Z_SIZE};
-        use crate::block::Block;
-        
-        for x in 0..CHUNK_X_SIZE {
-            for y in 0..CHUNK_Y_SIZE {
-                for z in 0..CHUNK_Z_SIZE {
-                    let block_type = chunk.blocks[x][y][z].mat;
-                    if block_type == 0 {
-                        continue;
-                    }
-                    
-                    let mut close_blocks = [false; 6];
-                    
-                    // BACK (-z)
-                    close_blocks[0] = if z == 0 {
-                        back_blocks.as_ref()
-                            .and_then(|blocks| blocks.get(x).and_then(|col| col.get(y)))
-                            .map(|&b| b != 0)
-                            .unwrap_or(false)
-                    } else {
-                        chunk.blocks[x][y][z-1].mat != 0
-                    };
-                    
-                    // FRONT (+z)
-                    close_blocks[1] = if z == CHUNK_Z_SIZE - 1 {
-                        front_blocks.as_ref()
-                            .and_then(|blocks| blocks.get(x).and_then(|col| col.get(y)))
-                            .map(|&b| b != 0)
-                            .unwrap_or(false)
-                    } else {
-                        chunk.blocks[x][y][z+1].mat != 0
-                    };
-                    
-                    // LEFT (-x)
-                    close_blocks[2] = if x == 0 {
-                        left_blocks.as_ref()
-                            .and_then(|blocks| blocks.get(z).and_then(|col| col.get(y)))
-                            .map(|&b| b != 0)
-                            .unwrap_or(false)
-                    } else {
-                        chunk.blocks[x-1][y][z].mat != 0
-                    };
-                    
-                    // RIGHT (+x)
-                    close_blocks[3] = if x == CHUNK_X_SIZE - 1 {
-                        right_blocks.as_ref()
-                            .and_then(|blocks| blocks.get(z).and_then(|col| col.get(y)))
-                            .map(|&b| b != 0)
-                            .unwrap_or(false)
-                    } else {
-                        chunk.blocks[x+1][y][z].mat != 0
-                    };
-                    
-                    // TOP (+y)
-                    close_blocks[4] = if y == CHUNK_Y_SIZE - 1 {
-                        false
-                    } else {
-                        chunk.blocks[x][y+1][z].mat != 0
-                    };
-                    
-                    // BOTTOM (-y)
-                    close_blocks[5] = if y == 0 {
-                        false
-                    } else {
-                        chunk.blocks[x][y-1][z].mat != 0
-                    };
-                    
-                    chunk.blocks[x][y][z] = Block::new(block_type, close_blocks);
+
+    // Writes feature blocks into the chunks that are there and queues the others,
+    // returns the chunks that changed
+    fn place_features(&mut self, features: Vec<(BlockPos, BlockType)>) -> Vec<ChunkPos> {
+        let mut changed = Vec::new();
+        for (pos, block_type) in features {
+            let chunk_pos = pos.chunk();
+            let Some(idx) = self.find_chunk(chunk_pos) else {
+                self.pending_features.entry(chunk_pos).or_default().push((pos, block_type));
+                continue;
+            };
+            let chunk = &mut self.chunks[idx];
+            if chunk.get_block(pos.local()).is_some_and(|old| feature::can_replace(old, block_type)) {
+                chunk.set_block(pos.local(), block_type);
+                if !changed.contains(&chunk_pos) {
+                    changed.push(chunk_pos);
                 }
             }
         }
+        changed
     }
-    
-    fn update_chunk_mesh(&mut self, device: &wgpu::Device, chunk_index: usize) {
+
+    fn find_chunk(&self, pos: ChunkPos) -> Option<usize> {
+        self.chunks.iter().position(|c| c.pos == pos)
+    }
+
+    // The blocks of a chunk on one of its sides, indexed [z][y] for the x sides and [x][y] for the z sides
+    fn get_boundary_blocks(chunk: &Chunk, side: Direction) -> Vec<Vec<BlockType>> {
+        let column = |x: usize, z: usize| (0..chunk.height.height).map(|y| chunk.blocks[x][y][z]).collect();
+        match side {
+            Direction::North => (0..CHUNK_X_SIZE).map(|x| column(x, 0)).collect(),
+            Direction::South => (0..CHUNK_X_SIZE).map(|x| column(x, CHUNK_Z_SIZE - 1)).collect(),
+            Direction::West => (0..CHUNK_Z_SIZE).map(|z| column(0, z)).collect(),
+            Direction::East => (0..CHUNK_Z_SIZE).map(|z| column(CHUNK_X_SIZE - 1, z)).collect(),
+            Direction::Up | Direction::Down => Vec::new(),
+        }
+    }
+
+    fn update_chunk_mesh(&mut self, chunk_index: usize) {
         let pos = self.chunks[chunk_index].pos;
+        // The side of each neighbour that touches this chunk
+        let neighbors = Direction::HORIZONTAL.map(|direction| {
+            self.find_chunk(pos.neighbor(direction))
+                .map(|idx| Self::get_boundary_blocks(&self.chunks[idx], direction.opposite()))
+        });
 
-        let left_idx = self.chunks.iter().position(|c| c.pos == [pos[0] - 1, pos[1]]);
-        let right_idx = self.chunks.iter().position(|c| c.pos == [pos[0] + 1, pos[1]]);
-        let back_idx = self.chunks.iter().position(|c| c.pos == [pos[0], pos[1] - 1]);
-        let front_idx = self.chunks.iter().position(|c| c.pos == [pos[0], pos[1] + 1]);
-
-        let left_blocks = left_idx.map(|idx| Self::get_boundary_blocks(&self.chunks[idx], 3));
-        let right_blocks = right_idx.map(|idx| Self::get_boundary_blocks(&self.chunks[idx], 2));
-        let back_blocks = back_idx.map(|idx| Self::get_boundary_blocks(&self.chunks[idx], 1));
-        let front_blocks = front_idx.map(|idx| Self::get_boundary_blocks(&self.chunks[idx], 0));
-
-        Self::update_chunk_faces_with_neighbor_blocks(
-            &mut self.chunks[chunk_index],
-            left_blocks,
-            right_blocks,
-            back_blocks,
-            front_blocks,
-        );
-
-        self.chunks[chunk_index].regenerate_mesh();
-        let mesh = self.chunks[chunk_index].mesh.clone();
-        self.chunk_buffers[chunk_index] = ChunkBuffer::new(
-            device,
-            mesh.vertices,
-            mesh.indices,
-            mesh.num_elements,
-        );
+        self.chunks[chunk_index].regenerate_mesh(&neighbors);
+        if !self.remeshed.contains(&pos) {
+            self.remeshed.push(pos);
+        }
     }
 }
@@ -0,0 +1,55 @@
+use bassicraft2::{
+    biome::{Biome, Climate, ClimateNoise},
+    block,
+    generator::NoiseGenerator,
+    pos::BlockPos,
+    terrain::TerrainSettings,
+    world::{World, WorldSettings},
+};
+
+#[test]
+fn climates_pick_biomes() {
+    let climate = |temperature, humidity, continentalness| Biome::from_climate(Climate { temperature, humidity, continentalness });
+    assert_eq!(climate(0.0, 0.0, 0.0), Biome::Plains);
+    assert_eq!(climate(0.8, -0.5, 0.0), Biome::Desert);
+    assert_eq!(climate(-0.8, 0.0, 0.0), Biome::SnowyPlains);
+    assert_eq!(climate(0.0, 0.8, 0.0), Biome::Swamp);
+    assert_eq!(climate(0.0, 0.0, 0.8), Biome::Mountains);
+    assert_eq!(climate(0.8, 0.8, -0.8), Biome::Ocean);
+    assert_eq!(Biome::Swamp.tint(block::GRASS), Biome::Swamp.properties().grass_tint);
+    assert_eq!(Biome::Swamp.tint(block::STONE), block::NO_TINT);
+}
+
+#[test]
+fn chunks_know_their_biomes() {
+    let world = World::with_settings(WorldSettings { seed: 1, radius: 1, ..Default::default() });
+    let climate = ClimateNoise::new(1);
+    for pos in [BlockPos::new(-16, 0, -16), BlockPos::new(3, 100, 7), BlockPos::new(15, 0, -1)] {
+        assert_eq!(world.biome_at(pos), Some(climate.biome(pos.x, pos.z)));
+    }
+    assert_eq!(world.biome_at(BlockPos::new(100, 0, 0)), None);
+
+    let chunk = &world.chunks[0];
+    let tinted = chunk.mesh.vertices.iter().any(|v| v.color != block::NO_TINT);
+    assert_eq!(tinted, chunk.biomes.iter().any(|&biome| biome.tint(block::GRASS) != block::NO_TINT));
+}
+
+#[test]
+fn heights_blend_across_biome_borders() {
+    // Without hills and ridges only the blended base heights are left
+    let generator = NoiseGenerator::new(0x1f6c2, TerrainSettings { amplification: 0.0, ..Default::default() });
+    let climate = ClimateNoise::new(0x1f6c2);
+    let mut borders = 0;
+    for z in (-800..800).step_by(100) {
+        let mut previous = generator.surface_height(-800, z);
+        for x in -800..800 {
+            let height = generator.surface_height(x + 1, z);
+            assert!((height - previous).abs() <= 2, "the surface jumps from {} to {} at {}, {}", previous, height, x, z);
+            previous = height;
+            if climate.biome(x, z) != climate.biome(x + 1, z) {
+                borders += 1;
+            }
+        }
+    }
+    assert!(borders > 0);
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use std::collections::HashSet;
+
+use bassicraft2::{
+    block,
+    cave::CaveSettings,
+    chunk::{WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    generator::NoiseGenerator,
+    pos::ChunkPos,
+    terrain::TerrainSettings,
+};
+
+use common::generate;
+
+fn without_caves(seed: u32) -> NoiseGenerator {
+    NoiseGenerator::new(seed, TerrainSettings { caves: CaveSettings { enabled: false, ..Default::default() }, ..Default::default() })
+}
+
+#[test]
+fn caves_are_carved_underground() {
+    let (with, without) = (NoiseGenerator::default_terrain(5), without_caves(5));
+    let height = WorldHeight::default();
+    let mut carved = 0;
+    for x in -4..4 {
+        for z in -4..4 {
+            let pos = ChunkPos::new(x, z);
+            let (caves, solid) = (generate(&with, pos), generate(&without, pos));
+            for (cave_plane, solid_plane) in caves.iter().zip(&solid) {
+                for (i, (cave_row, solid_row)) in cave_plane.iter().zip(solid_plane).enumerate() {
+                    for (&cave, &solid) in cave_row.iter().zip(solid_row) {
+                        if cave != solid {
+                            assert_eq!(cave, block::AIR);
+                            carved += 1;
+                        }
+                        // The bottom of the world stays solid
+                        if i == 0 {
+                            assert_ne!(cave, block::AIR);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    let total = 64 * CHUNK_X_SIZE * CHUNK_Z_SIZE * height.height;
+    assert!(carved > total / 100, "only {carved} blocks carved");
+    assert!(carved < total / 4, "{carved} blocks carved");
+}
+
+#[test]
+fn tunnels_line_up_across_chunk_borders() {
+    // Caverns never go over the threshold, so everything carved is a worm
+    let worms_only = NoiseGenerator::new(11, TerrainSettings {
+        caves: CaveSettings { cheese_threshold: 2.0, ..Default::default() },
+        ..Default::default()
+    });
+    let solid = without_caves(11);
+    // The (y, z) of every block carved in a slice of a chunk at local `x`
+    let carved = |pos: ChunkPos, x: usize| -> HashSet<(usize, usize)> {
+        let (tunnels, ground) = (generate(&worms_only, pos), generate(&solid, pos));
+        let mut carved = HashSet::new();
+        for (i, (tunnel_row, ground_row)) in tunnels[x].iter().zip(&ground[x]).enumerate() {
+            for z in 0..CHUNK_Z_SIZE {
+                if tunnel_row[z] != ground_row[z] {
+                    carved.insert((i, z));
+                }
+            }
+        }
+        carved
+    };
+
+    let (mut shared, mut total) = (0, 0);
+    for x in -3..3 {
+        for z in -3..3 {
+            let west = carved(ChunkPos::new(x, z), CHUNK_X_SIZE - 1);
+            let east = carved(ChunkPos::new(x + 1, z), 0);
+            shared += west.intersection(&east).count();
+            total += west.union(&east).count();
+        }
+    }
+    // Tunnels only narrow a little from one block to the next, so most of a tunnel's cross
+    // section is carved on both sides of the face it goes through
+    assert!(shared > 0, "no tunnel crosses a border");
+    assert!(shared * 2 > total, "only {shared} of {total} carved blocks line up");
+}
+
+#[test]
+fn most_of_the_surface_is_left_alone() {
+    let generator = NoiseGenerator::default_terrain(8);
+    let height = WorldHeight::default();
+    let (mut intact, mut columns) = (0, 0);
+    for x in -2..2 {
+        for z in -2..2 {
+            let pos = ChunkPos::new(x, z);
+            let blocks = generate(&generator, pos);
+            for (lx, plane) in blocks.iter().enumerate() {
+                let surface = |lz: usize| generator.surface_height(pos.x * 16 + lx as i32, pos.z * 16 + lz as i32);
+                intact += (0..CHUNK_Z_SIZE).filter(|&lz| plane[height.index(surface(lz)).unwrap()][lz] != block::AIR).count();
+                columns += CHUNK_Z_SIZE;
+            }
+        }
+    }
+    assert!(intact * 10 > columns * 9, "{intact} of {columns} columns have their surface");
+}
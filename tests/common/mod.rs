@@ -0,0 +1,62 @@
+// Fixtures shared by the integration tests, each test file only uses some of them
+#![allow(dead_code)]
+
+use bassicraft2::{
+    block::{self, BlockType},
+    chunk::{WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    dimension::{Dimensions, Sky, OVERWORLD},
+    generator::{GeneratorSettings, TerrainGenerator},
+    pos::ChunkPos,
+    save::{self, SavedWorld},
+    world::{World, WorldSettings},
+};
+
+// Only chunks -1 and 0 on each axis are generated up front
+pub fn small_settings(seed: i64, generator: GeneratorSettings) -> WorldSettings {
+    WorldSettings { seed, radius: 1, generator, ..Default::default() }
+}
+
+pub fn small_world(seed: i64, generator: GeneratorSettings) -> World {
+    World::with_settings(small_settings(seed, generator))
+}
+
+// Three blocks of dirt and grass over bedrock
+pub fn flat_world() -> World {
+    let generator = GeneratorSettings::parse("flat:bedrock,2*dirt,grass_block").unwrap();
+    small_world(1, generator)
+}
+
+// The y of the grass on top of a flat_world
+pub fn flat_top(world: &World) -> i32 {
+    world.height.min_y + 3
+}
+
+// The blocks of one chunk straight from the generator, without features or a world around it
+pub fn generate(generator: &dyn TerrainGenerator, pos: ChunkPos) -> Vec<Vec<Vec<BlockType>>> {
+    let height = WorldHeight::default();
+    let mut blocks = vec![vec![vec![block::AIR; CHUNK_Z_SIZE]; height.height]; CHUNK_X_SIZE];
+    generator.generate(pos, height, &mut blocks);
+    blocks
+}
+
+pub fn overworld(world: World) -> Dimensions {
+    Dimensions::new(OVERWORLD, world, Sky::default())
+}
+
+// Writes the dimensions to a save and loads it into `into`
+pub fn load_into(saved: &Dimensions, mut into: Dimensions) -> Dimensions {
+    into.restore(save::read(&save::write(saved).unwrap()).unwrap()).unwrap();
+    into
+}
+
+// Saves the world as the only dimension and loads it back into another world
+pub fn save_round_trip_into(world: World, into: World) -> (Dimensions, Dimensions) {
+    let saved = overworld(world);
+    let restored = load_into(&saved, overworld(into));
+    (saved, restored)
+}
+
+// Saves the world as the only dimension and reads it back without loading it
+pub fn saved_world(world: World) -> SavedWorld {
+    save::read(&save::write(&overworld(world)).unwrap()).unwrap().dimensions.remove(0).world
+}
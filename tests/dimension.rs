@@ -0,0 +1,73 @@
+mod common;
+
+use bassicraft2::{
+    block,
+    dimension::{Dimensions, Sky, OVERWORLD},
+    generator::GeneratorSettings,
+    pos::BlockPos,
+    world::{World, WorldSettings},
+};
+
+use common::{load_into, overworld, small_settings, small_world};
+
+const SKY: i32 = 150;
+
+fn two_dimensions() -> Dimensions {
+    let mut dimensions = overworld(small_world(1, GeneratorSettings::Default));
+    dimensions.create("nether", small_settings(2, GeneratorSettings::Default), Sky { color: [0.4, 0.05, 0.0] }).unwrap();
+    dimensions
+}
+
+#[test]
+fn switching_changes_the_active_world() {
+    let mut dimensions = two_dimensions();
+    assert_eq!(dimensions.active().name, OVERWORLD);
+    assert!(dimensions.switch_to("end").is_err());
+    assert!(dimensions.create("nether", small_settings(3, GeneratorSettings::Default), Sky::default()).is_err());
+    assert!(dimensions.create("two words", small_settings(3, GeneratorSettings::Default), Sky::default()).is_err());
+
+    dimensions.switch_to("nether").unwrap();
+    assert_eq!(dimensions.world().seed, 2);
+    dimensions.world_mut().place_block(BlockPos::new(0, SKY, 0), block::PLANKS);
+    assert_eq!(dimensions.get(OVERWORLD).unwrap().world.get_block_type(BlockPos::new(0, SKY, 0)), Some(block::AIR));
+}
+
+#[test]
+fn every_dimension_ticks() {
+    let mut dimensions = two_dimensions();
+    dimensions.get_mut("nether").unwrap().world.place_block(BlockPos::new(0, SKY, 0), block::SAND);
+    dimensions.update_ticks(1.0);
+    assert_eq!(dimensions.get("nether").unwrap().world.get_block_type(BlockPos::new(0, SKY, 0)), Some(block::AIR));
+}
+
+#[test]
+fn saves_hold_every_dimension() {
+    let mut dimensions = two_dimensions();
+    dimensions.switch_to("nether").unwrap();
+    dimensions.world_mut().place_block(BlockPos::new(1, SKY, 1), block::GLASS);
+
+    let restored = load_into(&dimensions, overworld(small_world(5, GeneratorSettings::Default)));
+
+    assert_eq!(restored.iter().count(), 2);
+    assert_eq!(restored.active().name, "nether");
+    assert_eq!(restored.active().sky, Sky { color: [0.4, 0.05, 0.0] });
+    assert_eq!(restored.world().get_block_type(BlockPos::new(1, SKY, 1)), Some(block::GLASS));
+    assert_eq!(restored.world().chunks.len(), 4);
+    assert_eq!(restored.get(OVERWORLD).unwrap().world.seed, 1);
+}
+
+#[test]
+fn loading_drops_dimensions_that_arent_saved() {
+    let mut into = overworld(World::with_settings(WorldSettings { seed: 9, radius: 2, ..Default::default() }));
+    into.create("end", small_settings(10, GeneratorSettings::Default), Sky::default()).unwrap();
+    into.switch_to("end").unwrap();
+    let restored = load_into(&two_dimensions(), into);
+
+    let names: Vec<_> = restored.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, [OVERWORLD, "nether"]);
+    assert_eq!(restored.active().name, OVERWORLD);
+    let overworld = restored.world();
+    assert_eq!(overworld.seed, 1);
+    assert_eq!(overworld.chunks.len(), 4);
+    assert_eq!(overworld.get_block_type(BlockPos::new(-20, 0, -20)), None);
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use bassicraft2::{
+    block,
+    dimension::Dimensions,
+    feature::{self, Tree},
+    generator::GeneratorSettings,
+    pos::{BlockPos, ChunkPos},
+    tick::Random,
+    world::World,
+};
+
+use common::small_world;
+
+#[test]
+fn trees_have_a_trunk_under_their_leaves() {
+    let base = BlockPos::new(0, 70, 0);
+    for tree in [Tree::Oak, Tree::Birch, Tree::Spruce, Tree::Acacia] {
+        let blocks = tree.blocks(base, &mut Random::new(5));
+        assert_eq!(blocks.first(), Some(&(base, block::LOG)));
+        let top_log = blocks.iter().filter(|(_, b)| *b == block::LOG).map(|(pos, _)| pos.y).max().unwrap();
+        assert!(top_log >= base.y + 3, "{:?} is too short", tree);
+        assert!(blocks.iter().any(|&(pos, b)| b == block::LEAVES && pos.y > top_log));
+        // Small enough to only reach the chunks next to the one it grows in
+        assert!(blocks.iter().all(|(pos, _)| (pos.x - base.x).abs() <= 8 && (pos.z - base.z).abs() <= 8));
+    }
+    assert!(feature::can_replace(block::LEAVES, block::LOG));
+    assert!(!feature::can_replace(block::LOG, block::LEAVES));
+    assert!(!feature::can_replace(block::GRASS, block::COBBLESTONE));
+    assert!(!feature::can_replace(block::STONE, block::LOG));
+    // Features of the same rank claiming a block agree on which one gets it
+    for (a, b) in [(block::LOG, block::COBBLESTONE), (block::POPPY, block::TALL_GRASS), (block::LOG, block::LOG)] {
+        assert!(!(feature::can_replace(a, b) && feature::can_replace(b, a)));
+        assert_eq!(feature::can_replace(a, b) || feature::can_replace(b, a), a != b);
+    }
+}
+
+#[test]
+fn features_across_borders_match_whichever_chunk_comes_first() {
+    for seed in [21, 4] {
+        features_match_both_ways(seed);
+    }
+}
+
+fn features_match_both_ways(seed: i64) {
+    // Clear of the chunks the worlds start with
+    let positions: Vec<ChunkPos> = (1..5).flat_map(|x| (1..5).map(move |z| ChunkPos::new(x, z))).collect();
+    let (mut forward, mut backward) = (small_world(seed, GeneratorSettings::Default), small_world(seed, GeneratorSettings::Default));
+    for &pos in &positions {
+        assert!(forward.generate_chunk(pos));
+    }
+    for &pos in positions.iter().rev() {
+        assert!(backward.generate_chunk(pos));
+    }
+    assert!(!forward.generate_chunk(positions[0]));
+
+    let mut leaves = 0;
+    for &pos in &positions {
+        let chunk = |world: &World| world.chunks.iter().find(|chunk| chunk.pos == pos).unwrap().blocks.clone();
+        let blocks = chunk(&forward);
+        assert_eq!(blocks, chunk(&backward), "chunk {:?} differs for seed {}", pos, seed);
+        leaves += blocks.iter().flatten().flatten().filter(|&&b| b == block::LEAVES).count();
+    }
+    assert!(leaves > 0);
+    // What reaches past the generated area waits for its chunk
+    assert!(!forward.pending_features.is_empty());
+    assert!(forward.pending_features.keys().all(|pos| !positions.contains(pos)));
+}
+
+#[test]
+fn pending_features_are_saved() {
+    let mut world = small_world(21, GeneratorSettings::Default);
+    for x in 1..5 {
+        world.generate_chunk(ChunkPos::new(x, 1));
+    }
+    let (saved, restored) = common::save_round_trip_into(world, small_world(0, GeneratorSettings::Default));
+
+    let count = |dimensions: &Dimensions| dimensions.world().pending_features.values().map(Vec::len).sum::<usize>();
+    assert!(count(&saved) > 0);
+    assert_eq!(count(&restored), count(&saved));
+
+    // A chunk generated after loading gets the blocks its neighbours left for it
+    let pos = *saved.world().pending_features.keys().next().unwrap();
+    let (mut a, mut b) = (saved, restored);
+    a.world_mut().generate_chunk(pos);
+    b.world_mut().generate_chunk(pos);
+    let chunk = |dimensions: &Dimensions| dimensions.world().chunks.iter().find(|chunk| chunk.pos == pos).unwrap().blocks.clone();
+    assert_eq!(chunk(&a), chunk(&b));
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use bassicraft2::{
+    block,
+    command::{self, Command},
+    generator::{FlatGenerator, GeneratorSettings},
+    pos::BlockPos,
+    seed,
+    world::World,
+};
+
+use common::small_world;
+
+#[test]
+fn flat_presets_round_trip() {
+    let flat = FlatGenerator::parse("bedrock, 3*minecraft:stone,minecraft:grass_block;minecraft:plains").unwrap();
+    assert_eq!(flat.layers, vec![(1, block::BEDROCK), (3, block::STONE), (1, block::GRASS)]);
+    let settings = GeneratorSettings::Flat(flat);
+    assert_eq!(GeneratorSettings::parse(&settings.to_string()).unwrap(), settings);
+    let glass_and_tnt = GeneratorSettings::Flat(FlatGenerator { layers: vec![(2, block::GLASS), (1, block::TNT)] });
+    assert_eq!(GeneratorSettings::parse(&glass_and_tnt.to_string()).unwrap(), glass_and_tnt);
+
+    assert!(FlatGenerator::parse("minecraft:not_a_block").is_err());
+    assert!(FlatGenerator::parse("").is_err());
+    assert!(GeneratorSettings::parse("caves").is_err());
+}
+
+#[test]
+fn flat_worlds_are_layers_from_the_bottom() {
+    let world = small_world(1, GeneratorSettings::parse("flat:bedrock,2*dirt,grass_block").unwrap());
+    let min_y = world.height.min_y;
+    assert_eq!(world.get_block_type(BlockPos::new(5, min_y, -3)), Some(block::BEDROCK));
+    assert_eq!(world.get_block_type(BlockPos::new(5, min_y + 2, -3)), Some(block::DIRT));
+    assert_eq!(world.get_block_type(BlockPos::new(5, min_y + 3, -3)), Some(block::GRASS));
+    assert_eq!(world.get_block_type(BlockPos::new(5, min_y + 4, -3)), Some(block::AIR));
+}
+
+#[test]
+fn void_worlds_only_have_a_platform() {
+    let world = small_world(1, GeneratorSettings::Void);
+    assert_eq!(world.get_block_type(BlockPos::new(0, 63, 0)), Some(block::STONE));
+    assert_eq!(world.get_block_type(BlockPos::new(3, 63, 0)), Some(block::AIR));
+    assert_eq!(world.get_block_type(BlockPos::new(0, 0, 0)), Some(block::AIR));
+}
+
+#[test]
+fn amplified_terrain_is_taller() {
+    let highest = |world: &World| {
+        (-16..16).flat_map(|x| (-16..16).map(move |z| (x, z)))
+            .filter_map(|(x, z)| world.height.ys().rev().find(|&y| world.get_block_type(BlockPos::new(x, y, z)) != Some(block::AIR)))
+            .max()
+            .unwrap()
+    };
+    assert!(highest(&small_world(1, GeneratorSettings::Amplified)) > highest(&small_world(1, GeneratorSettings::Default)));
+}
+
+#[test]
+fn saves_keep_the_generator() {
+    let generator = GeneratorSettings::parse("flat:bedrock,stone").unwrap();
+    let (_, restored) = common::save_round_trip_into(small_world(1, generator.clone()), small_world(1, GeneratorSettings::Default));
+    assert_eq!(restored.world().generator_settings, generator);
+}
+
+#[test]
+fn create_command_takes_a_seed_and_a_generator() {
+    assert_eq!(command::parse("/dim create flatland 5 flat").unwrap(), Command::CreateDimension {
+        name: "flatland".to_string(),
+        seed: Some(5),
+        generator: GeneratorSettings::Flat(FlatGenerator::default()),
+    });
+    assert_eq!(command::parse("dimension create nothing void").unwrap(), Command::CreateDimension {
+        name: "nothing".to_string(),
+        seed: None,
+        generator: GeneratorSettings::Void,
+    });
+    assert_eq!(command::parse("dim create snow glacier").unwrap(), Command::CreateDimension {
+        name: "snow".to_string(),
+        seed: Some(seed::parse("glacier")),
+        generator: GeneratorSettings::Default,
+    });
+    assert!(command::parse("dimension create hills 5 mountains").is_err());
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use bassicraft2::{
+    block,
+    heightmap::Heightmap,
+    pos::BlockPos,
+    world::{World, WorldSettings},
+};
+
+use common::{flat_top, flat_world};
+
+#[test]
+fn generated_chunks_match_their_blocks() {
+    let world = World::with_settings(WorldSettings { seed: 3, radius: 1, ..Default::default() });
+    for heightmap in Heightmap::ALL {
+        for (x, z) in (-16..16).flat_map(|x| (-16..16).map(move |z| (x, z))) {
+            let top = world.height.ys().rev()
+                .find(|&y| world.get_block_type(BlockPos::new(x, y, z)).is_some_and(|b| heightmap.counts(b)));
+            assert_eq!(world.heightmap_at(heightmap, x, z), top, "{:?} at {}, {}", heightmap, x, z);
+        }
+    }
+    assert_eq!(world.height_at(1000, 0), None);
+}
+
+#[test]
+fn edits_move_the_top() {
+    let mut world = flat_world();
+    let ground = flat_top(&world);
+    assert_eq!(world.height_at(4, 4), Some(ground));
+
+    world.place_block(BlockPos::new(4, ground + 10, 4), block::STONE);
+    assert_eq!(world.height_at(4, 4), Some(ground + 10));
+    world.break_block(BlockPos::new(4, ground + 10, 4));
+    assert_eq!(world.height_at(4, 4), Some(ground));
+    world.break_block(BlockPos::new(4, ground, 4));
+    assert_eq!(world.height_at(4, 4), Some(ground - 1));
+
+    // Plants are on the surface but don't stop anything
+    world.place_block(BlockPos::new(-3, ground + 1, 2), block::POPPY);
+    assert_eq!(world.heightmap_at(Heightmap::WorldSurface, -3, 2), Some(ground + 1));
+    assert_eq!(world.heightmap_at(Heightmap::MotionBlocking, -3, 2), Some(ground));
+}
+
+#[test]
+fn water_is_above_the_ocean_floor() {
+    let mut world = flat_world();
+    let ground = flat_top(&world);
+    world.place_block(BlockPos::new(-7, ground + 1, -7), block::WATER);
+    assert_eq!(world.heightmap_at(Heightmap::MotionBlocking, -7, -7), Some(ground + 1));
+    assert_eq!(world.heightmap_at(Heightmap::WorldSurface, -7, -7), Some(ground + 1));
+    assert_eq!(world.heightmap_at(Heightmap::OceanFloor, -7, -7), Some(ground));
+    assert_eq!(world.height_at(-7, -7), Some(ground));
+}
@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+use bassicraft2::nbt::{self, Tag};
+
+// A root compound holding a single list named "List" with this item id and length, and no items
+fn list_header(item_id: u8, len: i32) -> Vec<u8> {
+    let mut bytes = vec![10, 0, 0, 9, 0, 4];
+    bytes.extend_from_slice(b"List");
+    bytes.push(item_id);
+    bytes.extend_from_slice(&len.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn tags_round_trip() {
+    let root = Tag::Compound(HashMap::from([
+        ("Empty".to_string(), Tag::List(Vec::new())),
+        ("Longs".to_string(), Tag::List(vec![Tag::Long(-3), Tag::Long(i64::MAX)])),
+        ("Name".to_string(), Tag::String("glacier".to_string())),
+    ]));
+    assert_eq!(nbt::read(&nbt::write("Root", &root)).unwrap(), ("Root".to_string(), root));
+}
+
+#[test]
+fn hostile_lists_are_rejected() {
+    // End tags have no payload, so this would otherwise push two billion of them
+    let mut bytes = list_header(0, i32::MAX);
+    bytes.push(0);
+    assert!(nbt::read(&bytes).is_err());
+
+    // More longs than there are bytes left
+    let mut bytes = list_header(4, 1000);
+    bytes.extend_from_slice(&[0; 64]);
+    assert!(nbt::read(&bytes).is_err());
+}
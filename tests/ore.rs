@@ -0,0 +1,64 @@
+use bassicraft2::{
+    block::{self, BlockType},
+    chunk::{WorldHeight, CHUNK_X_SIZE, CHUNK_Z_SIZE},
+    ore::{Distribution, Ores, VeinSettings},
+    pos::ChunkPos,
+    terrain::TerrainSettings,
+};
+
+fn stone(height: WorldHeight) -> Vec<Vec<Vec<BlockType>>> {
+    vec![vec![vec![block::STONE; CHUNK_Z_SIZE]; height.height]; CHUNK_X_SIZE]
+}
+
+#[test]
+fn veins_stay_in_their_height_range() {
+    let height = WorldHeight::default();
+    let ores = Ores::new(4, vec![VeinSettings::new(block::DIAMOND_ORE, 8, 4, [-40, -20], Distribution::Triangle)]);
+    let mut found = 0;
+    for x in 0..8 {
+        let mut blocks = stone(height);
+        ores.place(ChunkPos::new(x, -x), height, &mut blocks);
+        for plane in &blocks {
+            for (i, row) in plane.iter().enumerate() {
+                let y = height.min_y + i as i32;
+                for &block_type in row {
+                    if block_type == block::DIAMOND_ORE {
+                        // Veins wander a few blocks from where they start
+                        assert!((-48..=-12).contains(&y), "ore at y {}", y);
+                        found += 1;
+                    }
+                }
+            }
+        }
+    }
+    assert!(found > 8 * 4, "only {} ore blocks", found);
+}
+
+#[test]
+fn veins_only_replace_stone_and_repeat() {
+    let height = WorldHeight::default();
+    let ores = Ores::new(9, VeinSettings::defaults());
+    let mut blocks = stone(height);
+    for plane in blocks.iter_mut() {
+        for row in plane.iter_mut().skip(100) {
+            row.fill(block::AIR);
+        }
+    }
+    let mut again = blocks.clone();
+    ores.place(ChunkPos::new(2, 7), height, &mut blocks);
+    ores.place(ChunkPos::new(2, 7), height, &mut again);
+    assert_eq!(blocks, again);
+    assert!(blocks.iter().all(|plane| plane[100..].iter().flatten().all(|&b| b == block::AIR)));
+    assert!(blocks.iter().flatten().flatten().any(|&b| b == block::COAL_ORE));
+}
+
+#[test]
+fn veins_are_read_by_block_name() {
+    let json = r#"{ "ores": [{ "block": "minecraft:gold_ore", "size": 5, "count": 3, "min_y": 0, "max_y": 10, "distribution": "uniform" }] }"#;
+    let settings = TerrainSettings::from_json(json).unwrap();
+    assert_eq!(settings.ores, vec![VeinSettings::new(block::GOLD_ORE, 5, 3, [0, 10], Distribution::Uniform)]);
+    assert_eq!(TerrainSettings::from_json(&settings.to_json()).unwrap(), settings);
+
+    let unknown = json.replace("gold_ore", "mithril_ore");
+    assert!(TerrainSettings::from_json(&unknown).is_err());
+}
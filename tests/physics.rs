@@ -0,0 +1,82 @@
+mod common;
+
+use bassicraft2::{
+    block,
+    physics::{Body, MoveInput, TIMESTEP, WIDTH},
+    pos::BlockPos,
+    world::World,
+};
+
+// With the height of the top face of the grass
+fn flat_world() -> (World, f32) {
+    let world = common::flat_world();
+    let ground = (common::flat_top(&world) + 1) as f32;
+    (world, ground)
+}
+
+fn run(body: &mut Body, world: &World, input: MoveInput, seconds: f32) {
+    for _ in 0..(seconds / TIMESTEP) as usize {
+        body.step(world, input);
+    }
+}
+
+#[test]
+fn bodies_fall_onto_the_ground() {
+    let (world, ground) = flat_world();
+    let mut body = Body::new([0.5, ground + 10.0, 0.5]);
+    run(&mut body, &world, MoveInput::default(), 2.0);
+    assert!((body.position[1] - ground).abs() < 1e-3, "stopped at {}", body.position[1]);
+    assert!(body.on_ground);
+    assert_eq!(body.velocity, [0.0; 3]);
+
+    // Flying doesn't fall
+    body.flying = true;
+    run(&mut body, &world, MoveInput { up: true, ..Default::default() }, 0.5);
+    let height = body.position[1];
+    run(&mut body, &world, MoveInput::default(), 1.0);
+    assert!(height > ground + 3.0 && body.position[1] == height);
+}
+
+#[test]
+fn walls_stop_and_ledges_take_a_jump() {
+    let (mut world, ground) = flat_world();
+    let y = ground as i32;
+    // A ledge one block high from x = 3 and a wall two blocks high at x = 3 further along z
+    for x in 3..10 {
+        world.place_block(BlockPos::new(x, y, 0), block::STONE);
+    }
+    world.place_block(BlockPos::new(3, y, 5), block::STONE);
+    world.place_block(BlockPos::new(3, y + 1, 5), block::STONE);
+
+    let east = MoveInput { walk: [1.0, 0.0], ..Default::default() };
+    let mut body = Body::new([0.5, ground, 0.5]);
+    run(&mut body, &world, MoveInput::default(), 0.1);
+    run(&mut body, &world, east, 1.0);
+    // Too high to step onto
+    assert!((body.position[0] - (3.0 - WIDTH / 2.0)).abs() < 1e-3, "at {:?}", body.position);
+    assert!((body.position[1] - ground).abs() < 1e-3);
+    body.step(&world, MoveInput { up: true, ..east });
+    run(&mut body, &world, east, 1.0);
+    assert!(body.position[0] > 3.5 && (body.position[1] - (ground + 1.0)).abs() < 1e-3, "at {:?}", body.position);
+
+    let mut body = Body::new([0.5, ground, 5.5]);
+    run(&mut body, &world, MoveInput::default(), 0.1);
+    run(&mut body, &world, east, 1.0);
+    assert!((body.position[0] - (3.0 - WIDTH / 2.0)).abs() < 1e-3, "at {:?}", body.position);
+    assert!((body.position[1] - ground).abs() < 1e-3);
+}
+
+#[test]
+fn jumps_clear_a_block() {
+    let (world, ground) = flat_world();
+    let mut body = Body::new([0.5, ground, 0.5]);
+    run(&mut body, &world, MoveInput::default(), 0.1);
+    let mut highest = ground;
+    body.step(&world, MoveInput { up: true, ..Default::default() });
+    for _ in 0..120 {
+        body.step(&world, MoveInput::default());
+        highest = highest.max(body.position[1]);
+    }
+    assert!(highest > ground + 1.0 && highest < ground + 1.5, "jumped to {}", highest);
+    assert!(body.on_ground && (body.position[1] - ground).abs() < 1e-3);
+}
@@ -0,0 +1,43 @@
+use bassicraft2::pos::{BlockPos, BlockRegion, ChunkPos, Direction, LocalPos};
+
+#[test]
+fn negative_blocks_are_in_negative_chunks() {
+    let pos = BlockPos::new(-1, 70, -17);
+    assert_eq!(pos.chunk(), ChunkPos::new(-1, -2));
+    assert_eq!(pos.local(), LocalPos::new(15, 70, 15));
+    assert_eq!(pos.chunk().block(pos.local()), pos);
+
+    let pos = BlockPos::new(16, 0, 0);
+    assert_eq!(pos.chunk(), ChunkPos::new(1, 0));
+    assert_eq!(pos.local(), LocalPos::new(0, 0, 0));
+}
+
+#[test]
+fn containing_floors_points() {
+    assert_eq!(BlockPos::containing([-0.5, 2.0, 3.9]), BlockPos::new(-1, 2, 3));
+}
+
+#[test]
+fn neighbors_are_one_step_away() {
+    let pos = BlockPos::new(0, 10, 0);
+    for direction in Direction::ALL {
+        assert_eq!(pos.neighbor(direction).neighbor(direction.opposite()), pos);
+    }
+    assert_eq!(pos.neighbors().count(), 6);
+    assert_eq!(ChunkPos::new(0, 0).neighbor(Direction::West), ChunkPos::new(-1, 0));
+}
+
+#[test]
+fn region_iterates_every_block() {
+    let region = BlockRegion::new(BlockPos::new(1, 5, -1), BlockPos::new(-1, 4, 1));
+    assert_eq!(region.min, BlockPos::new(-1, 4, -1));
+    assert_eq!(region.size(), [3, 2, 3]);
+    assert_eq!(region.iter().count(), 18);
+    assert!(region.iter().all(|pos| region.contains(pos)));
+    assert_eq!(region.chunks().collect::<Vec<_>>(), vec![
+        ChunkPos::new(-1, -1),
+        ChunkPos::new(-1, 0),
+        ChunkPos::new(0, -1),
+        ChunkPos::new(0, 0),
+    ]);
+}
@@ -0,0 +1,46 @@
+use bassicraft2::{
+    block,
+    chunk::WorldHeight,
+    generator::GeneratorSettings,
+    preview::{self, Region},
+    vox::ColorMapping,
+};
+
+fn flat_maps(cave_y: i32) -> preview::Maps {
+    let generator = GeneratorSettings::parse("flat:bedrock,2*dirt,grass_block").unwrap().build(1);
+    // Across the corner of four chunks
+    preview::render(generator.as_ref(), WorldHeight::default(), Region::around([0, 0], [20, 12]), cave_y, &ColorMapping::default())
+}
+
+#[test]
+fn maps_cover_the_region() {
+    let maps = flat_maps(0);
+    for image in [&maps.height, &maps.surface, &maps.biomes, &maps.caves] {
+        assert_eq!(image.dimensions(), (20, 12));
+    }
+    assert_eq!(Region::around([10, -10], [20, 12]), Region { min: [0, -16], size: [20, 12] });
+}
+
+#[test]
+fn flat_worlds_are_one_colour() {
+    let maps = flat_maps(-62);
+    let grass = ColorMapping::default().color_of(block::GRASS);
+    assert!(maps.surface.pixels().all(|pixel| pixel == maps.surface.get_pixel(0, 0)));
+    assert!(maps.height.pixels().all(|pixel| pixel == maps.height.get_pixel(0, 0)));
+    assert_eq!(maps.surface.get_pixel(5, 5).0, grass);
+    // The slice goes through the dirt, above it is open air
+    let dirt = ColorMapping::default().color_of(block::DIRT);
+    assert!(maps.caves.pixels().all(|pixel| pixel.0 == dirt));
+    assert!(flat_maps(0).caves.pixels().all(|pixel| pixel.0 == [255, 255, 255]));
+}
+
+#[test]
+fn biome_map_follows_the_generator() {
+    let generator = GeneratorSettings::Default.build(5);
+    let region = Region { min: [-200, 40], size: [64, 8] };
+    let maps = preview::render(generator.as_ref(), WorldHeight::default(), region, 0, &ColorMapping::default());
+    for (x, z) in [(0, 0), (31, 4), (63, 7)] {
+        let biome = generator.biome(region.min[0] + x as i32, region.min[1] + z as i32);
+        assert_eq!(maps.biomes.get_pixel(x, z).0, preview::biome_color(biome));
+    }
+}
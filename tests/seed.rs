@@ -0,0 +1,55 @@
+mod common;
+
+use bassicraft2::{
+    chunk::WorldHeight,
+    generator::{GeneratorSettings, NoiseGenerator, TerrainGenerator},
+    pos::ChunkPos,
+    seed,
+};
+
+#[test]
+fn numbers_are_used_as_they_are() {
+    assert_eq!(seed::parse("42"), 42);
+    assert_eq!(seed::parse(" -9000000000 "), -9_000_000_000);
+    assert_eq!(seed::parse("9223372036854775807"), i64::MAX);
+    assert_eq!(seed::parse("0x10"), seed::hash_text("0x10"));
+}
+
+#[test]
+fn text_hashes_the_same_everywhere() {
+    // FNV-1a, fixed so a text seed keeps its world across builds
+    assert_eq!(seed::hash_text("a") as u64, 0xaf63dc4c8601ec8c);
+    assert_eq!(seed::parse(" glacier "), seed::parse("glacier"));
+    assert_ne!(seed::parse("glacier"), seed::parse("Glacier"));
+}
+
+#[test]
+fn large_seeds_survive_a_save() {
+    assert_eq!(seed::noise_seed(0x1f6c2), 0x1f6c2);
+    assert_eq!(seed::noise_seed(u32::MAX as i64), u32::MAX);
+    assert_ne!(seed::noise_seed(5 + (1 << 32)), seed::noise_seed(5));
+    assert_ne!(seed::noise_seed(-1), seed::noise_seed(0));
+    assert_ne!(seed::noise_seed(-2), seed::noise_seed(1));
+    assert_ne!(seed::noise_seed(7 | (7 << 32)), seed::noise_seed(0));
+
+    let seed = seed::parse("glacier");
+    let world = common::small_world(seed, GeneratorSettings::Default);
+    assert_eq!(common::saved_world(world).seed, seed);
+}
+
+// FNV-1a over the block types of a chunk and the features it starts
+fn chunk_hash(seed: i64, pos: ChunkPos) -> u64 {
+    let generator = NoiseGenerator::default_terrain(seed::noise_seed(seed));
+    let blocks = common::generate(&generator, pos);
+    let features = generator.decorate(pos, WorldHeight::default(), &blocks);
+    let values = blocks.iter().flatten().flatten().copied()
+        .chain(features.iter().flat_map(|&(pos, block_type)| [pos.x as u32, pos.y as u32, pos.z as u32, block_type]));
+    values.flat_map(u32::to_le_bytes).fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[test]
+fn terrain_is_the_same_on_every_build() {
+    // If this changes, every existing world generates different terrain in its new chunks.
+    // Only update it when that's the point of the change.
+    assert_eq!(chunk_hash(seed::parse("glacier"), ChunkPos::new(1, -2)), 0xd8d60cf86d9ba16);
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use bassicraft2::{
+    block,
+    chunk::WorldHeight,
+    command::{self, Command},
+    fluid::Fluid,
+    generator::GeneratorSettings,
+    pos::BlockPos,
+    world::{World, WorldSettings},
+};
+
+#[test]
+fn spawn_is_on_dry_ground() {
+    for seed in [1, 2, 3, -40] {
+        let world = World::with_settings(WorldSettings { seed, radius: 2, ..Default::default() });
+        let spawn = world.spawn;
+        let ground = world.get_block_type(spawn.down()).unwrap();
+        assert!(block::properties(ground).solid && ground != block::LEAVES, "seed {} spawns on {}", seed, ground);
+        for pos in [spawn, spawn.up()] {
+            let block_type = world.get_block_type(pos).unwrap();
+            assert!(!block::properties(block_type).solid && Fluid::of(block_type).is_none(), "seed {} spawns in {}", seed, block_type);
+        }
+        assert!(spawn.x.abs() <= 32 && spawn.z.abs() <= 32);
+    }
+}
+
+#[test]
+fn void_worlds_spawn_on_the_platform() {
+    let world = World::with_settings(WorldSettings { seed: 1, radius: 1, generator: GeneratorSettings::Void, ..Default::default() });
+    assert_eq!(world.spawn, BlockPos::new(0, 64, 0));
+}
+
+#[test]
+fn saves_keep_the_spawn() {
+    let mut world = World::with_settings(WorldSettings { seed: 1, radius: 1, ..Default::default() });
+    world.spawn = BlockPos::new(5, 120, -7);
+    assert_eq!(World::from_saved(common::saved_world(world)).spawn, BlockPos::new(5, 120, -7));
+
+    assert_eq!(command::parse("/setworldspawn 1 2 -3").unwrap(), Command::SetSpawn(Some([1, 2, -3])));
+    assert_eq!(command::parse("setworldspawn").unwrap(), Command::SetSpawn(None));
+    assert!(command::parse("setworldspawn 1.5 2 3").is_err());
+    assert!(command::parse("setworldspawn 40000000 2 3").is_err());
+    let height = WorldHeight::default();
+    assert!(command::check_block_height(height.min_y, height).is_ok());
+    assert!(command::check_block_height(height.max_y(), height).is_err());
+}
+
+#[test]
+fn teleports_stay_in_the_world() {
+    assert_eq!(command::parse("tp 1.5 70 -3").unwrap(), Command::Teleport([1.5, 70.0, -3.0]));
+    for line in ["tp nan 70 0", "tp 0 inf 0", "tp 0 70 -inf", "tp 1e30 70 0", "tp 0 70 -30000100"] {
+        assert!(command::parse(line).is_err(), "{} was accepted", line);
+    }
+    let height = WorldHeight::default();
+    assert!(command::check_height(height.max_y() as f32, height).is_ok());
+    assert!(command::check_height(height.min_y as f32 - 0.5, height).is_err());
+    assert!(command::check_height(1e30, height).is_err());
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use bassicraft2::{
+    block,
+    chunk::WorldHeight,
+    generator::NoiseGenerator,
+    pos::ChunkPos,
+    structure::{self, Facing, Placement, StructureKind, StructureTemplate, Structures},
+    terrain::TerrainSettings,
+    water::DEFAULT_SEA_LEVEL,
+};
+
+use common::generate;
+
+#[test]
+fn templates_are_read_from_layers() {
+    let templates = StructureTemplate::builtin();
+    let hut = templates.iter().find(|template| template.name == "hut").unwrap();
+    assert_eq!(hut.size, [5, 5, 5]);
+    assert!(hut.blocks.contains(&([3, 1, 1], block::CRAFTING_TABLE)));
+    // The doorway is cleared, nothing is written where the layers have spaces
+    assert!(hut.blocks.contains(&([2, 1, 4], block::AIR)));
+    let ruin = templates.iter().find(|template| template.name == "ruin").unwrap();
+    assert!(!ruin.blocks.iter().any(|&(pos, _)| pos == [1, 2, 1]));
+
+    assert_eq!(hut.rotate([0, 0, 0], 1), [4, 0, 0]);
+    assert_eq!(hut.rotate([3, 1, 1], 2), [1, 1, 3]);
+    assert_eq!(Facing::North.rotated(1), Facing::East);
+    assert_eq!(Facing::West.opposite(), Facing::East);
+
+    let template = |layers: &str| format!(r#"{{ "name": "bad", "palette": {{ "S": "minecraft:stone" }}, "layers": [[{}]] }}"#, layers);
+    assert!(StructureTemplate::from_json(&template(r#""SS""#)).is_ok());
+    assert!(StructureTemplate::from_json(&template(r#""SS", "S""#)).is_err());
+    assert!(StructureTemplate::from_json(&template(r#""SX""#)).is_err());
+    assert!(TerrainSettings::from_json(r#"{ "structures": [{ "name": "a", "start": "castle", "spacing": 4, "separation": 1, "salt": 0, "placement": { "type": "surface" } }] }"#).is_err());
+}
+
+#[test]
+fn jigsaw_pieces_attach_around_the_start() {
+    let kinds = StructureKind::defaults();
+    let camp = kinds.iter().find(|kind| kind.name == "camp").unwrap();
+    let structures = Structures::new(2, kinds.clone(), Vec::new());
+    let height = WorldHeight::default();
+    let flat = |_, _| 70;
+    let pieces = structures.layout(camp, ChunkPos::new(3, -5), height, DEFAULT_SEA_LEVEL, &flat).unwrap();
+    // A building on each side of the center
+    assert_eq!(pieces.len(), 5);
+    assert_eq!(pieces[0].template.name, "camp_center");
+    assert_eq!(pieces[0].min.y, 70);
+    for (i, piece) in pieces.iter().enumerate() {
+        assert!(pieces[i + 1..].iter().all(|other| !other.overlaps(piece)));
+        assert_eq!(piece.min.y, 70);
+    }
+    for building in &pieces[1..] {
+        let door = building.at(building.template.jigsaws[0].pos);
+        let center = &pieces[0];
+        assert!(center.template.jigsaws.iter().any(|jigsaw| {
+            let [dx, dz] = jigsaw.facing.rotated(center.rotation).offset();
+            center.at(jigsaw.pos).offset(dx, 0, dz) == door
+        }));
+    }
+    // No camps under the sea
+    assert!(structures.layout(camp, ChunkPos::new(3, -5), height, DEFAULT_SEA_LEVEL, &|_, _| 50).is_none());
+}
+
+#[test]
+fn structures_are_generated_across_chunks() {
+    let generator = NoiseGenerator::default_terrain(12);
+    let settings = TerrainSettings::default();
+    let dungeon = settings.structures.iter().find(|kind| kind.name == "dungeon").unwrap();
+    assert!(matches!(dungeon.placement, Placement::Underground { .. }));
+    let structures = Structures::new(12, vec![dungeon.clone()], Vec::new());
+    let height = WorldHeight::default();
+
+    let start = structures.start_chunk(dungeon, 1, -1);
+    let pieces = structures.layout(dungeon, start, height, DEFAULT_SEA_LEVEL, &|_, _| 0).unwrap();
+    let room = &pieces[0];
+    let (min, max) = (room.min.chunk(), room.max().chunk());
+    for x in min.x..=max.x {
+        for z in min.z..=max.z {
+            let pos = ChunkPos::new(x, z);
+            let blocks = generate(&generator, pos);
+            for (at, block_type) in room.blocks().filter(|(at, _)| at.chunk() == pos) {
+                let local = at.local();
+                assert_eq!(blocks[local.x as usize][height.index(at.y).unwrap()][local.z as usize], block_type, "at {:?}", at);
+            }
+        }
+    }
+}
+
+#[test]
+fn underground_ranges_are_checked() {
+    let settings = TerrainSettings::default();
+    let dungeon = settings.structures.iter().find(|kind| kind.name == "dungeon").unwrap();
+    let with = |min_y, max_y| vec![StructureKind { placement: Placement::Underground { min_y, max_y }, ..dungeon.clone() }];
+    assert!(structure::check(&with(-48, 32), &[]).is_ok());
+    assert!(structure::check(&with(10, 10), &[]).is_ok());
+    for (min_y, max_y) in [(32, -48), (i32::MIN, i32::MAX), (0, 100_000), (-100_000, 0)] {
+        assert!(structure::check(&with(min_y, max_y), &[]).is_err(), "{}..{} was accepted", min_y, max_y);
+    }
+}
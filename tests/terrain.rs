@@ -0,0 +1,72 @@
+use bassicraft2::{
+    generator::{GeneratorSettings, NoiseGenerator},
+    terrain::{FractalNoise, NoiseKind, NoiseSettings, Spline, TerrainSettings},
+};
+
+#[test]
+fn splines_interpolate_and_clamp() {
+    let spline = Spline { points: vec![[-1.0, 0.0], [0.0, 1.0], [0.5, 1.0], [1.0, 3.0]] };
+    assert_eq!(spline.at(-2.0), 0.0);
+    assert_eq!(spline.at(-0.5), 0.5);
+    assert_eq!(spline.at(0.25), 1.0);
+    assert_eq!(spline.at(0.75), 2.0);
+    assert_eq!(spline.at(5.0), 3.0);
+}
+
+#[test]
+fn fractal_noise_stays_in_range() {
+    let fbm = FractalNoise::new(3, &NoiseSettings::default());
+    let ridged = FractalNoise::new(3, &NoiseSettings { kind: NoiseKind::Ridged, ..Default::default() });
+    for i in 0..2000 {
+        let (x, z) = (i as f64 * 7.3, i as f64 * -3.1);
+        assert!((-1.0..=1.0).contains(&fbm.get(x, z)));
+        assert!((0.0..=1.0).contains(&ridged.get(x, z)));
+    }
+}
+
+#[test]
+fn settings_files_fill_in_defaults() {
+    let settings = TerrainSettings::from_json(r#"{ "amplification": 2.0, "hills": { "octaves": 6 } }"#).unwrap();
+    assert_eq!(settings.amplification, 2.0);
+    assert_eq!(settings.hills.octaves, 6);
+    assert_eq!(settings.hills.scale, NoiseSettings::default().scale);
+    assert_eq!(settings.ridges, TerrainSettings::default().ridges);
+    assert!(TerrainSettings::from_json("{ \"hills\": 3 }").is_err());
+
+    let shipped = TerrainSettings::from_json(include_str!("../res/terrain.json")).unwrap();
+    assert_eq!(shipped, TerrainSettings::default());
+
+    let custom = GeneratorSettings::Custom(Box::new(settings));
+    assert_eq!(GeneratorSettings::parse(&custom.to_string()).unwrap(), custom);
+}
+
+#[test]
+fn settings_that_break_the_noise_are_rejected() {
+    for json in [
+        r#"{ "hills": { "octaves": 4000000000 } }"#,
+        r#"{ "hills": { "octaves": 0 } }"#,
+        r#"{ "ridges": { "scale": 0.0 } }"#,
+        r#"{ "warp": { "noise": { "lacunarity": -2.0 } } }"#,
+        r#"{ "caves": { "worm_radius": [1.0, 1000000.0] } }"#,
+        r#"{ "caves": { "worm_length": [10, 4000000000] } }"#,
+        r#"{ "ores": [{ "block": "minecraft:stone", "size": 100000, "count": 1, "min_y": 0, "max_y": 10, "distribution": "uniform" }] }"#,
+    ] {
+        assert!(TerrainSettings::from_json(json).is_err(), "{} was accepted", json);
+    }
+    assert!(GeneratorSettings::parse(r#"custom:{"hills":{"octaves":4000000000}}"#).is_err());
+}
+
+#[test]
+fn flat_shapes_give_flat_plains() {
+    let flat = TerrainSettings {
+        shape: Spline { points: vec![[-1.0, 0.0], [1.0, 0.0]] },
+        ridge_height: 0.0,
+        ..Default::default()
+    };
+    let generator = NoiseGenerator::new(5, flat);
+    let heights: Vec<i32> = (0..64).map(|x| generator.surface_height(x * 3, 0)).collect();
+    let default = NoiseGenerator::default_terrain(5);
+    let rolling: Vec<i32> = (0..64).map(|x| default.surface_height(x * 3, 0)).collect();
+    let spread = |heights: &[i32]| heights.iter().max().unwrap() - heights.iter().min().unwrap();
+    assert!(spread(&heights) < spread(&rolling));
+}
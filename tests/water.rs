@@ -0,0 +1,78 @@
+mod common;
+
+use bassicraft2::{
+    biome::{Biome, ClimateNoise},
+    block::{self, BlockType},
+    chunk::WorldHeight,
+    generator::NoiseGenerator,
+    pos::ChunkPos,
+    water::DEFAULT_SEA_LEVEL,
+};
+
+use common::generate;
+
+#[test]
+fn low_terrain_is_under_the_sea() {
+    let (generator, climate) = (NoiseGenerator::default_terrain(3), ClimateNoise::new(3));
+    let height = WorldHeight::default();
+    let sea = height.index(DEFAULT_SEA_LEVEL).unwrap();
+    let (mut seabeds, mut beaches) = (0, 0);
+    for x in (-1600..1600).step_by(160) {
+        for z in (-1600..1600).step_by(160) {
+            let surface = generator.surface_height(x, z);
+            let pos = ChunkPos::new(x.div_euclid(16), z.div_euclid(16));
+            let column = |blocks: &[Vec<Vec<BlockType>>], y: usize| blocks[x.rem_euclid(16) as usize][y][z.rem_euclid(16) as usize];
+            if surface < DEFAULT_SEA_LEVEL - 2 {
+                let blocks = generate(&generator, pos);
+                let ground = height.index(surface).unwrap();
+                assert_eq!(column(&blocks, ground), block::GRAVEL);
+                assert!((ground + 1..=sea).all(|y| column(&blocks, y) == block::WATER));
+                assert_eq!(column(&blocks, sea + 1), block::AIR);
+                seabeds += 1;
+            } else if surface <= DEFAULT_SEA_LEVEL + 1 && climate.biome(x, z) != Biome::Ocean {
+                let blocks = generate(&generator, pos);
+                assert_eq!(column(&blocks, height.index(surface).unwrap()), block::SAND);
+                beaches += 1;
+            } else {
+                assert!(surface > DEFAULT_SEA_LEVEL || climate.biome(x, z) == Biome::Ocean);
+            }
+        }
+    }
+    assert!(seabeds > 0 && beaches > 0, "{} sea floors, {} beaches", seabeds, beaches);
+}
+
+#[test]
+fn lakes_are_flat_and_held_in() {
+    let generator = NoiseGenerator::default_terrain(7);
+    let height = WorldHeight::default();
+    let mut lakes = 0;
+    for x in -6..6 {
+        for z in -6..6 {
+            let blocks = generate(&generator, ChunkPos::new(x, z));
+            let mut levels = Vec::new();
+            for (bx, plane) in blocks.iter().enumerate() {
+                for (i, row) in plane.iter().enumerate() {
+                    for (bz, &block_type) in row.iter().enumerate() {
+                        let y = height.min_y + i as i32;
+                        if block_type != block::WATER || y <= DEFAULT_SEA_LEVEL {
+                            continue;
+                        }
+                        if !levels.contains(&y) && plane[i + 1][bz] == block::AIR {
+                            levels.push(y);
+                        }
+                        // Nothing for the water to run out through
+                        assert_ne!(plane[i - 1][bz], block::AIR);
+                        for (nx, nz) in [(bx.wrapping_sub(1), bz), (bx + 1, bz), (bx, bz.wrapping_sub(1)), (bx, bz + 1)] {
+                            if let Some(neighbor) = blocks.get(nx).and_then(|plane| plane[i].get(nz)) {
+                                assert!(*neighbor != block::AIR, "water spills at {}, {}, {} in chunk {}, {}", bx, y, bz, x, z);
+                            }
+                        }
+                    }
+                }
+            }
+            assert!(levels.len() <= 1, "lake levels {:?} in chunk {}, {}", levels, x, z);
+            lakes += levels.len();
+        }
+    }
+    assert!(lakes > 0);
+}
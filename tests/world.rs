@@ -0,0 +1,239 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use bassicraft2::{
+    block,
+    chunk::WorldHeight,
+    edit::{PasteTransform, Selection},
+    events::{BlockChangeCause, WorldEvent},
+    fluid,
+    generator::GeneratorSettings,
+    nbt::{self, Tag},
+    pos::{BlockPos, ChunkPos},
+    save,
+    world::{World, WorldSettings},
+};
+
+use common::{save_round_trip_into, saved_world, small_world};
+
+// Terrain never gets this high, so tests can build in the air above it
+const SKY: i32 = 150;
+
+#[test]
+fn generates_without_a_gpu() {
+    let mut world = small_world(1, GeneratorSettings::Default);
+    assert_eq!(world.chunks.len(), 4);
+    assert!(world.chunks.iter().all(|chunk| chunk.mesh.num_elements > 0));
+    assert_eq!(world.take_remeshed_chunks().len(), 4);
+    assert_eq!(world.get_block_type(BlockPos::new(0, 0, 0)), Some(block::STONE));
+    assert_eq!(world.get_block_type(BlockPos::new(0, SKY, 0)), Some(block::AIR));
+    assert_eq!(world.get_block_type(BlockPos::new(100, 0, 0)), None);
+}
+
+#[test]
+fn blocks_go_below_zero() {
+    let mut world = small_world(1, GeneratorSettings::Default);
+    assert_eq!(world.get_block_type(BlockPos::new(0, -64, 0)), Some(block::STONE));
+    assert_eq!(world.get_block_type(BlockPos::new(0, -65, 0)), None);
+    assert_eq!(world.get_block_type(BlockPos::new(0, 319, 0)), Some(block::AIR));
+    assert_eq!(world.get_block_type(BlockPos::new(0, 320, 0)), None);
+
+    world.place_block(BlockPos::new(0, 319, 0), block::SAND);
+    world.run_ticks(400);
+    assert_eq!(world.get_block_type(BlockPos::new(0, 319, 0)), Some(block::AIR));
+    assert!(world.entities.is_empty());
+}
+
+#[test]
+fn height_is_configurable() {
+    let height = WorldHeight::new(32, 96);
+    let mut world = World::with_settings(WorldSettings { seed: 1, radius: 1, height, ..Default::default() });
+    assert_eq!(world.get_block_type(BlockPos::new(0, 31, 0)), None);
+    assert_eq!(world.get_block_type(BlockPos::new(0, 32, 0)), Some(block::STONE));
+    assert_eq!(world.get_block_type(BlockPos::new(0, 128, 0)), None);
+
+    world.place_block(BlockPos::new(0, 127, 0), block::GLASS);
+    let (_, restored) = save_round_trip_into(world, small_world(1, GeneratorSettings::Default));
+    let restored = restored.world();
+    assert_eq!(restored.height, height);
+    assert_eq!(restored.chunks.len(), 4);
+    assert_eq!(restored.get_block_type(BlockPos::new(0, 127, 0)), Some(block::GLASS));
+    assert_eq!(restored.get_block_type(BlockPos::new(0, -64, 0)), None);
+}
+
+#[test]
+fn break_place_undo_redo() {
+    let mut world = small_world(1, GeneratorSettings::Default);
+    world.take_remeshed_chunks();
+
+    world.place_block(BlockPos::new(3, SKY, 3), block::PLANKS);
+    assert_eq!(world.get_block_type(BlockPos::new(3, SKY, 3)), Some(block::PLANKS));
+    assert_eq!(world.take_remeshed_chunks(), vec![ChunkPos::new(0, 0)]);
+
+    world.break_block(BlockPos::new(3, SKY, 3));
+    assert_eq!(world.get_block_type(BlockPos::new(3, SKY, 3)), Some(block::AIR));
+
+    assert!(world.undo());
+    assert_eq!(world.get_block_type(BlockPos::new(3, SKY, 3)), Some(block::PLANKS));
+    assert!(world.undo());
+    assert_eq!(world.get_block_type(BlockPos::new(3, SKY, 3)), Some(block::AIR));
+    assert!(!world.undo());
+    assert!(world.redo());
+    assert_eq!(world.get_block_type(BlockPos::new(3, SKY, 3)), Some(block::PLANKS));
+}
+
+#[test]
+fn fill_copy_paste() {
+    let mut world = small_world(1, GeneratorSettings::Default);
+    let selection = Selection { pos1: Some(BlockPos::new(-2, SKY, -2)), pos2: Some(BlockPos::new(1, SKY + 1, 1)) };
+
+    assert_eq!(world.fill(&selection, block::COBBLESTONE), 32);
+    let copied = world.copy(&selection).unwrap();
+    world.paste(BlockPos::new(-2, SKY + 10, -2), &copied, &PasteTransform::default());
+    assert_eq!(world.get_block_type(BlockPos::new(1, SKY + 11, 1)), Some(block::COBBLESTONE));
+    assert_eq!(world.get_block_type(BlockPos::new(1, SKY + 12, 1)), Some(block::AIR));
+
+    assert!(world.undo());
+    assert_eq!(world.get_block_type(BlockPos::new(1, SKY + 11, 1)), Some(block::AIR));
+}
+
+#[test]
+fn hollow_keeps_the_shell_of_a_buried_cube() {
+    let mut world = small_world(1, GeneratorSettings::Default);
+    let ground = Selection { pos1: Some(BlockPos::new(-4, -20, -4)), pos2: Some(BlockPos::new(4, -12, 4)) };
+    world.fill(&ground, block::STONE);
+
+    let cube = Selection { pos1: Some(BlockPos::new(-2, -18, -2)), pos2: Some(BlockPos::new(2, -14, 2)) };
+    assert_eq!(world.hollow(&cube), 27);
+    assert_eq!(world.get_block_type(BlockPos::new(0, -16, 0)), Some(block::AIR));
+    assert_eq!(world.get_block_type(BlockPos::new(1, -15, -1)), Some(block::AIR));
+    for face in [BlockPos::new(2, -16, 0), BlockPos::new(0, -14, 0), BlockPos::new(0, -16, -2)] {
+        assert_eq!(world.get_block_type(face), Some(block::STONE));
+    }
+}
+
+#[test]
+fn events_reach_subscribers_after_a_tick() {
+    let mut world = small_world(1, GeneratorSettings::Default);
+    let changes = Arc::new(AtomicUsize::new(0));
+    let counter = changes.clone();
+    world.subscribe(move |event| {
+        if let WorldEvent::BlockChanged { .. } = event {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+
+    world.place_block(BlockPos::new(0, SKY, 0), block::PLANKS);
+    assert_eq!(changes.load(Ordering::Relaxed), 0);
+    world.run_ticks(1);
+    assert_eq!(changes.load(Ordering::Relaxed), 1);
+
+    // A server can run the world on its own thread, subscribers included
+    let world = std::thread::spawn(move || {
+        world.place_block(BlockPos::new(1, SKY, 0), block::PLANKS);
+        world.run_ticks(1);
+        world
+    }).join().unwrap();
+    assert_eq!(changes.load(Ordering::Relaxed), 2);
+    drop(world);
+}
+
+#[test]
+fn sand_falls_and_lands() {
+    let mut world = small_world(1, GeneratorSettings::Default);
+    world.set_blocks([(BlockPos::new(5, SKY, 5), block::STONE), (BlockPos::new(5, SKY + 10, 5), block::SAND)], BlockChangeCause::Edit);
+
+    world.run_ticks(100);
+    assert!(world.entities.is_empty());
+    assert_eq!(world.get_block_type(BlockPos::new(5, SKY + 10, 5)), Some(block::AIR));
+    assert_eq!(world.get_block_type(BlockPos::new(5, SKY + 1, 5)), Some(block::SAND));
+}
+
+#[test]
+fn water_spreads_on_a_floor() {
+    let mut world = small_world(1, GeneratorSettings::Default);
+    let floor = Selection { pos1: Some(BlockPos::new(-8, SKY, -8)), pos2: Some(BlockPos::new(7, SKY, 7)) };
+    world.fill(&floor, block::STONE);
+    world.place_block(BlockPos::new(0, SKY + 1, 0), block::WATER);
+
+    world.run_ticks(200);
+    let flowing = world.get_block_type(BlockPos::new(3, SKY + 1, 0)).unwrap();
+    assert_eq!(block::id_of(flowing), block::WATER);
+    assert_eq!(fluid::level(flowing), 3);
+    assert_eq!(world.get_block_type(BlockPos::new(0, SKY + 1, 7)).map(block::id_of), Some(block::WATER));
+    assert_eq!(world.get_block_type(BlockPos::new(0, SKY + 1, 8)).map(block::id_of), Some(block::AIR));
+}
+
+#[test]
+fn save_round_trip() {
+    let mut world = small_world(1, GeneratorSettings::Default);
+    world.place_block(BlockPos::new(2, SKY, 2), block::GLASS);
+    world.place_block(BlockPos::new(2, SKY + 5, 2), block::GRAVEL);
+    world.run_ticks(3);
+    assert_eq!(world.entities.len(), 1);
+
+    let other = small_world(7, GeneratorSettings::Default);
+    let (saved, mut restored) = save_round_trip_into(world, other);
+    let (world, restored) = (saved.world(), restored.world_mut());
+
+    assert_eq!(restored.seed, world.seed);
+    assert_eq!(restored.ticks.tick_count, world.ticks.tick_count);
+    assert_eq!(restored.get_block_type(BlockPos::new(2, SKY, 2)), Some(block::GLASS));
+    assert_eq!(restored.entities.len(), 1);
+    for chunk in &world.chunks {
+        let other = restored.chunks.iter().find(|c| c.pos == chunk.pos).unwrap();
+        assert!(chunk.blocks == other.blocks);
+    }
+
+    restored.run_ticks(100);
+    assert_eq!(restored.get_block_type(BlockPos::new(2, SKY + 1, 2)), Some(block::GRAVEL));
+}
+
+#[test]
+fn loading_replaces_the_whole_world() {
+    let mut world = small_world(1, GeneratorSettings::Default);
+    world.place_block(BlockPos::new(2, SKY, 2), block::GLASS);
+
+    let mut other = World::with_settings(WorldSettings { seed: 9, radius: 2, ..Default::default() });
+    other.place_block(BlockPos::new(-20, SKY, -20), block::PLANKS);
+    let unloaded = Arc::new(AtomicUsize::new(0));
+    let counter = unloaded.clone();
+    other.subscribe(move |event| {
+        if let WorldEvent::ChunkUnloaded { .. } = event {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+
+    other.restore(saved_world(world));
+    assert_eq!(other.seed, 1);
+    assert_eq!(other.chunks.len(), 4);
+    assert_eq!(other.get_block_type(BlockPos::new(2, SKY, 2)), Some(block::GLASS));
+    assert_eq!(other.get_block_type(BlockPos::new(-20, SKY, -20)), None);
+    assert!(!other.undo());
+
+    other.run_ticks(1);
+    assert_eq!(unloaded.load(Ordering::Relaxed), 16);
+}
+
+#[test]
+fn hostile_run_lengths_are_rejected() {
+    let chunk = Tag::Compound(HashMap::from([
+        ("Pos".to_string(), Tag::IntArray(vec![0, 0])),
+        ("Blocks".to_string(), Tag::IntArray(vec![i32::MAX, block::STONE as i32, i32::MAX, block::STONE as i32])),
+    ]));
+    let root = Tag::Compound(HashMap::from([
+        ("Version".to_string(), Tag::Int(1)),
+        ("Seed".to_string(), Tag::Long(1)),
+        ("TickCount".to_string(), Tag::Long(0)),
+        ("RandomState".to_string(), Tag::Long(1)),
+        ("Chunks".to_string(), Tag::List(vec![chunk])),
+    ]));
+    let bytes = nbt::write_compressed("Save", &root).unwrap();
+    let error = save::read(&bytes).err().unwrap();
+    assert!(format!("{:#}", error).contains("wrong number of blocks"));
+}